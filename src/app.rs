@@ -1,18 +1,48 @@
 use core::fmt;
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::fs;
 use std::io::{self, Write};
+use std::time::{Duration, Instant};
 use std::{
     fmt::{Display, Formatter},
     fs::File,
 };
 
 use indexmap::IndexMap;
-use ratatui::{crossterm::event::KeyCode, widgets::ListState};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyModifiers},
+    layout::{Position, Rect},
+    widgets::ListState,
+};
+
+use crate::convert::{convert_value, ConversionError};
+use crate::diff_view::DiffView;
+use crate::find_replace::{apply_matches, FindReplace, FindReplaceField, SearchScope};
+use crate::format::{is_datetime, FileFormat, FormatError};
+use crate::history::History;
+use crate::jsonc::{self, JsoncLayout};
+use crate::merge::{insert_after, MergeState};
+use crate::patch::{apply_patch, diff, parse_patch, PatchApply, PatchExport, PatchOperation};
+use crate::path::{
+    display_path, object_at_mut, path_from_keys, resolve_json_pointer, to_jq, to_json_path,
+    to_json_pointer, value_at_mut, PathSegment,
+};
+use crate::query::QueryView;
+use crate::records::{RecordError, RecordList};
+use crate::sort::{sort_pairs, SortOrder};
+use crate::text_editor::TextEditor;
 
 pub struct App {
     pub key_input: String,
     pub value_input: String,
     pub pairs: JsonData,
+    /// The document as it was loaded, which exported patches are computed against
+    pub original_pairs: JsonData,
+    /// The format the document was read in, and will be saved in
+    pub format: FileFormat,
+    /// The comments of a JSONC document, written back when it is saved
+    pub jsonc_layout: JsoncLayout,
     pub edit_popup_focus: Option<EditFocus>,
     pub exit_popup_focus: Option<ExitFocus>,
     pub available_bindings: Vec<ActionBinding>,
@@ -20,8 +50,67 @@ pub struct App {
     pub selected_value_type: JsonValueType,
     pub type_list_ui_state: ListState,
     pub type_list_open: bool,
-    pub target_delete_key: Option<String>,
+    pub target_delete_keys: Option<Vec<String>>,
     pub target_write_file: Option<String>,
+    pub help_open: bool,
+    pub help_scroll: u16,
+    pub status_message: Option<StatusMessage>,
+    /// The reason the last save attempt from the exit popup failed
+    pub exit_error: Option<String>,
+    /// Set once the user has been warned that the chosen format loses part of the document
+    pub lossy_save_confirmed: bool,
+    /// When set, saving from the exit popup skips writing to disk
+    pub dry_run: bool,
+    pub text_editor: Option<TextEditor>,
+    pub external_edit: Option<ExternalEdit>,
+    /// What the raw JSON editor's contents will replace when saved
+    pub raw_edit_target: Option<EditTarget>,
+    /// The keys leading from the root of the document to the object being viewed
+    pub current_path: Vec<String>,
+    /// The keys of the pairs whose values are being converted to another type
+    pub convert_target_keys: Option<Vec<String>>,
+    pub convert_list_ui_state: ListState,
+    /// Where the pairs list was last drawn, used to map mouse clicks to pairs
+    pub pairs_list_area: Rect,
+    /// The index of the pair currently being dragged with the mouse
+    pub dragged_pair_index: Option<usize>,
+    /// Whether the current drag has moved a pair yet, so that
+    /// a whole drag is recorded as a single change
+    pub drag_moved_pair: bool,
+    pub history: History<JsonData>,
+    pub sort_popup_open: bool,
+    pub sort_list_ui_state: ListState,
+    /// Whether sorting also applies to the objects nested in the current one
+    pub sort_recursive: bool,
+    /// When set, the whole document is sorted in this order when it is saved
+    pub sort_on_save: Option<SortOrder>,
+    /// The pairs most recently copied or cut
+    pub clipboard: Vec<(String, JsonValue)>,
+    /// Keys of the current object marked for bulk operations
+    pub marked_keys: HashSet<String>,
+    /// The index of the pair most recently marked, where range marking starts from
+    pub mark_anchor: Option<usize>,
+    pub find_replace: Option<FindReplace>,
+    /// Kept after leaving the query screen so the last query is shown when it is reopened
+    pub query: Option<QueryView>,
+    /// The ways of writing the selected node's path, as `(format name, path)`,
+    /// shown while choosing one to copy
+    pub copy_path_options: Option<Vec<(&'static str, String)>>,
+    pub copy_path_list_ui_state: ListState,
+    /// When set, the session's changes are also written here as a JSON Patch on save
+    pub patch_output_file: Option<String>,
+    pub patch_export: Option<PatchExport>,
+    pub patch_apply: Option<PatchApply>,
+    /// Set when comparing two files with the `diff` command
+    pub diff_view: Option<DiffView>,
+    /// Set while the conflicts left by the `merge` command are being resolved
+    pub merge: Option<MergeState>,
+    /// Set when a JSON Lines file is open, `pairs` then holds the record being edited
+    pub records: Option<RecordList>,
+    /// Whether copied pairs are also sent to the system clipboard with OSC 52
+    pub sync_system_clipboard: bool,
+    /// Text waiting to be sent to the system clipboard by the main loop
+    pub pending_system_clipboard: Option<String>,
     current_screen: AppScreen,
 }
 
@@ -32,63 +121,127 @@ impl App {
             JsonValueType::Number,
             JsonValueType::Boolean,
             JsonValueType::Null,
+            JsonValueType::Array,
+            JsonValueType::Object,
+            JsonValueType::Datetime,
         ]
     }
 
-    pub fn new(input_file_path: Option<String>) -> Result<App, AppError> {
-        let input_file_contents = input_file_path
-            .clone()
-            .map(fs::read_to_string)
-            .map(Result::ok)
-            .flatten();
-
-        if input_file_path.is_some() && input_file_contents.is_none() {
-            return Err(AppError::InputFileNotFound(input_file_path.unwrap()));
-        }
+    /// Opens a file in the given format, or the one its extension suggests
+    pub fn new(
+        input_file_path: Option<String>,
+        format: Option<FileFormat>,
+    ) -> Result<App, AppError> {
+        let format_given = format.is_some();
+        let mut format = format
+            .or_else(|| input_file_path.as_deref().map(FileFormat::from_path))
+            .unwrap_or(FileFormat::Json);
+        let mut jsonc_layout = JsoncLayout::default();
 
-        let parsed_data: Option<serde_json::Value> = input_file_contents
-            .map(|s| serde_json::from_str(s.as_str()))
-            .map(Result::ok)
-            .flatten();
+        // JSON Lines files can be far larger than memory, so they are never read whole
+        let records = match (&input_file_path, format) {
+            (Some(path), FileFormat::Ndjson) => Some(
+                RecordList::open(path).map_err(|_| AppError::InputFileNotFound(path.clone()))?,
+            ),
+            _ => None,
+        };
 
-        let data_read_opt: Option<JsonData> = match parsed_data {
-            None => Some(IndexMap::new()),
-            Some(serde_json::Value::Object(data)) => {
-                let mut ret = JsonData::new();
+        let input_file_contents = match records {
+            Some(_) => None,
+            None => input_file_path
+                .clone()
+                .and_then(|path| fs::read_to_string(path).ok()),
+        };
 
-                let parse_attempt: Result<(), JsonValueFromSerdeError> =
-                    data.into_iter().try_for_each(|(key, value)| {
-                        let json_value = JsonValue::from_serde(value)?;
-                        ret.insert(key, json_value);
-                        Ok(())
-                    });
+        if let (Some(path), None, None) = (&input_file_path, &input_file_contents, &records) {
+            return Err(AppError::InputFileNotFound(path.clone()));
+        }
 
-                if parse_attempt.is_err() {
-                    None
-                } else {
-                    Some(ret)
-                }
-            }
-            _ => None,
+        let data_read_res = match input_file_contents {
+            // an empty file is treated as an empty document
+            Some(contents) if contents.trim().is_empty() => Ok(IndexMap::new()),
+            Some(contents) => match format {
+                FileFormat::Jsonc => jsonc::parse(&contents).map(|(data, layout)| {
+                    jsonc_layout = layout;
+                    data
+                }),
+                _ => match format.parse(&contents) {
+                    // JSON files holding comments, like VS Code's settings.json, are edited as JSONC
+                    Err(e) if format == FileFormat::Json && !format_given => {
+                        match jsonc::parse(&contents) {
+                            Ok((data, layout)) => {
+                                format = FileFormat::Jsonc;
+                                jsonc_layout = layout;
+                                Ok(data)
+                            }
+                            Err(_) => Err(e),
+                        }
+                    }
+                    result => result,
+                },
+            },
+            None => Ok(IndexMap::new()),
         };
 
-        match data_read_opt {
-            None => Err(AppError::InvalidInputJson),
-            Some(data) => {
+        match data_read_res {
+            Err(e) => Err(AppError::InvalidInput(e)),
+            Ok(data) => {
                 let mut result = App {
                     key_input: String::new(),
                     value_input: String::new(),
+                    original_pairs: data.clone(),
                     pairs: data,
+                    format,
+                    jsonc_layout,
                     edit_popup_focus: None,
                     exit_popup_focus: None,
                     available_bindings: Vec::new(),
                     list_ui_state: ListState::default(),
-                    current_screen: AppScreen::Main,
+                    current_screen: if records.is_some() {
+                        AppScreen::Records
+                    } else {
+                        AppScreen::Main
+                    },
                     selected_value_type: JsonValueType::String,
                     type_list_ui_state: ListState::default(),
                     type_list_open: false,
-                    target_delete_key: None,
+                    target_delete_keys: None,
                     target_write_file: input_file_path,
+                    help_open: false,
+                    help_scroll: 0,
+                    status_message: None,
+                    exit_error: None,
+                    lossy_save_confirmed: false,
+                    dry_run: false,
+                    text_editor: None,
+                    external_edit: None,
+                    raw_edit_target: None,
+                    current_path: Vec::new(),
+                    convert_target_keys: None,
+                    convert_list_ui_state: ListState::default(),
+                    pairs_list_area: Rect::default(),
+                    dragged_pair_index: None,
+                    drag_moved_pair: false,
+                    history: History::default(),
+                    sort_popup_open: false,
+                    sort_list_ui_state: ListState::default(),
+                    sort_recursive: false,
+                    sort_on_save: None,
+                    clipboard: Vec::new(),
+                    marked_keys: HashSet::new(),
+                    mark_anchor: None,
+                    find_replace: None,
+                    query: None,
+                    copy_path_options: None,
+                    copy_path_list_ui_state: ListState::default(),
+                    patch_output_file: None,
+                    patch_export: None,
+                    patch_apply: None,
+                    diff_view: None,
+                    merge: None,
+                    records,
+                    sync_system_clipboard: false,
+                    pending_system_clipboard: None,
                 };
                 result.update_state();
 
@@ -108,17 +261,178 @@ impl App {
             }
             AppScreen::Exiting => {
                 self.exit_popup_focus = Some(ExitFocus::Input);
+                self.lossy_save_confirmed = false;
+                // conflicts may have been resolved since saving was last warned about
+                if let Some(merge) = &mut self.merge {
+                    merge.save_confirmed = false;
+                }
             }
             _ => {}
         }
         self.current_screen = new_screen;
     }
 
+    /// Show a transient message in the status bar, replacing any
+    /// message that is currently displayed
+    pub fn notify(&mut self, severity: Severity, text: impl Into<String>) {
+        self.status_message = Some(StatusMessage {
+            text: text.into(),
+            severity,
+            expires_at: Instant::now() + severity.display_duration(),
+        });
+    }
+
     pub fn update_state(&mut self) {
-        self.available_bindings = match self.current_screen {
+        if self
+            .status_message
+            .as_ref()
+            .is_some_and(|message| message.expires_at <= Instant::now())
+        {
+            self.status_message = None;
+        }
+
+        if let AppScreen::Editing = self.current_screen {
+            self.list_ui_state.select(None);
+
+            if self.type_list_open && self.type_list_ui_state.selected().is_none() {
+                self.type_list_ui_state.select_first();
+            }
+        }
+
+        self.available_bindings = if self.help_open {
+            vec![
+                (Binding::Static(KeyCode::Esc), InputAction::CloseHelp),
+                (Binding::Static(KeyCode::Char('?')), InputAction::CloseHelp),
+                (Binding::Static(KeyCode::Up), InputAction::HelpScrollUp),
+                (Binding::Static(KeyCode::Down), InputAction::HelpScrollDown),
+            ]
+        } else {
+            let mut result = self.screen_bindings();
+
+            // `?` would swallow a character the user is trying to type,
+            // so fall back to F1 whenever a text field is focused
+            let accepts_text_entry = result
+                .iter()
+                .any(|(binding, _)| matches!(binding, Binding::TextEntry));
+            let help_key = if accepts_text_entry {
+                KeyCode::F(1)
+            } else {
+                KeyCode::Char('?')
+            };
+            result.push((Binding::Static(help_key), InputAction::OpenHelp));
+
+            result
+        };
+    }
+
+    /// The bindings available on the current screen, ignoring the help overlay.
+    /// This is the single source of truth for both input handling and the help
+    /// overlay's listing.
+    pub fn screen_bindings(&self) -> Vec<ActionBinding> {
+        match self.current_screen {
             AppScreen::Main => {
-                let delete_modal_is_open = self.target_delete_key.is_some();
-                if delete_modal_is_open {
+                let delete_modal_is_open = self.target_delete_keys.is_some();
+                let external_edit_failed = self
+                    .external_edit
+                    .as_ref()
+                    .is_some_and(|edit| edit.error.is_some());
+
+                if self.merge.is_some() {
+                    vec![
+                        (
+                            Binding::Static(KeyCode::Char('o')),
+                            InputAction::MergeKeepOurs,
+                        ),
+                        (
+                            Binding::Static(KeyCode::Char('t')),
+                            InputAction::MergeTakeTheirs,
+                        ),
+                        (Binding::Static(KeyCode::Char('e')), InputAction::MergeEdit),
+                        (Binding::Static(KeyCode::Char('q')), InputAction::Quit),
+                    ]
+                } else if let Some(patch_apply) = &self.patch_apply {
+                    if patch_apply.preview.is_some() {
+                        vec![
+                            (
+                                Binding::Static(KeyCode::Enter),
+                                InputAction::PatchApplyConfirm,
+                            ),
+                            (Binding::Static(KeyCode::Esc), InputAction::PatchApplyBack),
+                            (Binding::Static(KeyCode::Up), InputAction::PatchApplyUp),
+                            (Binding::Static(KeyCode::Down), InputAction::PatchApplyDown),
+                        ]
+                    } else {
+                        vec![
+                            (Binding::Static(KeyCode::Enter), InputAction::PatchApplyLoad),
+                            (Binding::Static(KeyCode::Esc), InputAction::PatchApplyCancel),
+                            (
+                                Binding::Static(KeyCode::Backspace),
+                                InputAction::BackspaceFieldText(TextField::ApplyFile),
+                            ),
+                            (
+                                Binding::TextEntry,
+                                InputAction::EnterFieldText(TextField::ApplyFile),
+                            ),
+                        ]
+                    }
+                } else if self.patch_export.is_some() {
+                    vec![
+                        (
+                            Binding::Static(KeyCode::Enter),
+                            InputAction::PatchExportSubmit,
+                        ),
+                        (
+                            Binding::Static(KeyCode::Esc),
+                            InputAction::PatchExportCancel,
+                        ),
+                        (
+                            Binding::Static(KeyCode::Backspace),
+                            InputAction::BackspaceFieldText(TextField::PatchFile),
+                        ),
+                        (
+                            Binding::TextEntry,
+                            InputAction::EnterFieldText(TextField::PatchFile),
+                        ),
+                    ]
+                } else if self.copy_path_options.is_some() {
+                    vec![
+                        (Binding::Static(KeyCode::Enter), InputAction::CopyPathSubmit),
+                        (Binding::Static(KeyCode::Esc), InputAction::CopyPathCancel),
+                        (Binding::Static(KeyCode::Up), InputAction::CopyPathUp),
+                        (Binding::Static(KeyCode::Down), InputAction::CopyPathDown),
+                    ]
+                } else if let Some(find_replace) = &self.find_replace {
+                    Self::find_replace_bindings(find_replace)
+                } else if self.sort_popup_open {
+                    vec![
+                        (Binding::Static(KeyCode::Enter), InputAction::SortSubmit),
+                        (Binding::Static(KeyCode::Esc), InputAction::SortCancel),
+                        (Binding::Static(KeyCode::Up), InputAction::SortUp),
+                        (Binding::Static(KeyCode::Down), InputAction::SortDown),
+                        (
+                            Binding::Static(KeyCode::Tab),
+                            InputAction::SortToggleRecursive,
+                        ),
+                    ]
+                } else if self.convert_target_keys.is_some() {
+                    vec![
+                        (Binding::Static(KeyCode::Enter), InputAction::ConvertSubmit),
+                        (Binding::Static(KeyCode::Esc), InputAction::ConvertCancel),
+                        (Binding::Static(KeyCode::Up), InputAction::ConvertUp),
+                        (Binding::Static(KeyCode::Down), InputAction::ConvertDown),
+                    ]
+                } else if external_edit_failed {
+                    vec![
+                        (
+                            Binding::Static(KeyCode::Char('y')),
+                            InputAction::ExternalEditRetry,
+                        ),
+                        (
+                            Binding::Static(KeyCode::Char('n')),
+                            InputAction::ExternalEditDiscard,
+                        ),
+                    ]
+                } else if delete_modal_is_open {
                     vec![
                         (Binding::Static(KeyCode::Char('y')), InputAction::DeleteYes),
                         (Binding::Static(KeyCode::Char('n')), InputAction::DeleteNo),
@@ -131,31 +445,125 @@ impl App {
                         ),
                         (Binding::Static(KeyCode::Char('q')), InputAction::Quit),
                         (Binding::Static(KeyCode::Char('p')), InputAction::Preview),
+                        (
+                            Binding::Static(KeyCode::Char('E')),
+                            InputAction::OpenExternalEditor,
+                        ),
+                        (
+                            Binding::Static(KeyCode::Char('r')),
+                            InputAction::OpenRawEditor,
+                        ),
                     ];
 
-                    if !self.pairs.is_empty() && !delete_modal_is_open {
+                    if !self.current_path.is_empty() {
+                        result.push((Binding::Static(KeyCode::Left), InputAction::NavigateOut));
+                    } else if self.records.is_some() {
+                        result.push((Binding::Static(KeyCode::Left), InputAction::CloseRecord));
+                    }
+
+                    if self.history.can_undo() {
+                        result.push((Binding::Static(KeyCode::Char('u')), InputAction::Undo));
+                    }
+                    if self.history.can_redo() {
+                        result.push((
+                            Binding::Chord(KeyCode::Char('r'), KeyModifiers::CONTROL),
+                            InputAction::Redo,
+                        ));
+                    }
+
+                    if self.current_pairs().len() > 1 {
+                        result.push((
+                            Binding::Static(KeyCode::Char('s')),
+                            InputAction::OpenSortPopup,
+                        ));
+                    }
+
+                    if !self.current_pairs().is_empty() {
+                        result.push((
+                            Binding::Static(KeyCode::Char('f')),
+                            InputAction::OpenFindReplace,
+                        ));
+                    }
+                    result.push((Binding::Static(KeyCode::Char('/')), InputAction::OpenQuery));
+                    result.push((
+                        Binding::Static(KeyCode::Char('P')),
+                        InputAction::OpenCopyPath,
+                    ));
+                    // the patch would only cover the open record
+                    if self.records.is_none() {
+                        result.push((
+                            Binding::Static(KeyCode::Char('X')),
+                            InputAction::OpenPatchExport,
+                        ));
+                    }
+                    result.push((
+                        Binding::Static(KeyCode::Char('A')),
+                        InputAction::OpenPatchApply,
+                    ));
+
+                    if !self.current_pairs().is_empty() {
                         result.push((Binding::Static(KeyCode::Enter), InputAction::CursorSelect));
                         result.push((Binding::Static(KeyCode::Down), InputAction::CursorDown));
                         result.push((Binding::Static(KeyCode::Up), InputAction::CursorUp));
 
+                        if !self.marked_keys.is_empty() {
+                            result.push((Binding::Static(KeyCode::Esc), InputAction::ClearMarks));
+                        }
+                        result.push((
+                            Binding::Chord(KeyCode::Char('a'), KeyModifiers::CONTROL),
+                            InputAction::MarkAll,
+                        ));
+
                         if self.list_ui_state.selected().is_some() {
                             result.push((Binding::Static(KeyCode::Esc), InputAction::CursorCancel));
+                            result.push((
+                                Binding::Static(KeyCode::Char(' ')),
+                                InputAction::ToggleMark,
+                            ));
+                            if self.mark_anchor.is_some() {
+                                result.push((
+                                    Binding::Static(KeyCode::Char('V')),
+                                    InputAction::MarkRange,
+                                ));
+                            }
+                        }
+
+                        if !self.target_keys().is_empty() {
                             result.push((
                                 Binding::Static(KeyCode::Backspace),
                                 InputAction::RequestPairDelete,
                             ));
+                            result.push((
+                                Binding::Static(KeyCode::Char('c')),
+                                InputAction::RequestConvert,
+                            ));
+                            result.push((
+                                Binding::Chord(KeyCode::Up, KeyModifiers::SHIFT),
+                                InputAction::MovePairUp,
+                            ));
+                            result.push((
+                                Binding::Chord(KeyCode::Down, KeyModifiers::SHIFT),
+                                InputAction::MovePairDown,
+                            ));
+                            result
+                                .push((Binding::Static(KeyCode::Char('y')), InputAction::CopyPair));
+                            result
+                                .push((Binding::Static(KeyCode::Char('x')), InputAction::CutPair));
+                            result.push((
+                                Binding::Static(KeyCode::Char('d')),
+                                InputAction::DuplicatePair,
+                            ));
                         }
                     }
 
+                    if !self.clipboard.is_empty() {
+                        result.push((Binding::Static(KeyCode::Char('v')), InputAction::PastePair));
+                    }
+
                     result
                 }
             }
             AppScreen::Editing => {
-                self.list_ui_state.select(None);
-
-                if self.type_list_open && !self.type_list_ui_state.selected().is_some() {
-                    self.type_list_ui_state.select_first();
-                }
                 let mut result = vec![
                     (Binding::Static(KeyCode::Enter), InputAction::EditingSubmit),
                     (
@@ -180,11 +588,20 @@ impl App {
                             InputAction::EnterFieldText(TextField::Value),
                         ));
 
-                        if let JsonValueType::Boolean = self.selected_value_type {
-                            result.push((
-                                Binding::Static(KeyCode::Char('t')),
-                                InputAction::EditingBoolToggle,
-                            ));
+                        match self.selected_value_type {
+                            JsonValueType::Boolean => {
+                                result.push((
+                                    Binding::Static(KeyCode::Char('t')),
+                                    InputAction::EditingBoolToggle,
+                                ));
+                            }
+                            JsonValueType::String => {
+                                result.push((
+                                    Binding::Chord(KeyCode::Char('e'), KeyModifiers::CONTROL),
+                                    InputAction::OpenStringEditor,
+                                ));
+                            }
+                            _ => {}
                         }
                     }
                     Some(EditFocus::Key) => {
@@ -215,27 +632,1197 @@ impl App {
                     ),
                 ];
 
-                match self.exit_popup_focus {
-                    Some(ExitFocus::Input) => {
-                        result.push((
+                if let Some(ExitFocus::Input) = self.exit_popup_focus {
+                    result.push((
+                        Binding::Static(KeyCode::Backspace),
+                        InputAction::BackspaceFieldText(TextField::OutputFile),
+                    ));
+                    result.push((
+                        Binding::TextEntry,
+                        InputAction::EnterFieldText(TextField::OutputFile),
+                    ));
+                }
+
+                result
+            }
+            AppScreen::Preview => vec![(Binding::Static(KeyCode::Esc), InputAction::ExitPreview)],
+            AppScreen::Diff => {
+                let Some(diff_view) = &self.diff_view else {
+                    return Vec::new();
+                };
+
+                let mut result = vec![
+                    (Binding::Static(KeyCode::Char('q')), InputAction::DiffQuit),
+                    (Binding::Static(KeyCode::Tab), InputAction::DiffToggleSide),
+                    (Binding::Static(KeyCode::Char('w')), InputAction::DiffSave),
+                ];
+
+                if !diff_view.rows.is_empty() {
+                    result.push((Binding::Static(KeyCode::Up), InputAction::DiffUp));
+                    result.push((Binding::Static(KeyCode::Down), InputAction::DiffDown));
+                    result.push((
+                        Binding::Static(KeyCode::Char('n')),
+                        InputAction::DiffNextDifference,
+                    ));
+                }
+                if diff_view.selected_row().is_some() {
+                    result.push((
+                        Binding::Static(KeyCode::Char('c')),
+                        InputAction::DiffCopyAcross,
+                    ));
+                }
+                if diff_view.selected_row_is_object() {
+                    result.push((Binding::Static(KeyCode::Enter), InputAction::DiffEnter));
+                }
+                if !diff_view.path.is_empty() {
+                    result.push((Binding::Static(KeyCode::Left), InputAction::DiffLeave));
+                }
+                if diff_view.can_undo() {
+                    result.push((Binding::Static(KeyCode::Char('u')), InputAction::DiffUndo));
+                }
+                if diff_view.can_redo() {
+                    result.push((
+                        Binding::Chord(KeyCode::Char('r'), KeyModifiers::CONTROL),
+                        InputAction::DiffRedo,
+                    ));
+                }
+
+                result
+            }
+            AppScreen::Records => {
+                let Some(records) = &self.records else {
+                    return Vec::new();
+                };
+
+                if records.delete_requested {
+                    return vec![
+                        (
+                            Binding::Static(KeyCode::Char('y')),
+                            InputAction::RecordDeleteYes,
+                        ),
+                        (
+                            Binding::Static(KeyCode::Char('n')),
+                            InputAction::RecordDeleteNo,
+                        ),
+                    ];
+                }
+
+                let mut result = vec![
+                    (Binding::Static(KeyCode::Char('e')), InputAction::RecordAdd),
+                    (Binding::Static(KeyCode::Char('q')), InputAction::Quit),
+                ];
+                if records.selected.is_some() {
+                    result.extend([
+                        (Binding::Static(KeyCode::Enter), InputAction::RecordOpen),
+                        (Binding::Static(KeyCode::Up), InputAction::RecordUp),
+                        (Binding::Static(KeyCode::Down), InputAction::RecordDown),
+                        (
                             Binding::Static(KeyCode::Backspace),
-                            InputAction::BackspaceFieldText(TextField::OutputFile),
-                        ));
+                            InputAction::RecordDelete,
+                        ),
+                        (
+                            Binding::Chord(KeyCode::Up, KeyModifiers::SHIFT),
+                            InputAction::RecordMoveUp,
+                        ),
+                        (
+                            Binding::Chord(KeyCode::Down, KeyModifiers::SHIFT),
+                            InputAction::RecordMoveDown,
+                        ),
+                    ]);
+                }
+                if records.can_undo() {
+                    result.push((Binding::Static(KeyCode::Char('u')), InputAction::RecordUndo));
+                }
+                if records.can_redo() {
+                    result.push((
+                        Binding::Chord(KeyCode::Char('r'), KeyModifiers::CONTROL),
+                        InputAction::RecordRedo,
+                    ));
+                }
+                result
+            }
+            AppScreen::Query => {
+                let results_focused = self
+                    .query
+                    .as_ref()
+                    .is_some_and(|query| query.results_focused);
+
+                let mut result = vec![(Binding::Static(KeyCode::Esc), InputAction::CloseQuery)];
+                if results_focused {
+                    result.extend([
+                        (Binding::Static(KeyCode::Enter), InputAction::QueryJump),
+                        (Binding::Static(KeyCode::Char('e')), InputAction::QueryEdit),
+                        (Binding::Static(KeyCode::Tab), InputAction::QueryToggleFocus),
+                        (Binding::Static(KeyCode::Up), InputAction::QueryResultUp),
+                        (Binding::Static(KeyCode::Down), InputAction::QueryResultDown),
+                    ]);
+                } else {
+                    let has_results = self
+                        .query
+                        .as_ref()
+                        .is_some_and(|query| !query.results.is_empty());
+                    if has_results {
                         result.push((
-                            Binding::TextEntry,
-                            InputAction::EnterFieldText(TextField::OutputFile),
+                            Binding::Static(KeyCode::Enter),
+                            InputAction::QueryToggleFocus,
                         ));
+                        result.push((Binding::Static(KeyCode::Tab), InputAction::QueryToggleFocus));
                     }
-                    _ => {}
+                    result.push((
+                        Binding::Static(KeyCode::Backspace),
+                        InputAction::BackspaceFieldText(TextField::Query),
+                    ));
+                    result.push((
+                        Binding::TextEntry,
+                        InputAction::EnterFieldText(TextField::Query),
+                    ));
                 }
-
                 result
             }
-            AppScreen::Preview => vec![(Binding::Static(KeyCode::Esc), InputAction::ExitPreview)],
+            AppScreen::StringEditor => {
+                let mut result = vec![(
+                    Binding::Chord(KeyCode::Char('t'), KeyModifiers::CONTROL),
+                    InputAction::TextEditorToggleEscaped,
+                )];
+                result.extend(Self::text_editor_bindings());
+                result
+            }
+            AppScreen::RawEditor => {
+                let mut result = vec![(
+                    Binding::Chord(KeyCode::Char('g'), KeyModifiers::CONTROL),
+                    InputAction::TextEditorGotoError,
+                )];
+                result.extend(Self::text_editor_bindings());
+                result
+            }
+        }
+    }
+
+    /// Bindings shared by every full-screen text editor
+    fn text_editor_bindings() -> Vec<ActionBinding> {
+        vec![
+            (
+                Binding::Chord(KeyCode::Char('s'), KeyModifiers::CONTROL),
+                InputAction::TextEditorSave,
+            ),
+            (Binding::Static(KeyCode::Esc), InputAction::TextEditorCancel),
+            (
+                Binding::Static(KeyCode::Enter),
+                InputAction::TextEditorNewline,
+            ),
+            (Binding::Static(KeyCode::Up), InputAction::TextEditorUp),
+            (Binding::Static(KeyCode::Down), InputAction::TextEditorDown),
+            (Binding::Static(KeyCode::Left), InputAction::TextEditorLeft),
+            (
+                Binding::Static(KeyCode::Right),
+                InputAction::TextEditorRight,
+            ),
+            (Binding::Static(KeyCode::Home), InputAction::TextEditorHome),
+            (Binding::Static(KeyCode::End), InputAction::TextEditorEnd),
+            (
+                Binding::Static(KeyCode::Backspace),
+                InputAction::BackspaceFieldText(TextField::Editor),
+            ),
+            (
+                Binding::TextEntry,
+                InputAction::EnterFieldText(TextField::Editor),
+            ),
+        ]
+    }
+
+    /// The object currently being viewed in the main list
+    pub fn current_pairs(&self) -> &JsonData {
+        let mut pairs = &self.pairs;
+        for key in &self.current_path[..self.valid_path_depth()] {
+            if let Some(JsonValue::Object(child)) = pairs.get(key) {
+                pairs = child;
+            }
+        }
+        pairs
+    }
+
+    pub fn current_pairs_mut(&mut self) -> &mut JsonData {
+        let depth = self.valid_path_depth();
+        let mut pairs = &mut self.pairs;
+        for key in &self.current_path[..depth] {
+            pairs = match pairs.get_mut(key) {
+                Some(JsonValue::Object(child)) => child,
+                _ => unreachable!("`valid_path_depth` only counts keys that lead to objects"),
+            };
+        }
+        pairs
+    }
+
+    /// How many keys of `current_path` still lead to an object, the data
+    /// can change underneath the path (eg: when a whole document is replaced)
+    fn valid_path_depth(&self) -> usize {
+        let mut pairs = &self.pairs;
+        for (depth, key) in self.current_path.iter().enumerate() {
+            match pairs.get(key) {
+                Some(JsonValue::Object(child)) => pairs = child,
+                _ => return depth,
+            }
+        }
+        self.current_path.len()
+    }
+
+    /// Shows the contents of the object stored under `key` in the main list
+    pub fn enter_object(&mut self, key: String) {
+        self.clear_marks();
+        self.current_path.push(key);
+        self.list_ui_state
+            .select(if self.current_pairs().is_empty() {
+                None
+            } else {
+                Some(0)
+            });
+    }
+
+    /// Returns to the parent of the object being viewed, selecting the key it was stored under
+    pub fn leave_object(&mut self) {
+        self.clear_marks();
+        self.current_path.truncate(self.valid_path_depth());
+        if let Some(key) = self.current_path.pop() {
+            self.list_ui_state
+                .select(self.current_pairs().get_index_of(key.as_str()));
+        }
+    }
+
+    /// The screen that screens covering the whole document return to
+    pub fn home_screen(&self) -> AppScreen {
+        match &self.records {
+            Some(records) if records.open_record.is_none() => AppScreen::Records,
+            _ => AppScreen::Main,
+        }
+    }
+
+    /// Switches the format the document is saved in, giving the output file the
+    /// new format's extension. The records of a JSON Lines file are always saved as JSON Lines.
+    pub fn cycle_save_format(&mut self, offset: isize) {
+        if self.records.is_some() {
+            return;
+        }
+
+        let format = self.format.cycle(offset);
+        self.target_write_file = self
+            .target_write_file
+            .as_deref()
+            .map(|path| self.format.swap_extension(path, format));
+        self.format = format;
+        self.lossy_save_confirmed = false;
+    }
+
+    /// What saving in the chosen format would lose of the document as it was read, if anything
+    pub fn lossy_save_warning(&self) -> Option<String> {
+        let mut losses = Vec::new();
+        if self.format != FileFormat::Jsonc && self.jsonc_layout.has_comments() {
+            losses.push("drops the comments");
+        }
+        if self.format != FileFormat::Toml && self.pairs.values().any(JsonValue::holds_datetime) {
+            losses.push("writes the datetimes as strings");
+        }
+
+        if losses.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "Saving as {} {}, save again to save anyway",
+            self.format,
+            losses.join(" and ")
+        ))
+    }
+
+    /// Opens the selected record, objects in the main list and anything else in the raw editor
+    pub fn open_selected_record(&mut self) -> Result<(), RecordError> {
+        let Some(records) = &mut self.records else {
+            return Ok(());
+        };
+        let Some(index) = records.selected else {
+            return Ok(());
+        };
+
+        match records.read(index)? {
+            JsonValue::Object(data) => {
+                records.open_record = Some(index);
+                self.original_pairs = data.clone();
+                self.pairs = data;
+                self.history = History::default();
+                self.current_path.clear();
+                self.clear_marks();
+                self.list_ui_state
+                    .select(if self.pairs.is_empty() { None } else { Some(0) });
+                self.current_screen = AppScreen::Main;
+            }
+            value => {
+                let text = serde_json::to_string_pretty(&value).unwrap_or_default();
+                self.text_editor = Some(TextEditor::new(&text));
+                self.raw_edit_target = Some(EditTarget::Record(index));
+                self.current_screen = AppScreen::RawEditor;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies the record being edited into the list if it has changed
+    fn store_open_record(&mut self) {
+        let Some(records) = &mut self.records else {
+            return;
+        };
+        let Some(index) = records.open_record else {
+            return;
+        };
+
+        if self.pairs != self.original_pairs {
+            records.replace(index, JsonValue::Object(self.pairs.clone()));
+            self.original_pairs = self.pairs.clone();
+        }
+    }
+
+    /// Puts the record being edited back in the list and returns to it
+    pub fn close_record(&mut self) {
+        self.store_open_record();
+        if let Some(records) = &mut self.records {
+            records.open_record = None;
+        }
+
+        self.pairs = JsonData::new();
+        self.original_pairs = JsonData::new();
+        self.history = History::default();
+        self.current_path.clear();
+        self.clear_marks();
+        self.list_ui_state.select(None);
+        self.current_screen = AppScreen::Records;
+    }
+
+    fn find_replace_bindings(find_replace: &FindReplace) -> Vec<ActionBinding> {
+        if find_replace.matches.is_some() {
+            return vec![
+                (Binding::Static(KeyCode::Char('y')), InputAction::FindAccept),
+                (Binding::Static(KeyCode::Char('n')), InputAction::FindSkip),
+                (
+                    Binding::Static(KeyCode::Char('a')),
+                    InputAction::FindAcceptAll,
+                ),
+                (Binding::Static(KeyCode::Enter), InputAction::FindApply),
+                (Binding::Static(KeyCode::Esc), InputAction::FindBack),
+                (Binding::Static(KeyCode::Up), InputAction::FindMatchUp),
+                (Binding::Static(KeyCode::Down), InputAction::FindMatchDown),
+            ];
+        }
+
+        let field = match find_replace.focus {
+            FindReplaceField::Find => TextField::Find,
+            FindReplaceField::Replace => TextField::Replace,
+        };
+
+        vec![
+            (Binding::Static(KeyCode::Enter), InputAction::FindSearch),
+            (Binding::Static(KeyCode::Esc), InputAction::FindCancel),
+            (Binding::Static(KeyCode::Tab), InputAction::FindToggleField),
+            (Binding::Static(KeyCode::F(2)), InputAction::FindToggleRegex),
+            (Binding::Static(KeyCode::F(3)), InputAction::FindCycleTarget),
+            (Binding::Static(KeyCode::F(4)), InputAction::FindToggleScope),
+            (
+                Binding::Static(KeyCode::Backspace),
+                InputAction::BackspaceFieldText(field),
+            ),
+            (Binding::TextEntry, InputAction::EnterFieldText(field)),
+        ]
+    }
+
+    /// Searches with the find and replace popup's settings and starts reviewing the matches
+    pub fn run_find(&mut self) {
+        let Some(find_replace) = &self.find_replace else {
+            return;
+        };
+
+        let depth = self.valid_path_depth();
+        let result = match find_replace.scope {
+            SearchScope::Document => find_replace.find_matches(&self.pairs, &[]),
+            SearchScope::Subtree => find_replace.find_matches(
+                self.current_pairs(),
+                &path_from_keys(&self.current_path[..depth]),
+            ),
+        };
+
+        let Some(find_replace) = &mut self.find_replace else {
+            return;
+        };
+        match result {
+            Ok(matches) if matches.is_empty() => {
+                find_replace.error = Some("No matches found".to_string());
+            }
+            Ok(matches) => {
+                find_replace.error = None;
+                find_replace.matches = Some(matches);
+                find_replace.match_list_ui_state.select_first();
+            }
+            Err(e) => find_replace.error = Some(e.to_string()),
+        }
+    }
+
+    /// Accepts or skips the highlighted match and moves on to the next undecided one,
+    /// applying the accepted matches once every match has been decided
+    pub fn decide_find_match(&mut self, accept: bool) {
+        let Some(find_replace) = &mut self.find_replace else {
+            return;
+        };
+        let (Some(matches), Some(index)) = (
+            &mut find_replace.matches,
+            find_replace.match_list_ui_state.selected(),
+        ) else {
+            return;
+        };
+
+        if let Some(find_match) = matches.get_mut(index) {
+            find_match.accepted = Some(accept);
+        }
+
+        let next_undecided = (index + 1..matches.len())
+            .chain(0..index)
+            .find(|i| matches[*i].accepted.is_none());
+        match next_undecided {
+            Some(next_index) => find_replace.match_list_ui_state.select(Some(next_index)),
+            None => self.apply_find_replace(),
+        }
+    }
+
+    pub fn accept_all_find_matches(&mut self) {
+        if let Some(matches) = self
+            .find_replace
+            .as_mut()
+            .and_then(|find_replace| find_replace.matches.as_mut())
+        {
+            for find_match in matches.iter_mut().filter(|m| m.accepted.is_none()) {
+                find_match.accepted = Some(true);
+            }
+        }
+
+        self.apply_find_replace();
+    }
+
+    /// Replaces every accepted match as a single change and closes the popup
+    pub fn apply_find_replace(&mut self) {
+        let Some(matches) = self.find_replace.take().and_then(|f| f.matches) else {
+            return;
         };
+        if !matches.iter().any(|m| m.accepted == Some(true)) {
+            self.notify(Severity::Info, "Nothing was replaced");
+            return;
+        }
+
+        self.record_change();
+        let (replaced, collisions) = apply_matches(&mut self.pairs, &matches);
+        self.clear_marks();
+        self.clamp_selection();
+
+        if collisions.is_empty() {
+            self.notify(Severity::Info, format!("Replaced {replaced} matches"));
+        } else {
+            self.notify(
+                Severity::Warn,
+                format!(
+                    "Replaced {replaced} matches, could not rename {} as the new key already exists",
+                    collisions.join(", ")
+                ),
+            );
+        }
+    }
+
+    pub fn open_query(&mut self) {
+        let query = self.query.get_or_insert_with(QueryView::new);
+        query.refresh(&self.pairs);
+        self.current_screen = AppScreen::Query;
+    }
+
+    pub fn refresh_query(&mut self) {
+        if let Some(query) = &mut self.query {
+            query.refresh(&self.pairs);
+        }
+    }
+
+    /// Shows the selected query result in the main list. Values inside arrays
+    /// cannot be shown there, so the key of the array holding them is selected instead.
+    pub fn jump_to_query_result(&mut self) {
+        let Some(path) = self
+            .query
+            .as_ref()
+            .and_then(|query| query.selected_result())
+            .map(|result| result.path.clone())
+        else {
+            return;
+        };
+
+        self.reveal_path(path);
+        self.current_screen = AppScreen::Main;
+    }
+
+    /// Navigates the main list to the node at `path` and selects it. Values inside
+    /// arrays cannot be shown in the list, so the key of the array holding them is selected.
+    pub fn reveal_path(&mut self, path: Vec<PathSegment>) {
+        let mut keys: Vec<String> = path
+            .into_iter()
+            .map_while(|segment| match segment {
+                PathSegment::Key(key) => Some(key),
+                PathSegment::Index(_) => None,
+            })
+            .collect();
+        let selected_key = keys.pop();
+
+        self.clear_marks();
+        self.current_path = keys;
+        self.list_ui_state
+            .select(selected_key.and_then(|key| self.current_pairs().get_index_of(key.as_str())));
+    }
+
+    /// Opens the document with the node an RFC 6901 JSON Pointer refers to selected
+    pub fn goto_pointer(&mut self, pointer: &str) -> Result<(), AppError> {
+        let path = resolve_json_pointer(&self.pairs, pointer)
+            .ok_or_else(|| AppError::PointerNotFound(pointer.to_string()))?;
+        self.reveal_path(path);
+        Ok(())
+    }
+
+    /// The path from the document root to the selected pair,
+    /// or to the object being viewed if nothing is selected
+    fn selected_node_path(&self) -> Vec<PathSegment> {
+        let mut path = path_from_keys(&self.current_path[..self.valid_path_depth()]);
+        if let Some(key) = self.selected_key() {
+            path.push(PathSegment::Key(key));
+        }
+        path
+    }
+
+    pub fn open_copy_path_popup(&mut self) {
+        let path = self.selected_node_path();
+        self.copy_path_options = Some(vec![
+            ("JSON Pointer", to_json_pointer(&path)),
+            ("JSONPath", to_json_path(&path)),
+            ("jq", to_jq(&path)),
+        ]);
+        self.copy_path_list_ui_state.select_first();
+    }
+
+    /// Sends the highlighted path to the system clipboard and closes the popup
+    pub fn copy_highlighted_path(&mut self) {
+        let Some((format_name, path)) = self
+            .copy_path_options
+            .take()
+            .zip(self.copy_path_list_ui_state.selected())
+            .and_then(|(options, index)| options.into_iter().nth(index))
+        else {
+            return;
+        };
+
+        self.notify(Severity::Info, format!("Copied {format_name} {path}"));
+        self.pending_system_clipboard = Some(path);
+    }
+
+    /// Opens the selected query result in the raw JSON editor
+    pub fn edit_query_result(&mut self) -> Result<(), serde_json::Error> {
+        let Some(result) = self
+            .query
+            .as_ref()
+            .and_then(|query| query.selected_result())
+        else {
+            return Ok(());
+        };
+
+        let target = if result.path.is_empty() {
+            EditTarget::Document
+        } else {
+            EditTarget::Node(result.path.clone())
+        };
+        let text = serde_json::to_string_pretty(&result.value)?;
+
+        self.text_editor = Some(TextEditor::new(&text));
+        self.raw_edit_target = Some(target);
+        self.current_screen = AppScreen::RawEditor;
+
+        Ok(())
+    }
+
+    /// Saves a snapshot of the document so the change about to be made can be undone
+    pub fn record_change(&mut self) {
+        self.history.record(self.pairs.clone());
+    }
+
+    pub fn undo(&mut self) -> bool {
+        self.clear_marks();
+        let undone = self
+            .history
+            .undo(|snapshot| std::mem::replace(&mut self.pairs, snapshot));
+        self.clamp_selection();
+        undone
+    }
+
+    pub fn redo(&mut self) -> bool {
+        self.clear_marks();
+        let redone = self
+            .history
+            .redo(|snapshot| std::mem::replace(&mut self.pairs, snapshot));
+        self.clamp_selection();
+        redone
+    }
+
+    /// Keeps the selection inside the current object after its pairs have been replaced
+    fn clamp_selection(&mut self) {
+        let pair_count = self.current_pairs().len();
+        if let Some(selected_index) = self.list_ui_state.selected() {
+            self.list_ui_state.select(if pair_count == 0 {
+                None
+            } else {
+                Some(selected_index.min(pair_count - 1))
+            });
+        }
+    }
+
+    /// Sorts the current object in the order highlighted in the sort popup,
+    /// keeping the selected pair selected
+    pub fn apply_sort(&mut self) {
+        self.sort_popup_open = false;
+        let Some(order) = self
+            .sort_list_ui_state
+            .selected()
+            .and_then(|index| SortOrder::all().get(index).copied())
+        else {
+            return;
+        };
+
+        let selected_key = self.selected_key();
+        let recursive = self.sort_recursive;
+
+        self.record_change();
+        sort_pairs(self.current_pairs_mut(), order, recursive);
+
+        if let Some(key) = selected_key {
+            self.list_ui_state
+                .select(self.current_pairs().get_index_of(key.as_str()));
+        }
+        self.notify(
+            Severity::Info,
+            format!(
+                "Sorted by {order}{}",
+                if recursive {
+                    ", including nested objects"
+                } else {
+                    ""
+                }
+            ),
+        );
+    }
+
+    /// The keys that bulk operations apply to: the marked pairs if there are any,
+    /// otherwise the selected pair. Keys are in the order they appear in the object.
+    pub fn target_keys(&self) -> Vec<String> {
+        if self.marked_keys.is_empty() {
+            return self.selected_key().into_iter().collect();
+        }
+
+        self.current_pairs()
+            .keys()
+            .filter(|key| self.marked_keys.contains(*key))
+            .cloned()
+            .collect()
+    }
+
+    fn target_pairs(&self) -> Vec<(String, JsonValue)> {
+        let pairs = self.current_pairs();
+        self.target_keys()
+            .into_iter()
+            .filter_map(|key| pairs.get(&key).cloned().map(|value| (key, value)))
+            .collect()
+    }
+
+    pub fn toggle_mark(&mut self) {
+        let Some(key) = self.selected_key() else {
+            return;
+        };
+
+        if !self.marked_keys.remove(&key) {
+            self.marked_keys.insert(key);
+        }
+        self.mark_anchor = self.list_ui_state.selected();
+    }
+
+    /// Marks every pair between the anchor and the selected pair, inclusive
+    pub fn mark_range(&mut self) {
+        let (Some(anchor), Some(selected_index)) =
+            (self.mark_anchor, self.list_ui_state.selected())
+        else {
+            return;
+        };
+
+        let range = anchor.min(selected_index)..=anchor.max(selected_index);
+        let keys: Vec<String> = self.current_pairs().keys().cloned().collect();
+        self.marked_keys.extend(
+            keys.into_iter()
+                .enumerate()
+                .filter_map(|(index, key)| range.contains(&index).then_some(key)),
+        );
+        self.mark_anchor = Some(selected_index);
+    }
+
+    pub fn mark_all(&mut self) {
+        self.marked_keys = self.current_pairs().keys().cloned().collect();
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked_keys.clear();
+        self.mark_anchor = None;
+    }
+
+    /// Puts the target pairs on the clipboard, returning how many were copied
+    pub fn copy_target_pairs(&mut self) -> usize {
+        let copied_pairs = self.target_pairs();
+
+        if self.sync_system_clipboard && !copied_pairs.is_empty() {
+            let copied_data: JsonData = copied_pairs.iter().cloned().collect();
+            self.pending_system_clipboard = serde_json::to_string_pretty(&copied_data).ok();
+        }
+
+        let count = copied_pairs.len();
+        if count > 0 {
+            self.clipboard = copied_pairs;
+        }
+        count
+    }
+
+    /// Puts the target pairs on the clipboard and removes them,
+    /// returning how many were cut
+    pub fn cut_target_pairs(&mut self) -> usize {
+        let keys = self.target_keys();
+        if self.copy_target_pairs() > 0 {
+            self.remove_pairs(&keys);
+        }
+        keys.len()
+    }
+
+    /// Removes the given keys from the current object as a single change
+    pub fn remove_pairs(&mut self, keys: &[String]) {
+        self.record_change();
+        let pairs = self.current_pairs_mut();
+        for key in keys {
+            pairs.shift_remove(key.as_str());
+        }
+        self.clear_marks();
+        self.clamp_selection();
+    }
+
+    /// Inserts the clipboard's pairs after the selection, returning the keys they were stored under
+    pub fn paste_pairs(&mut self) -> Vec<String> {
+        if self.clipboard.is_empty() {
+            return Vec::new();
+        }
+
+        self.record_change();
+        self.clipboard
+            .clone()
+            .into_iter()
+            .map(|(key, value)| self.insert_pair_after_selection(key, value))
+            .collect()
+    }
+
+    /// Inserts a copy of each target pair directly after it,
+    /// returning the keys the copies were stored under
+    pub fn duplicate_target_pairs(&mut self) -> Vec<String> {
+        let target_pairs = self.target_pairs();
+        if target_pairs.is_empty() {
+            return Vec::new();
+        }
+
+        self.record_change();
+        target_pairs
+            .into_iter()
+            .map(|(key, value)| {
+                self.list_ui_state
+                    .select(self.current_pairs().get_index_of(key.as_str()));
+                self.insert_pair_after_selection(key, value)
+            })
+            .collect()
+    }
+
+    /// Moves every target pair one place up (`offset` of -1) or down (`offset`
+    /// of 1) as a single change. Nothing moves if any of them is already at the edge.
+    pub fn move_target_pairs(&mut self, offset: isize) {
+        let selected_key = self.selected_key();
+        let mut indexes: Vec<usize> = self
+            .target_keys()
+            .iter()
+            .filter_map(|key| self.current_pairs().get_index_of(key.as_str()))
+            .collect();
+        let pair_count = self.current_pairs().len();
+
+        let blocked = match offset {
+            -1 => indexes.first().is_none_or(|index| *index == 0),
+            _ => indexes.last().is_none_or(|index| *index + 1 >= pair_count),
+        };
+        if blocked {
+            return;
+        }
+
+        // move the pair nearest the edge first so pairs don't swap with each other
+        if offset > 0 {
+            indexes.reverse();
+        }
+
+        self.record_change();
+        let pairs = self.current_pairs_mut();
+        for index in indexes {
+            pairs.move_index(index, index.saturating_add_signed(offset));
+        }
+
+        if let Some(key) = selected_key {
+            self.list_ui_state
+                .select(self.current_pairs().get_index_of(key.as_str()));
+        }
+    }
+
+    /// Inserts a pair after the selected one (or at the end if nothing is selected)
+    /// and selects it. If the key is taken it is given a `_copy` suffix.
+    fn insert_pair_after_selection(&mut self, key: String, value: JsonValue) -> String {
+        let key = self.unused_key(key);
+        let index = match self.list_ui_state.selected() {
+            Some(selected_index) => (selected_index + 1).min(self.current_pairs().len()),
+            None => self.current_pairs().len(),
+        };
+
+        self.current_pairs_mut()
+            .shift_insert(index, key.clone(), value);
+        self.list_ui_state.select(Some(index));
+
+        key
+    }
+
+    /// `key` if it is not used in the current object, otherwise
+    /// the first of `key_copy`, `key_copy2`, `key_copy3`... that is free
+    fn unused_key(&self, key: String) -> String {
+        let pairs = self.current_pairs();
+        if !pairs.contains_key(&key) {
+            return key;
+        }
+
+        let copy_key = format!("{key}_copy");
+        let mut candidate = copy_key.clone();
+        let mut copy_number = 1;
+        while pairs.contains_key(&candidate) {
+            copy_number += 1;
+            candidate = format!("{copy_key}{copy_number}");
+        }
+
+        candidate
+    }
+
+    /// Moves the pair at index `from` of the current object to index `to`,
+    /// keeping it selected. Out of range indexes are ignored.
+    pub fn move_pair(&mut self, from: usize, to: usize) {
+        let pairs = self.current_pairs_mut();
+        if from >= pairs.len() || to >= pairs.len() {
+            return;
+        }
+
+        pairs.move_index(from, to);
+        self.list_ui_state.select(Some(to));
+    }
+
+    /// The index of the pair drawn at the given terminal position, if any
+    pub fn pair_index_at(&self, column: u16, row: u16) -> Option<usize> {
+        let area = self.pairs_list_area;
+        if !area.contains(Position { x: column, y: row }) {
+            return None;
+        }
+
+        let index = self.list_ui_state.offset() + (row - area.y) as usize;
+        (index < self.current_pairs().len()).then_some(index)
+    }
+
+    pub fn selected_key(&self) -> Option<String> {
+        self.list_ui_state
+            .selected()
+            .and_then(|index| self.current_pairs().get_index(index))
+            .map(|(key, _)| key.clone())
+    }
+
+    pub fn open_convert_popup(&mut self) {
+        let keys = self.target_keys();
+        if !keys.is_empty() {
+            self.convert_target_keys = Some(keys);
+            self.convert_list_ui_state.select_first();
+        }
+    }
+
+    /// The result of converting the targets of the convert popup to the
+    /// highlighted type, failing if any one of them cannot be converted
+    pub fn convert_preview(&self) -> Option<Result<Vec<(String, JsonValue)>, ConversionError>> {
+        let target_type = *Self::all_value_types().get(self.convert_list_ui_state.selected()?)?;
+        let pairs = self.current_pairs();
+
+        Some(
+            self.convert_target_keys
+                .as_ref()?
+                .iter()
+                .filter_map(|key| pairs.get(key).map(|value| (key, value)))
+                .map(|(key, value)| {
+                    convert_value(value, target_type).map(|converted| (key.clone(), converted))
+                })
+                .collect(),
+        )
+    }
+
+    pub fn apply_conversion(&mut self) -> Result<(), ConversionError> {
+        let Some(converted_pairs) = self.convert_preview().transpose()? else {
+            return Ok(());
+        };
+        self.convert_target_keys = None;
+
+        let message = match converted_pairs.as_slice() {
+            [] => return Ok(()),
+            [(key, converted)] => format!("Converted {key} to {}", converted.value_type()),
+            [(_, converted), ..] => format!(
+                "Converted {} values to {}",
+                converted_pairs.len(),
+                converted.value_type()
+            ),
+        };
+
+        self.record_change();
+        self.current_pairs_mut().extend(converted_pairs);
+        self.notify(Severity::Info, message);
+
+        Ok(())
+    }
+
+    /// The selected pair's value, or the whole document if nothing is
+    /// selected, along with its JSON source
+    fn selected_edit_target(&self) -> Result<(EditTarget, String), serde_json::Error> {
+        let selected_entry = self
+            .list_ui_state
+            .selected()
+            .and_then(|index| self.current_pairs().get_index(index));
+
+        match selected_entry {
+            Some((key, value)) => Ok((
+                EditTarget::Pair(key.clone()),
+                serde_json::to_string_pretty(value)?,
+            )),
+            None => Ok((
+                EditTarget::Document,
+                serde_json::to_string_pretty(&self.pairs)?,
+            )),
+        }
+    }
+
+    /// Replaces the target's data with an edit that has already been parsed,
+    /// returning a message describing the change
+    fn apply_edited_json(&mut self, target: &EditTarget, edited: EditedJson) -> String {
+        // records keep their own history of changes
+        if !matches!(target, EditTarget::Record(_)) {
+            self.record_change();
+        }
+
+        match (target, edited) {
+            (EditTarget::Pair(key), EditedJson::Value(mut value)) => {
+                if let Some(original) = self.current_pairs().get(key) {
+                    value.restore_datetimes(original);
+                }
+                self.current_pairs_mut().insert(key.clone(), value);
+                format!("Updated key {key}")
+            }
+            (EditTarget::Node(path), EditedJson::Value(mut value)) => {
+                if let Some(node) = value_at_mut(&mut self.pairs, path) {
+                    value.restore_datetimes(node);
+                    *node = value;
+                }
+                format!("Updated {}", to_json_path(path))
+            }
+            (_, EditedJson::Document(mut data)) => {
+                restore_datetimes(&mut data, &self.pairs);
+                self.pairs = data;
+                "Updated document".to_string()
+            }
+            (EditTarget::Conflict, EditedJson::Value(value)) => {
+                self.apply_merge_resolution(Some(value))
+            }
+            (EditTarget::Record(index), EditedJson::Value(value)) => {
+                if let Some(records) = &mut self.records {
+                    records.replace(*index, value);
+                }
+                format!("Updated record {}", index + 1)
+            }
+            (EditTarget::Document, EditedJson::Value(_)) => {
+                unreachable!("documents are always parsed into `EditedJson::Document`")
+            }
+        }
+    }
+
+    /// Queues the selected pair's value, or the whole document if nothing is
+    /// selected, to be opened in an external editor by the main loop
+    pub fn request_external_edit(&mut self) -> Result<(), serde_json::Error> {
+        let (target, text) = self.selected_edit_target()?;
+
+        self.external_edit = Some(ExternalEdit {
+            target,
+            text,
+            error: None,
+        });
+
+        Ok(())
+    }
+
+    /// Parses the text returned from the external editor back into the data.
+    /// On failure the edit is kept around with its error so it can be retried.
+    pub fn apply_external_edit(&mut self) {
+        let Some(edit) = self.external_edit.take() else {
+            return;
+        };
+
+        match parse_edited_json(&edit.target, &edit.text) {
+            Ok(edited) => {
+                let message = self.apply_edited_json(&edit.target, edited);
+                self.notify(Severity::Info, message);
+            }
+            Err(error) => {
+                self.external_edit = Some(ExternalEdit {
+                    error: Some(error.to_string()),
+                    ..edit
+                })
+            }
+        }
+    }
+
+    /// Opens the selected pair's value, or the whole document if nothing is
+    /// selected, as JSON source in the full-screen editor
+    pub fn open_raw_editor(&mut self) -> Result<(), serde_json::Error> {
+        let (target, text) = self.selected_edit_target()?;
+
+        self.text_editor = Some(TextEditor::new(&text));
+        self.raw_edit_target = Some(target);
+        self.current_screen = AppScreen::RawEditor;
+
+        Ok(())
+    }
+
+    /// Checks the raw editor's contents without applying them
+    pub fn validate_raw_editor(&self) -> Option<Result<EditedJson, EditedJsonError>> {
+        let editor = self.text_editor.as_ref()?;
+        let target = self.raw_edit_target.as_ref()?;
+
+        Some(parse_edited_json(target, &editor.text()))
+    }
+
+    /// Replaces the target of the raw editor with its contents, the editor
+    /// stays open if they are not valid
+    pub fn save_raw_editor(&mut self) -> Result<(), EditedJsonError> {
+        let (Some(editor), Some(target)) = (&self.text_editor, self.raw_edit_target.take()) else {
+            return Ok(());
+        };
+
+        match parse_edited_json(&target, &editor.text()) {
+            Ok(edited) => {
+                let message = self.apply_edited_json(&target, edited);
+                self.notify(Severity::Info, message);
+                // closing the editor needs the target to know which screen to return to
+                self.raw_edit_target = Some(target);
+                self.close_text_editor();
+                Ok(())
+            }
+            Err(e) => {
+                self.raw_edit_target = Some(target);
+                Err(e)
+            }
+        }
+    }
+
+    /// Moves the raw editor's cursor to the position of its syntax error
+    pub fn goto_raw_editor_error(&mut self) {
+        let Some(Err(EditedJsonError::Syntax(e))) = self.validate_raw_editor() else {
+            return;
+        };
+
+        if let Some(editor) = &mut self.text_editor {
+            // serde_json positions are one-based
+            editor.move_to(e.line().saturating_sub(1), e.column().saturating_sub(1));
+        }
+    }
+
+    /// Opens the full-screen editor for the string currently in the value field
+    pub fn open_string_editor(&mut self) {
+        self.text_editor = Some(TextEditor::new(&self.value_input));
+        self.current_screen = AppScreen::StringEditor;
+    }
+
+    /// Switches the string editor between showing the raw text and its
+    /// JSON-escaped form. Fails if the escaped form is not a valid JSON string.
+    pub fn toggle_editor_escaping(&mut self) -> Result<(), serde_json::Error> {
+        if let Some(editor) = &mut self.text_editor {
+            let converted_text = if editor.escaped {
+                serde_json::from_str::<String>(&editor.text())?
+            } else {
+                serde_json::to_string(&editor.text())?
+            };
+
+            let escaped = !editor.escaped;
+            *editor = TextEditor::new(&converted_text);
+            editor.escaped = escaped;
+        }
+
+        Ok(())
+    }
+
+    /// Copies the string editor's contents into the value field and
+    /// returns to the editing popup
+    pub fn save_string_editor(&mut self) -> Result<(), serde_json::Error> {
+        if let Some(editor) = &self.text_editor {
+            self.value_input = if editor.escaped {
+                serde_json::from_str::<String>(&editor.text())?
+            } else {
+                editor.text()
+            };
+        }
+
+        self.close_text_editor();
+        Ok(())
+    }
+
+    pub fn close_text_editor(&mut self) {
+        self.text_editor = None;
+
+        match self.current_screen {
+            AppScreen::RawEditor => {
+                // query results are edited from the query screen, so return there
+                self.current_screen = match self.raw_edit_target.take() {
+                    Some(EditTarget::Node(_)) => AppScreen::Query,
+                    Some(EditTarget::Record(_)) => AppScreen::Records,
+                    _ => AppScreen::Main,
+                };
+                self.refresh_query();
+            }
+            _ => {
+                // `goto_screen` would move focus back to the key field
+                self.current_screen = AppScreen::Editing;
+                self.edit_popup_focus = Some(EditFocus::Value);
+            }
+        }
     }
 
     pub fn select_value_type(&mut self, new_type: JsonValueType) {
+        // keep what has been typed if it still makes sense as the new type,
+        // a null has no content worth keeping
+        let converted = match self.selected_value_type {
+            JsonValueType::Null => None,
+            _ => self
+                .parse_value_input()
+                .ok()
+                .and_then(|value| convert_value(&value, new_type).ok()),
+        };
+        self.selected_value_type = new_type;
+
+        if let Some(value) = converted {
+            self.value_input = value.input_text();
+            return;
+        }
+
         match new_type {
             JsonValueType::Boolean => {
                 self.value_input = "false".to_string();
@@ -246,25 +1833,63 @@ impl App {
             JsonValueType::String => {
                 self.value_input = "".to_string();
             }
-            JsonValueType::Number => {
-                self.value_input = "".to_string();
+            JsonValueType::Number => {
+                self.value_input = "".to_string();
+            }
+            JsonValueType::Array => {
+                self.value_input = "[]".to_string();
+            }
+            JsonValueType::Object => {
+                self.value_input = "{}".to_string();
+            }
+            JsonValueType::Datetime => {
+                self.value_input = "".to_string();
+            }
+        }
+    }
+
+    /// Reads the value field as the selected type
+    pub fn parse_value_input(&self) -> Result<JsonValue, ValueInputError> {
+        match self.selected_value_type {
+            JsonValueType::Number => self
+                .value_input
+                .parse()
+                .map(JsonValue::Number)
+                .map_err(|_| ValueInputError::InvalidNumber(self.value_input.clone())),
+            JsonValueType::Boolean => self
+                .value_input
+                .parse()
+                .map(JsonValue::Boolean)
+                .map_err(|_| ValueInputError::InvalidBoolean(self.value_input.clone())),
+            JsonValueType::String => Ok(JsonValue::String(self.value_input.clone())),
+            JsonValueType::Datetime if is_datetime(&self.value_input) => {
+                Ok(JsonValue::Datetime(self.value_input.clone()))
+            }
+            JsonValueType::Datetime => {
+                Err(ValueInputError::InvalidDatetime(self.value_input.clone()))
+            }
+            JsonValueType::Null => Ok(JsonValue::Null),
+            JsonValueType::Array | JsonValueType::Object => {
+                let parsed = serde_json::from_str(&self.value_input)
+                    .map_err(|e| ValueInputError::InvalidJson(e.to_string()))?;
+                let value = JsonValue::from_serde(parsed);
+
+                if value.value_type() == self.selected_value_type {
+                    Ok(value)
+                } else {
+                    Err(ValueInputError::TypeMismatch(self.selected_value_type))
+                }
             }
         }
-        self.selected_value_type = new_type;
     }
 
-    pub fn save_key_value(&mut self) {
-        self.pairs.insert(
-            self.key_input.clone(),
-            match self.selected_value_type {
-                JsonValueType::Number => JsonValue::Number(self.value_input.parse().unwrap_or(0.0)),
-                JsonValueType::Boolean => {
-                    JsonValue::Boolean(self.value_input.parse().unwrap_or(false))
-                }
-                JsonValueType::String => JsonValue::String(self.value_input.clone()),
-                JsonValueType::Null => JsonValue::Null,
-            },
-        );
+    pub fn save_key_value(&mut self) -> Result<(), ValueInputError> {
+        let value = self.parse_value_input()?;
+        let key = self.key_input.clone();
+        self.record_change();
+        self.current_pairs_mut().insert(key, value);
+
+        Ok(())
     }
 
     pub fn clear_editing_state(&mut self) {
@@ -274,17 +1899,17 @@ impl App {
     }
 
     pub fn open_item_edit(&mut self, index: usize) -> Result<(), OpenItemEditError> {
-        match self.pairs.get_index(index) {
+        let entry = self
+            .current_pairs()
+            .get_index(index)
+            .map(|(key, json_value)| (key.clone(), json_value.clone()));
+
+        match entry {
             None => Err(OpenItemEditError::InvalidIndex(index)),
-            // Some(key, JsonValue::String(value)) => {}
             Some((key, json_value)) => {
-                self.key_input = key.clone();
-                self.value_input = match json_value {
-                    JsonValue::String(value) => value.clone(),
-                    JsonValue::Null => "null".to_string(),
-                    JsonValue::Boolean(value) => value.to_string(),
-                    JsonValue::Number(value) => value.to_string(),
-                };
+                self.key_input = key;
+                self.value_input = json_value.input_text();
+                self.selected_value_type = json_value.value_type();
                 self.goto_screen(AppScreen::Editing);
                 self.edit_popup_focus = Some(EditFocus::Value);
 
@@ -293,28 +1918,327 @@ impl App {
         }
     }
 
-    pub fn serialize(&self) -> serde_json::Result<String> {
-        serde_json::to_string(&self.pairs)
+    /// The JSON Patch turning the document as it was loaded into the one that will be saved
+    pub fn session_patch(&self) -> Vec<PatchOperation> {
+        diff(&self.original_pairs, &self.data_to_save())
     }
 
-    pub fn write(&self) -> Result<(), AppError> {
-        let serialized = self
-            .serialize()
+    /// Sets the file the session's changes are written to as a JSON Patch when saving.
+    /// The records of a JSON Lines file are edited one at a time, so they have no patch.
+    pub fn set_patch_output_file(&mut self, path: Option<String>) -> Result<(), AppError> {
+        if path.is_some() && self.records.is_some() {
+            return Err(AppError::PatchOfRecords);
+        }
+        self.patch_output_file = path;
+        Ok(())
+    }
+
+    /// Writes the session's changes as a JSON Patch, returning the number of operations
+    pub fn write_patch(&self, path: &str) -> Result<usize, AppError> {
+        let operations = self.session_patch();
+        let serialized = serde_json::to_string_pretty(&operations)
             .map_err(|e| AppError::UnableToSave(AppWriteError::Serde(e)))?;
 
-        match &self.target_write_file {
-            Some(path) => {
-                let mut file =
-                    File::create(path).map_err(|e| AppError::UnableToSave(AppWriteError::Io(e)))?;
+        fs::write(path, serialized).map_err(|e| AppError::UnableToSave(AppWriteError::Io(e)))?;
+
+        Ok(operations.len())
+    }
+
+    pub fn open_patch_export(&mut self) {
+        let output_file = match (&self.patch_output_file, &self.target_write_file) {
+            (Some(patch_file), _) => patch_file.clone(),
+            (None, Some(input_file)) => format!("{input_file}.patch.json"),
+            (None, None) => "changes.patch.json".to_string(),
+        };
+
+        self.patch_export = Some(PatchExport {
+            output_file,
+            operation_count: self.session_patch().len(),
+            error: None,
+        });
+    }
+
+    /// Writes the patch to the file chosen in the export popup, keeping
+    /// the popup open with the error if that fails
+    pub fn export_patch(&mut self) {
+        let Some(output_file) = self
+            .patch_export
+            .as_ref()
+            .map(|export| export.output_file.clone())
+        else {
+            return;
+        };
 
-                file.write_all(serialized.as_bytes())
-                    .map_err(|e| AppError::UnableToSave(AppWriteError::Io(e)))?;
+        match self.write_patch(&output_file) {
+            Ok(operation_count) => {
+                self.patch_export = None;
+                self.notify(
+                    Severity::Info,
+                    format!("Wrote {operation_count} patch operations to {output_file}"),
+                );
             }
-            _ => {}
+            Err(e) => {
+                if let Some(export) = &mut self.patch_export {
+                    export.error = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    pub fn open_patch_apply(&mut self) {
+        self.patch_apply = Some(PatchApply {
+            patch_file: String::new(),
+            preview: None,
+            preview_list_ui_state: ListState::default(),
+            error: None,
+        });
+    }
+
+    /// Reads the patch file chosen in the apply popup and applies it to a copy
+    /// of the document, so the changes can be reviewed before they are committed
+    pub fn preview_patch_apply(&mut self) {
+        let Some(patch_apply) = &self.patch_apply else {
+            return;
+        };
+
+        let result = fs::read_to_string(&patch_apply.patch_file)
+            .map_err(|e| format!("Failed to read {}: {e}", patch_apply.patch_file))
+            .and_then(|text| parse_patch(&text).map_err(|e| e.to_string()))
+            .and_then(|patch| apply_patch(&self.pairs, &patch).map_err(|e| e.to_string()));
+
+        let changes = result
+            .as_ref()
+            .map(|patched| diff(&self.pairs, patched))
+            .unwrap_or_default();
+
+        if let Some(patch_apply) = &mut self.patch_apply {
+            match result {
+                Ok(patched) => {
+                    patch_apply.error = None;
+                    patch_apply.preview = Some((patched, changes));
+                    patch_apply.preview_list_ui_state.select_first();
+                }
+                Err(e) => patch_apply.error = Some(e),
+            }
+        }
+    }
+
+    /// Replaces the document with the previewed result of the patch
+    pub fn confirm_patch_apply(&mut self) {
+        let Some((patched, changes)) = self
+            .patch_apply
+            .take()
+            .and_then(|patch_apply| patch_apply.preview)
+        else {
+            return;
+        };
+
+        self.record_change();
+        self.pairs = patched;
+        self.clear_marks();
+        self.clamp_selection();
+        self.notify(
+            Severity::Info,
+            format!("Applied patch with {} changes", changes.len()),
+        );
+    }
+
+    /// Resolves the conflict being shown with `value`, or by removing
+    /// the key if `value` is `None`, then moves on to the next conflict
+    pub fn resolve_merge_conflict(&mut self, value: Option<JsonValue>) {
+        let message = self.apply_merge_resolution(value);
+        self.notify(Severity::Info, message);
+    }
+
+    /// Applies a resolution, returning a message describing it. Undoing it would
+    /// bring back our value without showing the conflict again, so the history
+    /// is cleared instead of recording it.
+    fn apply_merge_resolution(&mut self, value: Option<JsonValue>) -> String {
+        let Some(merge) = &mut self.merge else {
+            return String::new();
+        };
+        let Some(conflict) = merge.conflicts.get(merge.current) else {
+            return String::new();
+        };
+        let path = conflict.path.clone();
+        let preceding_keys = conflict.preceding_keys.clone();
+
+        merge.current += 1;
+        let message = if merge.current_conflict().is_some() {
+            format!("Resolved conflict at {}", display_path(&path))
+        } else {
+            let conflict_count = merge.conflicts.len();
+            self.merge = None;
+            format!("Resolved all {conflict_count} conflicts, quit to save the result")
+        };
+
+        if let Some((PathSegment::Key(key), parent_path)) = path.split_last() {
+            if let Some(parent) = object_at_mut(&mut self.pairs, parent_path) {
+                match value {
+                    Some(value) => insert_after(parent, key.clone(), value, &preceding_keys),
+                    None => {
+                        parent.shift_remove(key);
+                    }
+                }
+            }
+        }
+        self.history = History::default();
+        self.clamp_selection();
+
+        message
+    }
+
+    /// Opens the value of the conflict being shown in the raw JSON editor,
+    /// starting from our value, or theirs if we removed the key
+    pub fn edit_merge_conflict(&mut self) -> Result<(), serde_json::Error> {
+        let Some(conflict) = self
+            .merge
+            .as_ref()
+            .and_then(|merge| merge.current_conflict())
+        else {
+            return Ok(());
         };
 
+        let value = conflict
+            .ours
+            .as_ref()
+            .or(conflict.theirs.as_ref())
+            .unwrap_or(&JsonValue::Null);
+        let text = serde_json::to_string_pretty(value)?;
+
+        self.text_editor = Some(TextEditor::new(&text));
+        self.raw_edit_target = Some(EditTarget::Conflict);
+        self.current_screen = AppScreen::RawEditor;
+
         Ok(())
     }
+
+    /// The document as it will be written to disk
+    pub fn data_to_save(&self) -> Cow<'_, JsonData> {
+        match self.sort_on_save {
+            Some(order) => {
+                let mut sorted = self.pairs.clone();
+                sort_pairs(&mut sorted, order, true);
+                Cow::Owned(sorted)
+            }
+            None => Cow::Borrowed(&self.pairs),
+        }
+    }
+
+    pub fn serialize(&self) -> Result<String, FormatError> {
+        self.format
+            .serialize_with_layout(&self.data_to_save(), &self.jsonc_layout)
+    }
+
+    /// The document as the Preview screen shows it
+    pub fn preview(&self) -> Result<String, FormatError> {
+        match self.format {
+            FileFormat::Jsonc => self.serialize(),
+            format => format.preview(&self.data_to_save()),
+        }
+    }
+
+    /// Writes the data to `target_write_file`, returning the number of bytes written
+    pub fn write(&mut self) -> Result<usize, AppError> {
+        if self.records.is_some() {
+            return self.write_records();
+        }
+
+        let serialized = self
+            .serialize()
+            .map_err(|e| AppError::UnableToSave(AppWriteError::Format(e)))?;
+
+        let path = self
+            .target_write_file
+            .as_ref()
+            .ok_or(AppError::UnableToSave(AppWriteError::MissingPath))?;
+
+        let mut file =
+            File::create(path).map_err(|e| AppError::UnableToSave(AppWriteError::Io(e)))?;
+
+        file.write_all(serialized.as_bytes())
+            .map_err(|e| AppError::UnableToSave(AppWriteError::Io(e)))?;
+
+        Ok(serialized.len())
+    }
+
+    fn write_records(&mut self) -> Result<usize, AppError> {
+        self.store_open_record();
+
+        let (Some(records), Some(path)) = (&mut self.records, &self.target_write_file) else {
+            return Err(AppError::UnableToSave(AppWriteError::MissingPath));
+        };
+        records
+            .check_numbers()
+            .map_err(|e| AppError::UnableToSave(AppWriteError::Format(e)))?;
+        records
+            .save(path)
+            .map_err(|e| AppError::UnableToSave(AppWriteError::Io(e)))
+    }
+}
+
+/// The part of the data replaced by a JSON edit
+pub enum EditTarget {
+    Pair(String),
+    Document,
+    /// A value anywhere in the document, eg: a query result inside an array
+    Node(Vec<PathSegment>),
+    /// The merge conflict currently being resolved
+    Conflict,
+    /// A record of a JSON Lines file that is not an object
+    Record(usize),
+}
+
+/// JSON source that has been parsed for an `EditTarget`
+pub enum EditedJson {
+    Value(JsonValue),
+    Document(JsonData),
+}
+
+#[derive(Debug)]
+pub enum EditedJsonError {
+    Syntax(serde_json::Error),
+    DocumentNotAnObject,
+}
+
+impl Display for EditedJsonError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            EditedJsonError::Syntax(e) => write!(f, "{e}"),
+            EditedJsonError::DocumentNotAnObject => write!(f, "The document must be a JSON object"),
+        }
+    }
+}
+
+impl std::error::Error for EditedJsonError {}
+
+/// Parses JSON source for the given target, applying the same
+/// conversion that is used when loading a file
+pub fn parse_edited_json(target: &EditTarget, text: &str) -> Result<EditedJson, EditedJsonError> {
+    let parsed: serde_json::Value = serde_json::from_str(text).map_err(EditedJsonError::Syntax)?;
+
+    match (target, parsed) {
+        (
+            EditTarget::Pair(_)
+            | EditTarget::Node(_)
+            | EditTarget::Conflict
+            | EditTarget::Record(_),
+            value,
+        ) => Ok(EditedJson::Value(JsonValue::from_serde(value))),
+        (EditTarget::Document, serde_json::Value::Object(data)) => {
+            Ok(EditedJson::Document(json_data_from_serde(data)))
+        }
+        (EditTarget::Document, _) => Err(EditedJsonError::DocumentNotAnObject),
+    }
+}
+
+/// An edit being made in the user's `$EDITOR`
+pub struct ExternalEdit {
+    pub target: EditTarget,
+    /// The text the editor was last opened with, or returned
+    pub text: String,
+    /// Why the returned text could not be applied
+    pub error: Option<String>,
 }
 
 pub enum AppScreen {
@@ -322,6 +2246,12 @@ pub enum AppScreen {
     Editing,
     Exiting,
     Preview,
+    StringEditor,
+    RawEditor,
+    Query,
+    Diff,
+    /// The records of a JSON Lines file
+    Records,
 }
 
 pub enum EditFocus {
@@ -333,6 +2263,7 @@ pub enum EditFocus {
 #[derive(Clone, Copy)]
 pub enum ExitFocus {
     Input,
+    Format,
     Positive,
     Negative,
 }
@@ -342,6 +2273,12 @@ pub enum TextField {
     Key,
     Value,
     OutputFile,
+    Editor,
+    Find,
+    Replace,
+    Query,
+    PatchFile,
+    ApplyFile,
 }
 
 #[derive(Clone, Copy)]
@@ -371,13 +2308,155 @@ pub enum InputAction {
     DeleteNo,
     ExitPreview,
     Preview,
+    OpenHelp,
+    CloseHelp,
+    HelpScrollUp,
+    HelpScrollDown,
+    OpenStringEditor,
+    OpenExternalEditor,
+    OpenRawEditor,
+    NavigateOut,
+    RequestConvert,
+    ConvertUp,
+    ConvertDown,
+    ConvertSubmit,
+    ConvertCancel,
+    MovePairUp,
+    MovePairDown,
+    Undo,
+    Redo,
+    OpenSortPopup,
+    SortUp,
+    SortDown,
+    SortToggleRecursive,
+    SortSubmit,
+    SortCancel,
+    CopyPair,
+    CutPair,
+    PastePair,
+    DuplicatePair,
+    ToggleMark,
+    MarkRange,
+    MarkAll,
+    ClearMarks,
+    OpenFindReplace,
+    FindToggleField,
+    FindToggleRegex,
+    FindCycleTarget,
+    FindToggleScope,
+    FindSearch,
+    FindCancel,
+    FindMatchUp,
+    FindMatchDown,
+    FindAccept,
+    FindSkip,
+    FindAcceptAll,
+    FindApply,
+    FindBack,
+    OpenQuery,
+    CloseQuery,
+    QueryToggleFocus,
+    QueryResultUp,
+    QueryResultDown,
+    QueryJump,
+    QueryEdit,
+    OpenCopyPath,
+    CopyPathUp,
+    CopyPathDown,
+    CopyPathSubmit,
+    CopyPathCancel,
+    OpenPatchExport,
+    PatchExportSubmit,
+    PatchExportCancel,
+    OpenPatchApply,
+    PatchApplyLoad,
+    PatchApplyConfirm,
+    PatchApplyBack,
+    PatchApplyCancel,
+    PatchApplyUp,
+    PatchApplyDown,
+    DiffUp,
+    DiffDown,
+    DiffNextDifference,
+    DiffToggleSide,
+    DiffEnter,
+    DiffLeave,
+    DiffCopyAcross,
+    DiffUndo,
+    DiffRedo,
+    DiffSave,
+    DiffQuit,
+    RecordUp,
+    RecordDown,
+    RecordOpen,
+    CloseRecord,
+    RecordAdd,
+    RecordDelete,
+    RecordDeleteYes,
+    RecordDeleteNo,
+    RecordMoveUp,
+    RecordMoveDown,
+    RecordUndo,
+    RecordRedo,
+    MergeKeepOurs,
+    MergeTakeTheirs,
+    MergeEdit,
+    TextEditorGotoError,
+    ExternalEditRetry,
+    ExternalEditDiscard,
+    TextEditorSave,
+    TextEditorCancel,
+    TextEditorToggleEscaped,
+    TextEditorNewline,
+    TextEditorUp,
+    TextEditorDown,
+    TextEditorLeft,
+    TextEditorRight,
+    TextEditorHome,
+    TextEditorEnd,
     EnterFieldText(TextField),
     BackspaceFieldText(TextField),
 }
 
+/// Used to group actions in the help overlay
+#[derive(Clone, Copy, PartialEq)]
+pub enum ActionCategory {
+    Navigation,
+    Editing,
+    Confirmation,
+    General,
+}
+
+impl ActionCategory {
+    /// The order in which categories are listed in the help overlay
+    pub fn all() -> Vec<ActionCategory> {
+        vec![
+            ActionCategory::Navigation,
+            ActionCategory::Editing,
+            ActionCategory::Confirmation,
+            ActionCategory::General,
+        ]
+    }
+}
+
+impl Display for ActionCategory {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ActionCategory::Navigation => write!(f, "Navigation"),
+            ActionCategory::Editing => write!(f, "Editing"),
+            ActionCategory::Confirmation => write!(f, "Confirmation"),
+            ActionCategory::General => write!(f, "General"),
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum Binding {
+    /// A key pressed without Control or Alt, Shift being part of the key itself
     Static(KeyCode),
+    /// A key that must be pressed with exactly the given modifiers,
+    /// takes priority over `Static` bindings for the same key
+    Chord(KeyCode, KeyModifiers),
     TextEntry,
 }
 
@@ -385,6 +2464,7 @@ impl Display for Binding {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
             Binding::Static(key_code) => write!(f, "{key_code}"),
+            Binding::Chord(key_code, modifiers) => write!(f, "{modifiers}+{key_code}"),
             Binding::TextEntry => write!(f, "Text Entry"),
         }
     }
@@ -408,9 +2488,404 @@ impl InputAction {
             InputAction::DeleteNo => Some("no"),
             InputAction::ExitPreview => Some("exit"),
             InputAction::Preview => Some("preview"),
+            InputAction::OpenHelp => Some("help"),
+            InputAction::CloseHelp => Some("close"),
+            InputAction::HelpScrollUp => Some("up"),
+            InputAction::HelpScrollDown => Some("down"),
+            InputAction::OpenStringEditor => Some("expand"),
+            InputAction::OpenExternalEditor => Some("$EDITOR"),
+            InputAction::OpenRawEditor => Some("raw JSON"),
+            InputAction::NavigateOut => Some("back"),
+            InputAction::RequestConvert => Some("convert"),
+            InputAction::MovePairUp => Some("move up"),
+            InputAction::MovePairDown => Some("move down"),
+            InputAction::Undo => Some("undo"),
+            InputAction::Redo => Some("redo"),
+            InputAction::OpenSortPopup => Some("sort"),
+            InputAction::SortToggleRecursive => Some("toggle recursive"),
+            InputAction::SortSubmit => Some("sort"),
+            InputAction::SortCancel => Some("cancel"),
+            InputAction::CopyPair => Some("copy"),
+            InputAction::CutPair => Some("cut"),
+            InputAction::PastePair => Some("paste"),
+            InputAction::DuplicatePair => Some("duplicate"),
+            InputAction::ToggleMark => Some("mark"),
+            InputAction::MarkRange => Some("mark range"),
+            InputAction::ClearMarks => Some("unmark all"),
+            InputAction::OpenFindReplace => Some("find/replace"),
+            InputAction::FindToggleField => Some("switch"),
+            InputAction::FindToggleRegex => Some("regex"),
+            InputAction::FindCycleTarget => Some("search in"),
+            InputAction::FindToggleScope => Some("scope"),
+            InputAction::FindSearch => Some("find"),
+            InputAction::FindCancel => Some("cancel"),
+            InputAction::FindAccept => Some("replace"),
+            InputAction::FindSkip => Some("skip"),
+            InputAction::FindAcceptAll => Some("replace all"),
+            InputAction::FindApply => Some("apply"),
+            InputAction::FindBack => Some("back"),
+            InputAction::OpenQuery => Some("query"),
+            InputAction::CloseQuery => Some("close"),
+            InputAction::QueryToggleFocus => Some("switch"),
+            InputAction::QueryJump => Some("jump to"),
+            InputAction::QueryEdit => Some("edit"),
+            InputAction::OpenCopyPath => Some("copy path"),
+            InputAction::CopyPathSubmit => Some("copy"),
+            InputAction::CopyPathCancel => Some("cancel"),
+            InputAction::OpenPatchExport => Some("export patch"),
+            InputAction::PatchExportSubmit => Some("write"),
+            InputAction::PatchExportCancel => Some("cancel"),
+            InputAction::OpenPatchApply => Some("apply patch"),
+            InputAction::PatchApplyLoad => Some("preview"),
+            InputAction::PatchApplyConfirm => Some("apply"),
+            InputAction::PatchApplyBack => Some("back"),
+            InputAction::PatchApplyCancel => Some("cancel"),
+            InputAction::DiffNextDifference => Some("next difference"),
+            InputAction::DiffToggleSide => Some("switch side"),
+            InputAction::DiffEnter => Some("open"),
+            InputAction::DiffLeave => Some("back"),
+            InputAction::DiffCopyAcross => Some("copy across"),
+            InputAction::DiffUndo => Some("undo"),
+            InputAction::DiffRedo => Some("redo"),
+            InputAction::DiffSave => Some("save side"),
+            InputAction::DiffQuit => Some("quit"),
+            InputAction::RecordOpen => Some("open"),
+            InputAction::CloseRecord => Some("records"),
+            InputAction::RecordAdd => Some("new"),
+            InputAction::RecordDelete => Some("delete"),
+            InputAction::RecordDeleteYes => Some("yes"),
+            InputAction::RecordDeleteNo => Some("no"),
+            InputAction::RecordMoveUp => Some("move up"),
+            InputAction::RecordUndo => Some("undo"),
+            InputAction::RecordRedo => Some("redo"),
+            InputAction::MergeKeepOurs => Some("ours"),
+            InputAction::MergeTakeTheirs => Some("theirs"),
+            InputAction::MergeEdit => Some("edit"),
+            InputAction::ConvertSubmit => Some("convert"),
+            InputAction::ConvertCancel => Some("cancel"),
+            InputAction::TextEditorGotoError => Some("go to error"),
+            InputAction::ExternalEditRetry => Some("re-edit"),
+            InputAction::ExternalEditDiscard => Some("discard"),
+            InputAction::TextEditorSave => Some("save"),
+            InputAction::TextEditorCancel => Some("cancel"),
+            InputAction::TextEditorToggleEscaped => Some("toggle escaping"),
             _ => None,
         }
     }
+
+    /// A longer explanation of the action, shown in the help overlay.
+    /// Unlike `description`, every action has one.
+    pub fn help_text(&self) -> &str {
+        match self {
+            InputAction::Quit => "Quit the editor",
+            InputAction::ExitCancel => "Return to the editor without quitting",
+            InputAction::OpenNewPairPopup => "Add a new key-value pair",
+            InputAction::EditingSubmit => "Move to the next field or save the pair",
+            InputAction::EditingCancel => "Close the popup without saving",
+            InputAction::EditingToggleField => "Switch between the key and value fields",
+            InputAction::EditingUp => "Move focus up",
+            InputAction::EditingDown => "Move focus down",
+            InputAction::EditingLeft => "Move focus left",
+            InputAction::EditingRight => "Move focus right",
+            InputAction::ExitUp => "Move focus up towards the output file",
+            InputAction::ExitDown => "Move focus down towards the buttons",
+            InputAction::ExitLeft => "Move focus to the previous button, or pick the previous format",
+            InputAction::ExitRight => "Move focus to the next button, or pick the next format",
+            InputAction::ExitCursorSelect => "Activate the focused button",
+            InputAction::EditingBoolToggle => "Toggle the boolean value",
+            InputAction::CursorUp => "Select the previous pair",
+            InputAction::CursorDown => "Select the next pair",
+            InputAction::CursorCancel => "Clear the selection",
+            InputAction::CursorSelect => "Edit the selected pair, or open it if it is an object",
+            InputAction::RequestPairDelete => "Delete the marked pairs, or the selected pair",
+            InputAction::DeleteYes => "Confirm deletion",
+            InputAction::DeleteNo => "Cancel deletion",
+            InputAction::ExitPreview => "Close the preview",
+            InputAction::Preview => "Preview the output file",
+            InputAction::OpenHelp => "Show this help",
+            InputAction::CloseHelp => "Close this help",
+            InputAction::HelpScrollUp => "Scroll the help up",
+            InputAction::HelpScrollDown => "Scroll the help down",
+            InputAction::EnterFieldText(TextField::Key) => "Type into the key field",
+            InputAction::EnterFieldText(TextField::Value) => "Type into the value field",
+            InputAction::EnterFieldText(TextField::OutputFile) => "Type the output file path",
+            InputAction::EnterFieldText(TextField::Editor) => "Type into the editor",
+            InputAction::EnterFieldText(TextField::Find) => "Type the text or regex to find",
+            InputAction::EnterFieldText(TextField::Replace) => "Type the replacement text",
+            InputAction::EnterFieldText(TextField::Query) => "Type the JSONPath query",
+            InputAction::EnterFieldText(TextField::PatchFile) => "Type the patch file path",
+            InputAction::EnterFieldText(TextField::ApplyFile) => {
+                "Type the path of the patch to apply"
+            }
+            InputAction::OpenStringEditor => "Edit the string in a full-screen editor",
+            InputAction::OpenExternalEditor => {
+                "Edit the selected value, or the whole document, in $VISUAL/$EDITOR"
+            }
+            InputAction::ExternalEditRetry => {
+                "Reopen the editor with the text that failed to parse"
+            }
+            InputAction::OpenRawEditor => {
+                "Edit the selected value, or the whole document, as JSON source"
+            }
+            InputAction::TextEditorGotoError => "Move the cursor to the syntax error",
+            InputAction::NavigateOut => "Return to the parent object",
+            InputAction::RequestConvert => {
+                "Convert the marked values, or the selected value, to another type"
+            }
+            InputAction::MovePairUp => "Move the marked pairs, or the selected pair, up one place",
+            InputAction::MovePairDown => {
+                "Move the marked pairs, or the selected pair, down one place"
+            }
+            InputAction::Undo => "Undo the last change to the document",
+            InputAction::Redo => "Redo the last undone change",
+            InputAction::OpenSortPopup => "Sort the pairs of the current object",
+            InputAction::SortUp => "Highlight the previous sort order",
+            InputAction::SortDown => "Highlight the next sort order",
+            InputAction::SortToggleRecursive => "Toggle whether nested objects are sorted too",
+            InputAction::SortSubmit => "Sort in the highlighted order",
+            InputAction::SortCancel => "Close the popup without sorting",
+            InputAction::CopyPair => "Copy the marked pairs, or the selected pair",
+            InputAction::CutPair => "Copy the marked pairs, or the selected pair, and delete them",
+            InputAction::PastePair => "Insert the copied pairs after the selection",
+            InputAction::DuplicatePair => {
+                "Insert a copy of each marked pair, or the selected pair, after it"
+            }
+            InputAction::ToggleMark => "Mark or unmark the selected pair",
+            InputAction::MarkRange => {
+                "Mark every pair between the last marked one and the selection"
+            }
+            InputAction::MarkAll => "Mark every pair in the current object",
+            InputAction::ClearMarks => "Unmark every pair",
+            InputAction::OpenFindReplace => "Find and replace text in keys and values",
+            InputAction::FindToggleField => "Switch between the find and replace fields",
+            InputAction::FindToggleRegex => "Toggle between literal text and regex patterns",
+            InputAction::FindCycleTarget => {
+                "Choose whether keys, string values or both are searched"
+            }
+            InputAction::FindToggleScope => {
+                "Toggle between searching the whole document and the current object"
+            }
+            InputAction::FindSearch => "Find matches and start reviewing them",
+            InputAction::FindCancel => "Close the popup without replacing anything",
+            InputAction::FindMatchUp => "Highlight the previous match",
+            InputAction::FindMatchDown => "Highlight the next match",
+            InputAction::FindAccept => "Replace the highlighted match and move to the next",
+            InputAction::FindSkip => "Skip the highlighted match and move to the next",
+            InputAction::FindAcceptAll => "Replace every match that has not been skipped",
+            InputAction::FindApply => "Replace the accepted matches and skip the rest",
+            InputAction::FindBack => "Return to the search without replacing anything",
+            InputAction::OpenQuery => "Filter the document with a JSONPath query",
+            InputAction::CloseQuery => "Return to the main list",
+            InputAction::QueryToggleFocus => "Switch between the query bar and its results",
+            InputAction::QueryResultUp => "Highlight the previous result",
+            InputAction::QueryResultDown => "Highlight the next result",
+            InputAction::QueryJump => "Show the highlighted result in the main list",
+            InputAction::QueryEdit => "Edit the highlighted result as raw JSON",
+            InputAction::OpenCopyPath => {
+                "Copy the selected pair's JSON Pointer, JSONPath or jq path"
+            }
+            InputAction::CopyPathUp => "Highlight the previous path format",
+            InputAction::CopyPathDown => "Highlight the next path format",
+            InputAction::CopyPathSubmit => "Copy the highlighted path to the system clipboard",
+            InputAction::CopyPathCancel => "Close the popup without copying",
+            InputAction::OpenPatchExport => {
+                "Write the changes made since loading as an RFC 6902 JSON Patch"
+            }
+            InputAction::PatchExportSubmit => "Write the patch to the file",
+            InputAction::PatchExportCancel => "Close the popup without writing",
+            InputAction::OpenPatchApply => {
+                "Apply a JSON Patch or JSON Merge Patch file to the document"
+            }
+            InputAction::PatchApplyLoad => "Read the patch and preview its changes",
+            InputAction::PatchApplyConfirm => "Apply the previewed changes",
+            InputAction::PatchApplyBack => "Return to the patch file path",
+            InputAction::PatchApplyCancel => "Close the popup without applying",
+            InputAction::PatchApplyUp => "Scroll to the previous change",
+            InputAction::PatchApplyDown => "Scroll to the next change",
+            InputAction::DiffUp => "Select the previous key",
+            InputAction::DiffDown => "Select the next key",
+            InputAction::DiffNextDifference => "Select the next key that differs",
+            InputAction::DiffToggleSide => "Switch which side values are copied from",
+            InputAction::DiffEnter => "Compare the objects stored under the selected key",
+            InputAction::DiffLeave => "Return to the parent objects",
+            InputAction::DiffCopyAcross => {
+                "Copy the selected value to the other side, or remove it there if this side lacks it"
+            }
+            InputAction::DiffUndo => "Undo the last copy across",
+            InputAction::DiffRedo => "Redo the last undone copy",
+            InputAction::DiffSave => "Save the focused side to its file",
+            InputAction::DiffQuit => "Quit, asking again if there are unsaved changes",
+            InputAction::RecordUp => "Select the previous record",
+            InputAction::RecordDown => "Select the next record",
+            InputAction::RecordOpen => "Edit the selected record",
+            InputAction::CloseRecord => "Return to the list of records",
+            InputAction::RecordAdd => "Add an empty record after the selected one",
+            InputAction::RecordDelete => "Delete the selected record",
+            InputAction::RecordDeleteYes => "Confirm deletion",
+            InputAction::RecordDeleteNo => "Cancel deletion",
+            InputAction::RecordMoveUp => "Move the selected record up one place",
+            InputAction::RecordMoveDown => "Move the selected record down one place",
+            InputAction::RecordUndo => "Undo the last change to the records",
+            InputAction::RecordRedo => "Redo the last undone change to the records",
+            InputAction::MergeKeepOurs => "Resolve the conflict with our value",
+            InputAction::MergeTakeTheirs => "Resolve the conflict with their value",
+            InputAction::MergeEdit => "Resolve the conflict by writing the value as JSON",
+            InputAction::ConvertUp => "Highlight the previous type",
+            InputAction::ConvertDown => "Highlight the next type",
+            InputAction::ConvertSubmit => "Convert the value to the highlighted type",
+            InputAction::ConvertCancel => "Close the popup without converting",
+            InputAction::ExternalEditDiscard => "Discard the text that failed to parse",
+            InputAction::TextEditorSave => "Save the text and close the editor",
+            InputAction::TextEditorCancel => "Close the editor without saving",
+            InputAction::TextEditorToggleEscaped => "Switch between raw and JSON-escaped text",
+            InputAction::TextEditorNewline => "Insert a new line",
+            InputAction::TextEditorUp => "Move the cursor up",
+            InputAction::TextEditorDown => "Move the cursor down",
+            InputAction::TextEditorLeft => "Move the cursor left",
+            InputAction::TextEditorRight => "Move the cursor right",
+            InputAction::TextEditorHome => "Move the cursor to the start of the line",
+            InputAction::TextEditorEnd => "Move the cursor to the end of the line",
+            InputAction::BackspaceFieldText(_) => "Delete the last character",
+        }
+    }
+
+    pub fn category(&self) -> ActionCategory {
+        match self {
+            InputAction::CursorUp
+            | InputAction::CursorDown
+            | InputAction::CursorCancel
+            | InputAction::CursorSelect
+            | InputAction::EditingToggleField
+            | InputAction::EditingUp
+            | InputAction::EditingDown
+            | InputAction::EditingLeft
+            | InputAction::EditingRight
+            | InputAction::ExitUp
+            | InputAction::ExitDown
+            | InputAction::ExitLeft
+            | InputAction::ExitRight
+            | InputAction::HelpScrollUp
+            | InputAction::HelpScrollDown
+            | InputAction::TextEditorUp
+            | InputAction::TextEditorDown
+            | InputAction::TextEditorLeft
+            | InputAction::TextEditorRight
+            | InputAction::TextEditorHome
+            | InputAction::TextEditorEnd
+            | InputAction::TextEditorGotoError
+            | InputAction::NavigateOut
+            | InputAction::ConvertUp
+            | InputAction::ConvertDown
+            | InputAction::SortUp
+            | InputAction::SortDown
+            | InputAction::ToggleMark
+            | InputAction::MarkRange
+            | InputAction::MarkAll
+            | InputAction::ClearMarks
+            | InputAction::FindToggleField
+            | InputAction::FindMatchUp
+            | InputAction::FindMatchDown
+            | InputAction::QueryToggleFocus
+            | InputAction::QueryResultUp
+            | InputAction::QueryResultDown
+            | InputAction::QueryJump
+            | InputAction::CopyPathUp
+            | InputAction::CopyPathDown
+            | InputAction::PatchApplyUp
+            | InputAction::PatchApplyDown
+            | InputAction::DiffUp
+            | InputAction::DiffDown
+            | InputAction::DiffNextDifference
+            | InputAction::DiffToggleSide
+            | InputAction::DiffEnter
+            | InputAction::DiffLeave
+            | InputAction::RecordUp
+            | InputAction::RecordDown
+            | InputAction::RecordOpen
+            | InputAction::CloseRecord => ActionCategory::Navigation,
+            InputAction::OpenNewPairPopup
+            | InputAction::OpenStringEditor
+            | InputAction::OpenExternalEditor
+            | InputAction::OpenRawEditor
+            | InputAction::RequestConvert
+            | InputAction::MovePairUp
+            | InputAction::MovePairDown
+            | InputAction::Undo
+            | InputAction::Redo
+            | InputAction::DiffUndo
+            | InputAction::DiffRedo
+            | InputAction::OpenSortPopup
+            | InputAction::SortToggleRecursive
+            | InputAction::CopyPair
+            | InputAction::CutPair
+            | InputAction::PastePair
+            | InputAction::DuplicatePair
+            | InputAction::OpenFindReplace
+            | InputAction::FindToggleRegex
+            | InputAction::FindCycleTarget
+            | InputAction::FindToggleScope
+            | InputAction::QueryEdit
+            | InputAction::TextEditorToggleEscaped
+            | InputAction::TextEditorNewline
+            | InputAction::EditingSubmit
+            | InputAction::EditingBoolToggle
+            | InputAction::RequestPairDelete
+            | InputAction::EnterFieldText(_)
+            | InputAction::BackspaceFieldText(_)
+            | InputAction::DiffCopyAcross
+            | InputAction::MergeEdit
+            | InputAction::RecordAdd
+            | InputAction::RecordDelete
+            | InputAction::RecordMoveUp
+            | InputAction::RecordMoveDown
+            | InputAction::RecordUndo
+            | InputAction::RecordRedo => ActionCategory::Editing,
+            InputAction::DeleteYes
+            | InputAction::DeleteNo
+            | InputAction::EditingCancel
+            | InputAction::ExitCancel
+            | InputAction::ExitCursorSelect
+            | InputAction::TextEditorSave
+            | InputAction::TextEditorCancel
+            | InputAction::ExternalEditRetry
+            | InputAction::ExternalEditDiscard
+            | InputAction::ConvertSubmit
+            | InputAction::ConvertCancel
+            | InputAction::SortSubmit
+            | InputAction::SortCancel
+            | InputAction::FindSearch
+            | InputAction::FindCancel
+            | InputAction::FindAccept
+            | InputAction::FindSkip
+            | InputAction::FindAcceptAll
+            | InputAction::FindApply
+            | InputAction::FindBack
+            | InputAction::CopyPathSubmit
+            | InputAction::CopyPathCancel
+            | InputAction::PatchExportSubmit
+            | InputAction::PatchExportCancel
+            | InputAction::PatchApplyLoad
+            | InputAction::PatchApplyConfirm
+            | InputAction::PatchApplyBack
+            | InputAction::PatchApplyCancel
+            | InputAction::MergeKeepOurs
+            | InputAction::MergeTakeTheirs
+            | InputAction::RecordDeleteYes
+            | InputAction::RecordDeleteNo => ActionCategory::Confirmation,
+            InputAction::Quit
+            | InputAction::Preview
+            | InputAction::ExitPreview
+            | InputAction::OpenHelp
+            | InputAction::CloseHelp
+            | InputAction::OpenQuery
+            | InputAction::CloseQuery
+            | InputAction::OpenCopyPath
+            | InputAction::OpenPatchExport
+            | InputAction::OpenPatchApply
+            | InputAction::DiffSave
+            | InputAction::DiffQuit => ActionCategory::General,
+        }
+    }
 }
 
 pub type ActionBinding = (Binding, InputAction);
@@ -428,12 +2903,96 @@ impl Display for OpenItemEditError {
     }
 }
 
+/// Formats a byte count for display, eg: "3.2 KB"
+pub fn format_byte_size(bytes: usize) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit_index = 0;
+    while size >= 1024.0 && unit_index < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit_index += 1;
+    }
+
+    format!("{size:.1} {}", UNITS[unit_index])
+}
+
+#[derive(Debug)]
+pub enum ValueInputError {
+    InvalidNumber(String),
+    InvalidBoolean(String),
+    InvalidJson(String),
+    TypeMismatch(JsonValueType),
+    InvalidDatetime(String),
+}
+
+impl Display for ValueInputError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ValueInputError::InvalidNumber(input) => write!(f, "\"{input}\" is not a valid number"),
+            ValueInputError::InvalidBoolean(input) => {
+                write!(f, "\"{input}\" is not a valid boolean")
+            }
+            ValueInputError::InvalidJson(e) => write!(f, "Invalid JSON: {e}"),
+            ValueInputError::InvalidDatetime(input) => write!(
+                f,
+                "\"{input}\" is not a valid datetime, eg: 1979-05-27T07:32:00Z"
+            ),
+            ValueInputError::TypeMismatch(expected) => {
+                write!(f, "The value is not {} {expected}", expected.article())
+            }
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
+pub enum Severity {
+    Info,
+    Warn,
+    Error,
+}
+
+impl Severity {
+    /// How long a message of this severity stays in the status bar,
+    /// more severe messages linger so they are not missed
+    pub fn display_duration(&self) -> Duration {
+        match self {
+            Severity::Info => Duration::from_secs(3),
+            Severity::Warn => Duration::from_secs(5),
+            Severity::Error => Duration::from_secs(8),
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warn => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+pub struct StatusMessage {
+    pub text: String,
+    pub severity: Severity,
+    expires_at: Instant,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum JsonValueType {
     Number,
     String,
     Boolean,
     Null,
+    Array,
+    Object,
+    Datetime,
 }
 
 impl Display for JsonValueType {
@@ -443,26 +3002,108 @@ impl Display for JsonValueType {
             JsonValueType::String => write!(f, "String"),
             JsonValueType::Boolean => write!(f, "Boolean"),
             JsonValueType::Null => write!(f, "null"),
+            JsonValueType::Array => write!(f, "Array"),
+            JsonValueType::Object => write!(f, "Object"),
+            JsonValueType::Datetime => write!(f, "Datetime"),
+        }
+    }
+}
+
+impl JsonValueType {
+    /// The indefinite article to put before the type's name
+    pub fn article(self) -> &'static str {
+        match self {
+            JsonValueType::Array | JsonValueType::Object => "an",
+            _ => "a",
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum JsonValue {
     Number(f64),
     String(String),
     Boolean(bool),
     Null,
+    Array(Vec<JsonValue>),
+    Object(JsonData),
+    /// A TOML date, time or datetime, kept as its text. Formats without
+    /// datetimes write it as a string.
+    Datetime(String),
 }
 
 impl JsonValue {
-    pub fn from_serde(serde_value: serde_json::Value) -> Result<Self, JsonValueFromSerdeError> {
+    pub fn from_serde(serde_value: serde_json::Value) -> Self {
         match serde_value {
-            serde_json::Value::Number(n) => Ok(JsonValue::Number(n.as_f64().unwrap_or(0.0))),
-            serde_json::Value::String(s) => Ok(JsonValue::String(s)),
-            serde_json::Value::Bool(b) => Ok(JsonValue::Boolean(b)),
-            serde_json::Value::Null => Ok(JsonValue::Null),
-            _ => Err(JsonValueFromSerdeError::UnsupportedType),
+            serde_json::Value::Number(n) => JsonValue::Number(n.as_f64().unwrap_or(0.0)),
+            serde_json::Value::String(s) => JsonValue::String(s),
+            serde_json::Value::Bool(b) => JsonValue::Boolean(b),
+            serde_json::Value::Null => JsonValue::Null,
+            serde_json::Value::Array(items) => {
+                JsonValue::Array(items.into_iter().map(JsonValue::from_serde).collect())
+            }
+            serde_json::Value::Object(data) => JsonValue::Object(json_data_from_serde(data)),
+        }
+    }
+
+    pub fn value_type(&self) -> JsonValueType {
+        match self {
+            JsonValue::Number(_) => JsonValueType::Number,
+            JsonValue::String(_) => JsonValueType::String,
+            JsonValue::Boolean(_) => JsonValueType::Boolean,
+            JsonValue::Null => JsonValueType::Null,
+            JsonValue::Array(_) => JsonValueType::Array,
+            JsonValue::Object(_) => JsonValueType::Object,
+            JsonValue::Datetime(_) => JsonValueType::Datetime,
+        }
+    }
+
+    /// The text shown in the value field when editing this value,
+    /// strings are shown without quotes and everything else as JSON
+    pub fn input_text(&self) -> String {
+        match self {
+            JsonValue::String(s) | JsonValue::Datetime(s) => s.clone(),
+            other => serde_json::to_string(other).unwrap_or_default(),
+        }
+    }
+
+    pub fn holds_datetime(&self) -> bool {
+        match self {
+            JsonValue::Datetime(_) => true,
+            JsonValue::Array(items) => items.iter().any(JsonValue::holds_datetime),
+            JsonValue::Object(data) => data.values().any(JsonValue::holds_datetime),
+            _ => false,
+        }
+    }
+
+    /// Turns strings back into the datetimes they were before the value was
+    /// edited as JSON source, which has no way to mark a string as a datetime
+    pub fn restore_datetimes(&mut self, original: &JsonValue) {
+        if let (JsonValue::String(s), JsonValue::Datetime(text)) = (&*self, original) {
+            if s == text {
+                *self = JsonValue::Datetime(text.clone());
+            }
+            return;
+        }
+
+        match (self, original) {
+            (JsonValue::Array(items), JsonValue::Array(original_items)) => {
+                for (item, original_item) in items.iter_mut().zip(original_items) {
+                    item.restore_datetimes(original_item);
+                }
+            }
+            (JsonValue::Object(data), JsonValue::Object(original_data)) => {
+                restore_datetimes(data, original_data)
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn restore_datetimes(data: &mut JsonData, original: &JsonData) {
+    for (key, value) in data.iter_mut() {
+        if let Some(original_value) = original.get(key) {
+            value.restore_datetimes(original_value);
         }
     }
 }
@@ -485,59 +3126,163 @@ impl serde::Serialize for JsonValue {
                     serializer.serialize_u64(*n as u64)
                 }
             }
-            JsonValue::String(s) => serializer.serialize_str(s),
+            JsonValue::String(s) | JsonValue::Datetime(s) => serializer.serialize_str(s),
             JsonValue::Boolean(b) => serializer.serialize_bool(*b),
             JsonValue::Null => serializer.serialize_none(),
+            JsonValue::Array(items) => serializer.collect_seq(items),
+            JsonValue::Object(data) => serializer.collect_map(data),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for JsonValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(JsonValueVisitor)
+    }
+}
+
+/// Reads values from formats that don't go through `serde_json::Value`,
+/// which can't hold the infinite and NaN numbers JSON5 allows
+struct JsonValueVisitor;
+
+impl<'de> serde::de::Visitor<'de> for JsonValueVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "a JSON value")
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<JsonValue, E> {
+        Ok(JsonValue::Boolean(b))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(n as f64))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(n as f64))
+    }
+
+    fn visit_f64<E>(self, n: f64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(n))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(s.to_string()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(s))
+    }
+
+    fn visit_unit<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<JsonValue, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        serde::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<JsonValue, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(JsonValue::Array(items))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<JsonValue, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        let mut data = JsonData::new();
+        while let Some((key, value)) = map.next_entry()? {
+            data.insert(key, value);
         }
+        Ok(JsonValue::Object(data))
     }
 }
 
 pub type JsonData = IndexMap<String, JsonValue>;
 
+pub fn json_data_from_serde(data: serde_json::Map<String, serde_json::Value>) -> JsonData {
+    data.into_iter()
+        .map(|(key, value)| (key, JsonValue::from_serde(value)))
+        .collect()
+}
+
 #[derive(Debug)]
 pub enum AppError {
     InputFileNotFound(String),
-    InvalidInputJson,
+    InvalidInput(FormatError),
     FailedToOpenPairEdit(OpenItemEditError),
     NoEntryAtIndex(usize),
     UnableToSave(AppWriteError),
     FailedToDraw(io::Error),
+    FailedToSuspend(io::Error),
     FailedToReadEvent(io::Error),
+    PointerNotFound(String),
+    /// A JSON Patch was asked for while editing the records of a JSON Lines file
+    PatchOfRecords,
 }
 
 impl Display for AppError {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
             AppError::InputFileNotFound(path) => write!(f, "No file found at path: {path}"),
-            AppError::InvalidInputJson => write!(f, "Invalid input JSON"),
+            AppError::InvalidInput(e) => write!(f, "Failed to read the input file: {e}"),
             AppError::FailedToOpenPairEdit(e) => write!(f, "Failed to open pair for editing: {e}"),
             AppError::UnableToSave(e) => write!(f, "Failed to write file: {e}"),
             AppError::FailedToDraw(e) => write!(f, "An error occurred while rendering the UI: {e}"),
+            AppError::FailedToSuspend(e) => {
+                write!(f, "An error occurred while suspending the UI: {e}")
+            }
             AppError::FailedToReadEvent(e) => {
                 write!(f, "An error occurred while reading input: {e}")
             }
             AppError::NoEntryAtIndex(usize) => write!(f, "No entry exists at index {usize}"),
+            AppError::PointerNotFound(pointer) => {
+                write!(f, "No value found at JSON Pointer: {pointer}")
+            }
+            AppError::PatchOfRecords => write!(
+                f,
+                "JSON Patches cannot be written for JSON Lines files, whose records are edited one at a time"
+            ),
         }
     }
 }
 
 impl std::error::Error for AppError {}
 
-pub enum JsonValueFromSerdeError {
-    UnsupportedType,
-}
-
 #[derive(Debug)]
 pub enum AppWriteError {
     Serde(serde_json::Error),
+    Format(FormatError),
     Io(io::Error),
+    MissingPath,
 }
 
 impl Display for AppWriteError {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
             AppWriteError::Serde(e) => write!(f, "Serde error: {e}"),
+            AppWriteError::Format(e) => write!(f, "{e}"),
             AppWriteError::Io(e) => write!(f, "IO error: {e}"),
+            AppWriteError::MissingPath => write!(f, "No output file was provided"),
         }
     }
 }
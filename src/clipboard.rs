@@ -0,0 +1,67 @@
+use std::io::{self, Write};
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Asks the terminal to place `text` on the system clipboard using an OSC 52
+/// escape sequence. Because the request travels through the terminal itself,
+/// this also works over SSH. Terminals that do not support it ignore it.
+pub fn copy_to_system_clipboard(text: &str) -> Result<(), io::Error> {
+    let mut stderr = io::stderr();
+    write!(stderr, "\x1b]52;c;{}\x07", encode_base64(text.as_bytes()))?;
+    stderr.flush()
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | (*byte as u32) << (16 - i * 8)
+        });
+
+        // every byte of input fills at least part of one more output character
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (group >> (18 - i * 6)) & 0b11_1111;
+                encoded.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_the_rfc_4648_test_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+
+        for (input, expected) in vectors {
+            assert_eq!(
+                encode_base64(input.as_bytes()),
+                expected,
+                "encoding {input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn encodes_bytes_using_the_whole_alphabet() {
+        assert_eq!(encode_base64(&[0xfb, 0xff, 0xbf]), "+/+/");
+        assert_eq!(encode_base64("é".as_bytes()), "w6k=");
+    }
+}
@@ -0,0 +1,259 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+use std::{fs, io};
+
+use clap::Subcommand;
+
+use crate::app::{format_byte_size, App, AppError, AppScreen, JsonData};
+use crate::diff_view::{DiffDocument, DiffView};
+use crate::format::{FileFormat, FormatError};
+use crate::jsonc::JsoncLayout;
+use crate::merge::{merge_documents, MergeState};
+use crate::patch::{apply_patch, diff, parse_patch, PatchError};
+use crate::path::value_at_pointer;
+
+// commands that work on files directly instead of opening the editor
+#[derive(Subcommand)]
+pub enum Command {
+    /// Apply an RFC 6902 JSON Patch (an array of operations)
+    /// or an RFC 7386 JSON Merge Patch (an object) to a file
+    Apply {
+        /// The patch to apply
+        patch_file: String,
+
+        /// The JSON file to patch
+        target_file: String,
+
+        /// Write the patched document here instead of over the target file
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<String>,
+    },
+    /// Compare two JSON files side by side, copying values between them
+    Diff {
+        left_file: String,
+        right_file: String,
+    },
+    /// Three-way merge two files changed from a common base, resolving
+    /// conflicting keys in the editor
+    Merge {
+        base_file: String,
+        our_file: String,
+        their_file: String,
+
+        /// Write the merged document here instead of over our file
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<String>,
+    },
+    /// Convert a file between JSON, JSONC, JSON5, YAML, TOML, NDJSON and CSV
+    Convert {
+        input_file: String,
+        output_file: String,
+
+        /// The format of the input file, guessed from its extension when not given
+        #[arg(long, value_name = "FORMAT")]
+        from: Option<FileFormat>,
+
+        /// The format to write, guessed from the output file's extension when not given
+        #[arg(long, value_name = "FORMAT")]
+        to: Option<FileFormat>,
+
+        /// Convert only the value at this RFC 6901 JSON Pointer (eg: /users),
+        /// such as an array of objects to write as CSV
+        #[arg(long, value_name = "POINTER")]
+        pointer: Option<String>,
+    },
+}
+
+pub enum CommandOutcome {
+    /// The command has finished, with a message to print
+    Done(String),
+    /// The command continues in the editor
+    OpenEditor(Box<App>),
+}
+
+pub fn run_command(command: Command) -> Result<CommandOutcome, CommandError> {
+    match command {
+        Command::Apply {
+            patch_file,
+            target_file,
+            output,
+        } => apply(&patch_file, &target_file, output.as_deref()).map(CommandOutcome::Done),
+        Command::Diff {
+            left_file,
+            right_file,
+        } => open_diff(left_file, right_file).map(|app| CommandOutcome::OpenEditor(Box::new(app))),
+        Command::Merge {
+            base_file,
+            our_file,
+            their_file,
+            output,
+        } => merge(&base_file, &our_file, &their_file, output),
+        Command::Convert {
+            input_file,
+            output_file,
+            from,
+            to,
+            pointer,
+        } => convert(&input_file, &output_file, from, to, pointer.as_deref())
+            .map(CommandOutcome::Done),
+    }
+}
+
+fn convert(
+    input_file: &str,
+    output_file: &str,
+    from: Option<FileFormat>,
+    to: Option<FileFormat>,
+    pointer: Option<&str>,
+) -> Result<String, CommandError> {
+    let from = from.unwrap_or_else(|| FileFormat::from_path(input_file));
+    let to = to.unwrap_or_else(|| FileFormat::from_path(output_file));
+
+    let text = fs::read_to_string(input_file)
+        .map_err(|e| CommandError::Read(input_file.to_string(), e))?;
+    let value = from
+        .parse_value(&text)
+        .map_err(|e| CommandError::Format(input_file.to_string(), e))?;
+    let value = match pointer {
+        Some(pointer) => value_at_pointer(&value, pointer).ok_or_else(|| {
+            CommandError::PointerNotFound(input_file.to_string(), pointer.to_string())
+        })?,
+        None => &value,
+    };
+    let serialized = to
+        .serialize_value(value)
+        .map_err(|e| CommandError::Format(output_file.to_string(), e))?;
+    fs::write(output_file, &serialized)
+        .map_err(|e| CommandError::Write(output_file.to_string(), e))?;
+
+    Ok(format!(
+        "Converted {input_file} ({from}) to {output_file} ({to}), {}",
+        format_byte_size(serialized.len())
+    ))
+}
+
+/// Writes the merge straight away when nothing conflicts,
+/// otherwise opens it in the editor to resolve the conflicts
+fn merge(
+    base_file: &str,
+    our_file: &str,
+    their_file: &str,
+    output_file: Option<String>,
+) -> Result<CommandOutcome, CommandError> {
+    let (base, _) = load_document(base_file)?;
+    let (ours, layout) = load_document(our_file)?;
+    let (theirs, _) = load_document(their_file)?;
+
+    let (merged, conflicts) = merge_documents(&base, &ours, &theirs);
+
+    let output_file = output_file.unwrap_or_else(|| our_file.to_string());
+
+    let mut app = App::new(None, None).map_err(CommandError::App)?;
+    app.pairs = merged;
+    app.original_pairs = ours;
+    // our file's comments are kept where the merge still has their values
+    app.jsonc_layout = layout;
+    app.format = FileFormat::from_path(&output_file);
+    app.target_write_file = Some(output_file);
+
+    if conflicts.is_empty() {
+        let bytes_written = app.write().map_err(CommandError::App)?;
+        return Ok(CommandOutcome::Done(format!(
+            "Merged without conflicts, saved {} to {}",
+            format_byte_size(bytes_written),
+            app.target_write_file.unwrap_or_default()
+        )));
+    }
+
+    app.merge = Some(MergeState::new(conflicts));
+    Ok(CommandOutcome::OpenEditor(Box::new(app)))
+}
+
+fn apply(
+    patch_file: &str,
+    target_file: &str,
+    output_file: Option<&str>,
+) -> Result<String, CommandError> {
+    let patch_text = fs::read_to_string(patch_file)
+        .map_err(|e| CommandError::Read(patch_file.to_string(), e))?;
+    let patch = parse_patch(&patch_text).map_err(CommandError::Patch)?;
+
+    let (document, layout) = load_document(target_file)?;
+    let patched = apply_patch(&document, &patch).map_err(CommandError::Patch)?;
+    let changes = diff(&document, &patched);
+
+    let output_file = output_file.unwrap_or(target_file);
+    write_document(output_file, &patched, &layout)?;
+
+    let mut message: String = changes.iter().map(|change| format!("{change}\n")).collect();
+    message.push_str(&format!(
+        "Applied {} changes to {output_file}",
+        changes.len()
+    ));
+    Ok(message)
+}
+
+fn open_diff(left_file: String, right_file: String) -> Result<App, CommandError> {
+    let (data, layout) = load_document(&left_file)?;
+    let left = DiffDocument {
+        file: left_file,
+        data,
+        layout,
+        unsaved: false,
+    };
+    let (data, layout) = load_document(&right_file)?;
+    let right = DiffDocument {
+        file: right_file,
+        data,
+        layout,
+        unsaved: false,
+    };
+
+    let mut app = App::new(None, None).map_err(CommandError::App)?;
+    app.diff_view = Some(DiffView::new(left, right));
+    app.goto_screen(AppScreen::Diff);
+    Ok(app)
+}
+
+/// Reads a document in the format its extension suggests, with the layout of its comments
+pub fn load_document(path: &str) -> Result<(JsonData, JsoncLayout), CommandError> {
+    let text = fs::read_to_string(path).map_err(|e| CommandError::Read(path.to_string(), e))?;
+    FileFormat::from_path(path)
+        .parse_with_layout(&text)
+        .map_err(|e| CommandError::Format(path.to_string(), e))
+}
+
+fn write_document(path: &str, data: &JsonData, layout: &JsoncLayout) -> Result<(), CommandError> {
+    let serialized = FileFormat::from_path(path)
+        .serialize_with_layout(data, layout)
+        .map_err(|e| CommandError::Format(path.to_string(), e))?;
+    fs::write(path, serialized).map_err(|e| CommandError::Write(path.to_string(), e))
+}
+
+#[derive(Debug)]
+pub enum CommandError {
+    Read(String, io::Error),
+    Write(String, io::Error),
+    Format(String, FormatError),
+    Patch(PatchError),
+    App(AppError),
+    /// The file and the pointer that refers to nothing in it
+    PointerNotFound(String, String),
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CommandError::Read(path, e) => write!(f, "Failed to read {path}: {e}"),
+            CommandError::Write(path, e) => write!(f, "Failed to write {path}: {e}"),
+            CommandError::Format(path, e) => write!(f, "{path}: {e}"),
+            CommandError::Patch(e) => write!(f, "{e}"),
+            CommandError::App(e) => write!(f, "{e}"),
+            CommandError::PointerNotFound(path, pointer) => {
+                write!(f, "{path}: No value found at JSON Pointer: {pointer}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
@@ -0,0 +1,270 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::app::{JsonValue, JsonValueType};
+use crate::format::is_datetime;
+
+/// Converts a value to another type, keeping as much of its meaning as possible.
+/// Conversions that would lose information are rejected rather than guessed at.
+pub fn convert_value(
+    value: &JsonValue,
+    target_type: JsonValueType,
+) -> Result<JsonValue, ConversionError> {
+    let source_type = value.value_type();
+
+    if source_type == target_type {
+        return Ok(value.clone());
+    }
+
+    let impossible = || ConversionError::Impossible(source_type, target_type);
+
+    match (value, target_type) {
+        // everything has a textual form, objects and arrays become their JSON source
+        (JsonValue::String(_), _) => convert_string(value, target_type),
+        // a datetime converts like its text
+        (JsonValue::Datetime(text), _) => {
+            convert_string(&JsonValue::String(text.clone()), target_type)
+        }
+        (JsonValue::Number(n), JsonValueType::String) => Ok(JsonValue::String(n.to_string())),
+        (JsonValue::Boolean(b), JsonValueType::String) => Ok(JsonValue::String(b.to_string())),
+        (JsonValue::Null, JsonValueType::String) => Ok(JsonValue::String("null".to_string())),
+        (JsonValue::Array(_) | JsonValue::Object(_), JsonValueType::String) => {
+            serde_json::to_string(value)
+                .map(JsonValue::String)
+                .map_err(|_| impossible())
+        }
+
+        // any scalar can be wrapped in an array
+        (JsonValue::Number(_) | JsonValue::Boolean(_) | JsonValue::Null, JsonValueType::Array) => {
+            Ok(JsonValue::Array(vec![value.clone()]))
+        }
+
+        // a single-element array can be unwrapped and converted
+        (JsonValue::Array(items), _) => match items.as_slice() {
+            [item] => convert_value(item, target_type),
+            _ => Err(ConversionError::Lossy(
+                source_type,
+                target_type,
+                "only single-element arrays can be unwrapped",
+            )),
+        },
+
+        (JsonValue::Number(n), JsonValueType::Boolean) => {
+            if *n == 0.0 || *n == 1.0 {
+                Ok(JsonValue::Boolean(*n == 1.0))
+            } else {
+                Err(ConversionError::Lossy(
+                    source_type,
+                    target_type,
+                    "only 0 and 1 can become booleans",
+                ))
+            }
+        }
+        (JsonValue::Boolean(b), JsonValueType::Number) => {
+            Ok(JsonValue::Number(if *b { 1.0 } else { 0.0 }))
+        }
+
+        (_, JsonValueType::Null) => Err(ConversionError::Lossy(
+            source_type,
+            target_type,
+            "the current value would be discarded",
+        )),
+
+        _ => Err(impossible()),
+    }
+}
+
+fn convert_string(
+    value: &JsonValue,
+    target_type: JsonValueType,
+) -> Result<JsonValue, ConversionError> {
+    let JsonValue::String(s) = value else {
+        unreachable!("`convert_string` is only called with strings");
+    };
+    let trimmed = s.trim();
+    let unparseable = || ConversionError::Unparseable(s.clone(), target_type);
+
+    match target_type {
+        JsonValueType::Number => trimmed
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite())
+            .map(JsonValue::Number)
+            .ok_or_else(unparseable),
+        JsonValueType::Boolean => trimmed
+            .parse::<bool>()
+            .map(JsonValue::Boolean)
+            .map_err(|_| unparseable()),
+        JsonValueType::Null => match trimmed {
+            "null" => Ok(JsonValue::Null),
+            _ => Err(ConversionError::Lossy(
+                JsonValueType::String,
+                target_type,
+                "only the text \"null\" can become null",
+            )),
+        },
+        JsonValueType::Array => Ok(JsonValue::Array(vec![value.clone()])),
+        // a string holding JSON source for an object is parsed
+        JsonValueType::Object => serde_json::from_str::<serde_json::Value>(trimmed)
+            .ok()
+            .and_then(|parsed| match JsonValue::from_serde(parsed) {
+                JsonValue::Object(data) => Some(JsonValue::Object(data)),
+                _ => None,
+            })
+            .ok_or_else(unparseable),
+        JsonValueType::Datetime if is_datetime(trimmed) => {
+            Ok(JsonValue::Datetime(trimmed.to_string()))
+        }
+        JsonValueType::Datetime => Err(unparseable()),
+        JsonValueType::String => Ok(value.clone()),
+    }
+}
+
+#[derive(Debug)]
+pub enum ConversionError {
+    Impossible(JsonValueType, JsonValueType),
+    Lossy(JsonValueType, JsonValueType, &'static str),
+    Unparseable(String, JsonValueType),
+}
+
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ConversionError::Impossible(from, to) => {
+                let article = match from.article() {
+                    "an" => "An",
+                    _ => "A",
+                };
+                write!(f, "{article} {from} cannot be converted to {to}")
+            }
+            ConversionError::Lossy(from, to, reason) => {
+                write!(f, "Converting {from} to {to} would lose data: {reason}")
+            }
+            ConversionError::Unparseable(input, to) => {
+                write!(f, "\"{input}\" cannot be read as {to}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn converted(value: JsonValue, target_type: JsonValueType) -> String {
+        match convert_value(&value, target_type) {
+            Ok(converted) => serde_json::to_string(&converted).unwrap(),
+            Err(e) => panic!("expected a conversion to {target_type}, got: {e}"),
+        }
+    }
+
+    fn rejection(value: JsonValue, target_type: JsonValueType) -> String {
+        let Err(e) = convert_value(&value, target_type) else {
+            panic!("expected the conversion to {target_type} to be rejected");
+        };
+        e.to_string()
+    }
+
+    fn string(text: &str) -> JsonValue {
+        JsonValue::String(text.to_string())
+    }
+
+    #[test]
+    fn reads_numbers_from_strings_but_not_non_finite_ones() {
+        assert_eq!(converted(string(" 2.5 "), JsonValueType::Number), "2.5");
+        assert_eq!(
+            rejection(string("NaN"), JsonValueType::Number),
+            "\"NaN\" cannot be read as Number"
+        );
+        assert_eq!(
+            rejection(string("inf"), JsonValueType::Number),
+            "\"inf\" cannot be read as Number"
+        );
+        assert_eq!(
+            rejection(string("1e999"), JsonValueType::Number),
+            "\"1e999\" cannot be read as Number"
+        );
+    }
+
+    #[test]
+    fn only_converts_zero_and_one_to_booleans() {
+        assert_eq!(
+            converted(JsonValue::Number(1.0), JsonValueType::Boolean),
+            "true"
+        );
+        assert_eq!(
+            converted(JsonValue::Number(0.0), JsonValueType::Boolean),
+            "false"
+        );
+        assert_eq!(
+            rejection(JsonValue::Number(0.5), JsonValueType::Boolean),
+            "Converting Number to Boolean would lose data: only 0 and 1 can become booleans"
+        );
+        assert_eq!(
+            rejection(JsonValue::Number(2.0), JsonValueType::Boolean),
+            "Converting Number to Boolean would lose data: only 0 and 1 can become booleans"
+        );
+    }
+
+    #[test]
+    fn only_converts_matching_text_to_booleans_and_null() {
+        assert_eq!(converted(string("true"), JsonValueType::Boolean), "true");
+        assert_eq!(
+            rejection(string("yes"), JsonValueType::Boolean),
+            "\"yes\" cannot be read as Boolean"
+        );
+        assert_eq!(converted(string(" null "), JsonValueType::Null), "null");
+        assert_eq!(
+            rejection(string("nothing"), JsonValueType::Null),
+            "Converting String to null would lose data: only the text \"null\" can become null"
+        );
+    }
+
+    #[test]
+    fn converts_scalars_back_to_strings() {
+        assert_eq!(
+            converted(JsonValue::Number(2.5), JsonValueType::String),
+            "\"2.5\""
+        );
+        assert_eq!(
+            converted(JsonValue::Boolean(false), JsonValueType::String),
+            "\"false\""
+        );
+        assert_eq!(
+            converted(JsonValue::Null, JsonValueType::String),
+            "\"null\""
+        );
+        assert_eq!(
+            converted(JsonValue::Boolean(true), JsonValueType::Number),
+            "1"
+        );
+    }
+
+    #[test]
+    fn refuses_to_discard_values_or_guess_at_their_meaning() {
+        assert_eq!(
+            rejection(JsonValue::Boolean(true), JsonValueType::Null),
+            "Converting Boolean to null would lose data: the current value would be discarded"
+        );
+        assert_eq!(
+            rejection(JsonValue::Number(1.0), JsonValueType::Object),
+            "A Number cannot be converted to Object"
+        );
+        assert_eq!(
+            rejection(
+                JsonValue::Array(vec![JsonValue::Null, JsonValue::Null]),
+                JsonValueType::Number
+            ),
+            "Converting Array to Number would lose data: only single-element arrays can be unwrapped"
+        );
+        assert_eq!(
+            converted(
+                JsonValue::Array(vec![string("true")]),
+                JsonValueType::Boolean
+            ),
+            "true"
+        );
+    }
+}
@@ -0,0 +1,505 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs;
+
+use ratatui::widgets::ListState;
+
+use crate::app::{AppError, AppWriteError, JsonData, JsonValue};
+use crate::format::FileFormat;
+use crate::history::History;
+use crate::jsonc::JsoncLayout;
+use crate::path::{display_path, path_from_keys};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DiffSide {
+    Left,
+    Right,
+}
+
+impl DiffSide {
+    pub fn other(self) -> DiffSide {
+        match self {
+            DiffSide::Left => DiffSide::Right,
+            DiffSide::Right => DiffSide::Left,
+        }
+    }
+}
+
+/// One of the two files being compared
+pub struct DiffDocument {
+    pub file: String,
+    pub data: JsonData,
+    /// Where the comments of a JSONC file go when it is saved
+    pub layout: JsoncLayout,
+    /// Whether values have been copied into this document since it was last saved
+    pub unsaved: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DiffStatus {
+    Unchanged,
+    /// Only in the right document
+    Added,
+    /// Only in the left document
+    Removed,
+    Changed,
+}
+
+/// A key of the objects being compared, with its value on each side
+pub struct DiffRow {
+    pub key: String,
+    pub left: Option<JsonValue>,
+    pub right: Option<JsonValue>,
+}
+
+impl DiffRow {
+    pub fn status(&self) -> DiffStatus {
+        match (&self.left, &self.right) {
+            (Some(left), Some(right)) if left == right => DiffStatus::Unchanged,
+            (Some(_), Some(_)) => DiffStatus::Changed,
+            (None, _) => DiffStatus::Added,
+            (_, None) => DiffStatus::Removed,
+        }
+    }
+
+    pub fn value(&self, side: DiffSide) -> Option<&JsonValue> {
+        match side {
+            DiffSide::Left => self.left.as_ref(),
+            DiffSide::Right => self.right.as_ref(),
+        }
+    }
+}
+
+/// The state of the side by side comparison of two documents
+pub struct DiffView {
+    pub left: DiffDocument,
+    pub right: DiffDocument,
+    /// The side values are copied from
+    pub focus: DiffSide,
+    /// The keys leading from the root of both documents to the objects being compared
+    pub path: Vec<String>,
+    pub rows: Vec<DiffRow>,
+    pub row_list_ui_state: ListState,
+    /// Set once quitting with unsaved changes has been warned about
+    pub quit_requested: bool,
+    /// The side changed by each copy and its document before the copy
+    history: History<(DiffSide, JsonData)>,
+}
+
+impl DiffView {
+    pub fn new(left: DiffDocument, right: DiffDocument) -> DiffView {
+        let mut result = DiffView {
+            left,
+            right,
+            focus: DiffSide::Left,
+            path: Vec::new(),
+            rows: Vec::new(),
+            row_list_ui_state: ListState::default(),
+            quit_requested: false,
+            history: History::default(),
+        };
+        result.refresh();
+        result.row_list_ui_state.select(
+            result
+                .rows
+                .iter()
+                .position(|row| row.status() != DiffStatus::Unchanged)
+                .or(if result.rows.is_empty() {
+                    None
+                } else {
+                    Some(0)
+                }),
+        );
+        result
+    }
+
+    pub fn document(&self, side: DiffSide) -> &DiffDocument {
+        match side {
+            DiffSide::Left => &self.left,
+            DiffSide::Right => &self.right,
+        }
+    }
+
+    fn document_mut(&mut self, side: DiffSide) -> &mut DiffDocument {
+        match side {
+            DiffSide::Left => &mut self.left,
+            DiffSide::Right => &mut self.right,
+        }
+    }
+
+    /// Rebuilds the rows for the objects at `path`. Rows follow the left
+    /// document's order, and keys only on the right are placed after the
+    /// key they follow in the right document.
+    pub fn refresh(&mut self) {
+        let left = object_at_path(&self.left.data, &self.path);
+        let right = object_at_path(&self.right.data, &self.path);
+
+        let mut rows: Vec<DiffRow> = left
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| DiffRow {
+                key: key.clone(),
+                left: Some(value.clone()),
+                right: right.and_then(|right| right.get(key)).cloned(),
+            })
+            .collect();
+
+        let mut insert_at = 0;
+        for (key, value) in right.into_iter().flatten() {
+            match rows.iter().position(|row| &row.key == key) {
+                Some(index) => insert_at = index + 1,
+                None => {
+                    rows.insert(
+                        insert_at,
+                        DiffRow {
+                            key: key.clone(),
+                            left: None,
+                            right: Some(value.clone()),
+                        },
+                    );
+                    insert_at += 1;
+                }
+            }
+        }
+
+        self.rows = rows;
+
+        if let Some(selected) = self.row_list_ui_state.selected() {
+            self.row_list_ui_state.select(if self.rows.is_empty() {
+                None
+            } else {
+                Some(selected.min(self.rows.len() - 1))
+            });
+        }
+    }
+
+    pub fn selected_row(&self) -> Option<&DiffRow> {
+        self.row_list_ui_state
+            .selected()
+            .and_then(|index| self.rows.get(index))
+    }
+
+    pub fn selected_row_is_object(&self) -> bool {
+        self.selected_row().is_some_and(|row| {
+            matches!(row.left, Some(JsonValue::Object(_)))
+                || matches!(row.right, Some(JsonValue::Object(_)))
+        })
+    }
+
+    /// Compares the objects stored under the selected key
+    pub fn enter_selected(&mut self) {
+        if !self.selected_row_is_object() {
+            return;
+        }
+        let Some(key) = self.selected_row().map(|row| row.key.clone()) else {
+            return;
+        };
+
+        self.path.push(key);
+        self.refresh();
+        self.row_list_ui_state
+            .select(if self.rows.is_empty() { None } else { Some(0) });
+    }
+
+    /// Returns to the parent objects, selecting the key they are stored under
+    pub fn leave(&mut self) {
+        let Some(key) = self.path.pop() else {
+            return;
+        };
+
+        self.refresh();
+        self.row_list_ui_state
+            .select(self.rows.iter().position(|row| row.key == key));
+    }
+
+    /// Selects the next row after the selected one that differs between the sides
+    pub fn select_next_difference(&mut self) {
+        let start = self
+            .row_list_ui_state
+            .selected()
+            .map_or(0, |index| index + 1);
+        if let Some(offset) = self
+            .rows
+            .iter()
+            .skip(start)
+            .position(|row| row.status() != DiffStatus::Unchanged)
+        {
+            self.row_list_ui_state.select(Some(start + offset));
+        }
+    }
+
+    /// Makes the other side's value for the selected key match the focused side,
+    /// removing it from the other side if the focused side has no such key.
+    /// Returns the key that was copied.
+    pub fn copy_selected_to_other_side(&mut self) -> Result<String, DiffCopyError> {
+        let row = self.selected_row().ok_or(DiffCopyError::NothingSelected)?;
+        let key = row.key.clone();
+        let value = row.value(self.focus).cloned();
+
+        let path = self.path.clone();
+        let side = self.focus.other();
+        let target = self.document_mut(side);
+        let snapshot = target.data.clone();
+        let target_object = object_at_path_mut(&mut target.data, &path)?;
+        match value {
+            Some(value) => {
+                target_object.insert(key.clone(), value);
+            }
+            None => {
+                target_object.shift_remove(&key);
+            }
+        }
+        target.unsaved = true;
+
+        self.history.record((side, snapshot));
+        self.refresh();
+        Ok(key)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    /// Restores the side changed by the last copy, returns whether there was anything to undo
+    pub fn undo(&mut self) -> bool {
+        let undone = self
+            .history
+            .undo(|snapshot| restore(&mut self.left, &mut self.right, snapshot));
+        self.refresh();
+        undone
+    }
+
+    /// Makes the last undone copy again, returns whether there was anything to redo
+    pub fn redo(&mut self) -> bool {
+        let redone = self
+            .history
+            .redo(|snapshot| restore(&mut self.left, &mut self.right, snapshot));
+        self.refresh();
+        redone
+    }
+
+    /// Writes one side back to its file, returning the number of bytes written
+    pub fn save(&mut self, side: DiffSide) -> Result<usize, AppError> {
+        let document = self.document_mut(side);
+        let serialized = FileFormat::from_path(&document.file)
+            .serialize_with_layout(&document.data, &document.layout)
+            .map_err(|e| AppError::UnableToSave(AppWriteError::Format(e)))?;
+
+        fs::write(&document.file, &serialized)
+            .map_err(|e| AppError::UnableToSave(AppWriteError::Io(e)))?;
+        document.unsaved = false;
+
+        Ok(serialized.len())
+    }
+
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.left.unsaved || self.right.unsaved
+    }
+
+    pub fn difference_count(&self) -> usize {
+        self.rows
+            .iter()
+            .filter(|row| row.status() != DiffStatus::Unchanged)
+            .count()
+    }
+}
+
+/// Swaps in a snapshot of one side, returning a snapshot of the document it replaced
+fn restore(
+    left: &mut DiffDocument,
+    right: &mut DiffDocument,
+    (side, snapshot): (DiffSide, JsonData),
+) -> (DiffSide, JsonData) {
+    let document = match side {
+        DiffSide::Left => left,
+        DiffSide::Right => right,
+    };
+    document.unsaved = true;
+    (side, std::mem::replace(&mut document.data, snapshot))
+}
+
+/// The object at `path`, or `None` if a key is missing or leads to something else
+fn object_at_path<'a>(data: &'a JsonData, path: &[String]) -> Option<&'a JsonData> {
+    path.iter()
+        .try_fold(data, |object, key| match object.get(key) {
+            Some(JsonValue::Object(child)) => Some(child),
+            _ => None,
+        })
+}
+
+/// The object at `path`, creating any objects that are missing along the way so
+/// a value can be copied into it. Other values in the way are left alone.
+fn object_at_path_mut<'a>(
+    data: &'a mut JsonData,
+    path: &[String],
+) -> Result<&'a mut JsonData, DiffCopyError> {
+    let mut object = data;
+    for (depth, key) in path.iter().enumerate() {
+        let child = object
+            .entry(key.clone())
+            .or_insert_with(|| JsonValue::Object(JsonData::new()));
+        object = match child {
+            JsonValue::Object(child) => child,
+            _ => {
+                return Err(DiffCopyError::NotAnObject(display_path(&path_from_keys(
+                    &path[..=depth],
+                ))))
+            }
+        };
+    }
+    Ok(object)
+}
+
+#[derive(Debug)]
+pub enum DiffCopyError {
+    NothingSelected,
+    /// The path of a value on the other side that would have to be replaced with an object
+    NotAnObject(String),
+}
+
+impl Display for DiffCopyError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            DiffCopyError::NothingSelected => write!(f, "No key is selected"),
+            DiffCopyError::NotAnObject(path) => write!(
+                f,
+                "Nothing was copied, {path} on the other side is not an object"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DiffCopyError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::json_data_from_serde;
+
+    fn document(file: &str, json: &str) -> DiffDocument {
+        let data = match serde_json::from_str(json).unwrap() {
+            serde_json::Value::Object(data) => json_data_from_serde(data),
+            _ => panic!("test documents must be objects"),
+        };
+        DiffDocument {
+            file: file.to_string(),
+            data,
+            layout: JsoncLayout::default(),
+            unsaved: false,
+        }
+    }
+
+    fn diff_view(left: &str, right: &str) -> DiffView {
+        DiffView::new(document("left.json", left), document("right.json", right))
+    }
+
+    /// Each row's key and whether it is on the left, the right, or both
+    fn rows(view: &DiffView) -> Vec<String> {
+        view.rows
+            .iter()
+            .map(|row| {
+                let status = match row.status() {
+                    DiffStatus::Unchanged => "=",
+                    DiffStatus::Added => "+",
+                    DiffStatus::Removed => "-",
+                    DiffStatus::Changed => "~",
+                };
+                format!("{status}{}", row.key)
+            })
+            .collect()
+    }
+
+    fn select(view: &mut DiffView, key: &str) {
+        let index = view.rows.iter().position(|row| row.key == key);
+        view.row_list_ui_state.select(index);
+    }
+
+    #[test]
+    fn aligns_keys_only_on_the_right_after_the_key_they_follow() {
+        let view = diff_view(
+            r#"{"a": 1, "b": 2, "d": 4}"#,
+            r#"{"first": 0, "a": 1, "c": 3, "d": 5, "e": 6}"#,
+        );
+        assert_eq!(rows(&view), ["+first", "=a", "+c", "-b", "~d", "+e"]);
+        assert_eq!(view.difference_count(), 5);
+        // the first difference is selected
+        assert_eq!(view.row_list_ui_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn compares_nested_objects() {
+        let mut view = diff_view(
+            r#"{"o": {"x": 1}, "n": 1}"#,
+            r#"{"o": {"x": 2, "y": 3}, "n": 1}"#,
+        );
+        select(&mut view, "o");
+        view.enter_selected();
+        assert_eq!(view.path, ["o"]);
+        assert_eq!(rows(&view), ["~x", "+y"]);
+
+        view.leave();
+        assert_eq!(rows(&view), ["~o", "=n"]);
+        assert_eq!(view.selected_row().map(|row| row.key.as_str()), Some("o"));
+    }
+
+    #[test]
+    fn copies_and_removes_values_across_and_undoes_the_copies() {
+        let mut view = diff_view(r#"{"a": 1, "b": 2}"#, r#"{"a": 3, "c": 4}"#);
+
+        select(&mut view, "a");
+        assert_eq!(view.copy_selected_to_other_side().unwrap(), "a");
+        select(&mut view, "c");
+        view.copy_selected_to_other_side().unwrap();
+        assert_eq!(rows(&view), ["=a", "-b"]);
+        assert!(view.right.unsaved);
+        assert!(!view.left.unsaved);
+
+        assert!(view.undo());
+        assert_eq!(rows(&view), ["=a", "+c", "-b"]);
+        assert!(view.undo());
+        assert_eq!(rows(&view), ["~a", "+c", "-b"]);
+        assert!(!view.undo());
+
+        assert!(view.redo());
+        assert_eq!(rows(&view), ["=a", "+c", "-b"]);
+    }
+
+    #[test]
+    fn copies_into_objects_missing_on_the_other_side() {
+        let mut view = diff_view(r#"{"o": {"x": 1}}"#, r#"{}"#);
+        select(&mut view, "o");
+        view.enter_selected();
+        view.copy_selected_to_other_side().unwrap();
+        assert_eq!(
+            serde_json::to_string(&view.right.data).unwrap(),
+            r#"{"o":{"x":1}}"#
+        );
+    }
+
+    #[test]
+    fn refuses_to_copy_into_a_value_that_is_not_an_object() {
+        let mut view = diff_view(r#"{"o": {"x": 1}}"#, r#"{"o": [1]}"#);
+        select(&mut view, "o");
+        view.enter_selected();
+        assert_eq!(
+            view.copy_selected_to_other_side().unwrap_err().to_string(),
+            "Nothing was copied, o on the other side is not an object"
+        );
+        assert_eq!(
+            serde_json::to_string(&view.right.data).unwrap(),
+            r#"{"o":[1]}"#
+        );
+        assert!(!view.right.unsaved);
+        assert!(!view.can_undo());
+
+        view.row_list_ui_state.select(None);
+        assert!(matches!(
+            view.copy_selected_to_other_side(),
+            Err(DiffCopyError::NothingSelected)
+        ));
+    }
+}
@@ -0,0 +1,40 @@
+use std::io::Write;
+use std::{env, fs, io, process::Command};
+
+use tempfile::Builder;
+
+/// Opens `text` in the user's `$VISUAL` or `$EDITOR` (falling back to `vi`)
+/// and returns the contents of the file once the editor exits.
+/// The terminal must already be restored before this is called.
+pub fn edit_externally(text: &str) -> Result<String, io::Error> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    // editors are commonly configured with arguments, eg: "code --wait"
+    let mut editor_parts = editor.split_whitespace();
+    let program = editor_parts
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "$EDITOR is empty"))?;
+
+    // a new file with a random name, so nothing placed at a predictable path is followed or
+    // overwritten. It is removed when dropped, once the edited text has been read.
+    let mut temp_file = Builder::new()
+        .prefix("rs-ratatui-json-editor-")
+        .suffix(".json")
+        .tempfile()?;
+    temp_file.write_all(text.as_bytes())?;
+    temp_file.flush()?;
+
+    let status = Command::new(program)
+        .args(editor_parts)
+        .arg(temp_file.path())
+        .status();
+
+    match status {
+        // editors may save by replacing the file, so it is read again by its path
+        Ok(status) if status.success() => fs::read_to_string(temp_file.path()),
+        Ok(status) => Err(io::Error::other(format!("{program} exited with {status}"))),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,346 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use ratatui::widgets::ListState;
+use regex::Regex;
+
+use crate::app::{JsonData, JsonValue};
+use crate::path::{object_at_mut, value_at_mut, PathSegment};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SearchTarget {
+    Keys,
+    Values,
+    Both,
+}
+
+impl SearchTarget {
+    fn includes_keys(&self) -> bool {
+        matches!(self, SearchTarget::Keys | SearchTarget::Both)
+    }
+
+    fn includes_values(&self) -> bool {
+        matches!(self, SearchTarget::Values | SearchTarget::Both)
+    }
+
+    pub fn next(&self) -> SearchTarget {
+        match self {
+            SearchTarget::Both => SearchTarget::Keys,
+            SearchTarget::Keys => SearchTarget::Values,
+            SearchTarget::Values => SearchTarget::Both,
+        }
+    }
+}
+
+impl Display for SearchTarget {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            SearchTarget::Keys => write!(f, "keys"),
+            SearchTarget::Values => write!(f, "string values"),
+            SearchTarget::Both => write!(f, "keys and string values"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SearchScope {
+    Document,
+    /// Only the object currently shown in the main list and everything inside it
+    Subtree,
+}
+
+impl Display for SearchScope {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            SearchScope::Document => write!(f, "whole document"),
+            SearchScope::Subtree => write!(f, "current object"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FindReplaceField {
+    Find,
+    Replace,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MatchLocation {
+    Key,
+    Value,
+}
+
+pub struct FindMatch {
+    /// For keys, the path to the value stored under the key
+    pub path: Vec<PathSegment>,
+    pub location: MatchLocation,
+    pub before: String,
+    pub after: String,
+    /// `None` until the user has accepted or skipped the match
+    pub accepted: Option<bool>,
+}
+
+/// The state of the find and replace popup
+pub struct FindReplace {
+    pub find_input: String,
+    pub replace_input: String,
+    pub focus: FindReplaceField,
+    pub use_regex: bool,
+    pub target: SearchTarget,
+    pub scope: SearchScope,
+    /// Set once a search has been run, while the matches are being reviewed
+    pub matches: Option<Vec<FindMatch>>,
+    pub match_list_ui_state: ListState,
+    pub error: Option<String>,
+}
+
+impl FindReplace {
+    pub fn new() -> FindReplace {
+        FindReplace {
+            find_input: String::new(),
+            replace_input: String::new(),
+            focus: FindReplaceField::Find,
+            use_regex: false,
+            target: SearchTarget::Both,
+            scope: SearchScope::Document,
+            matches: None,
+            match_list_ui_state: ListState::default(),
+            error: None,
+        }
+    }
+
+    /// Collects every match inside `data`, which is found at `base_path` in the document
+    pub fn find_matches(
+        &self,
+        data: &JsonData,
+        base_path: &[PathSegment],
+    ) -> Result<Vec<FindMatch>, FindReplaceError> {
+        if self.find_input.is_empty() {
+            return Err(FindReplaceError::EmptyPattern);
+        }
+
+        let pattern = if self.use_regex {
+            Pattern::Regex(
+                Regex::new(&self.find_input)
+                    .map_err(|e| FindReplaceError::InvalidRegex(e.to_string()))?,
+            )
+        } else {
+            Pattern::Literal(self.find_input.clone())
+        };
+
+        let mut matches = Vec::new();
+        self.collect_object_matches(&pattern, data, &mut base_path.to_vec(), &mut matches);
+        Ok(matches)
+    }
+
+    fn collect_object_matches(
+        &self,
+        pattern: &Pattern,
+        data: &JsonData,
+        path: &mut Vec<PathSegment>,
+        matches: &mut Vec<FindMatch>,
+    ) {
+        for (key, value) in data {
+            path.push(PathSegment::Key(key.clone()));
+
+            if self.target.includes_keys() {
+                if let Some(after) = pattern.replace(key, &self.replace_input) {
+                    matches.push(FindMatch {
+                        path: path.clone(),
+                        location: MatchLocation::Key,
+                        before: key.clone(),
+                        after,
+                        accepted: None,
+                    });
+                }
+            }
+            self.collect_value_matches(pattern, value, path, matches);
+
+            path.pop();
+        }
+    }
+
+    fn collect_value_matches(
+        &self,
+        pattern: &Pattern,
+        value: &JsonValue,
+        path: &mut Vec<PathSegment>,
+        matches: &mut Vec<FindMatch>,
+    ) {
+        match value {
+            JsonValue::String(s) if self.target.includes_values() => {
+                if let Some(after) = pattern.replace(s, &self.replace_input) {
+                    matches.push(FindMatch {
+                        path: path.clone(),
+                        location: MatchLocation::Value,
+                        before: s.clone(),
+                        after,
+                        accepted: None,
+                    });
+                }
+            }
+            JsonValue::Object(data) => self.collect_object_matches(pattern, data, path, matches),
+            JsonValue::Array(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    path.push(PathSegment::Index(index));
+                    self.collect_value_matches(pattern, item, path, matches);
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+enum Pattern {
+    Literal(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// Replaces every occurrence of the pattern, or returns `None` if nothing changed.
+    /// Regex replacements can refer to capture groups, eg: `$1`
+    fn replace(&self, text: &str, replacement: &str) -> Option<String> {
+        match self {
+            Pattern::Literal(literal) => text
+                .contains(literal.as_str())
+                .then(|| text.replace(literal.as_str(), replacement)),
+            Pattern::Regex(regex) => regex
+                .is_match(text)
+                .then(|| regex.replace_all(text, replacement).into_owned()),
+        }
+        // a replacement that changes nothing is not worth reviewing
+        .filter(|replaced| replaced != text)
+    }
+}
+
+/// Applies the accepted matches to the document. Returns how many were
+/// replaced and the keys that could not be renamed because the new key was taken.
+pub fn apply_matches(data: &mut JsonData, matches: &[FindMatch]) -> (usize, Vec<String>) {
+    let accepted = matches.iter().filter(|m| m.accepted == Some(true));
+    let (key_matches, value_matches): (Vec<&FindMatch>, Vec<&FindMatch>) =
+        accepted.partition(|m| m.location == MatchLocation::Key);
+
+    let mut replaced = 0;
+    let mut collisions = Vec::new();
+
+    // values are found through the original keys, so they are replaced before any renaming
+    for value_match in value_matches {
+        if let Some(value) = value_at_mut(data, &value_match.path) {
+            *value = JsonValue::String(value_match.after.clone());
+            replaced += 1;
+        }
+    }
+
+    // renaming the deepest keys first keeps the paths of the remaining keys valid
+    let mut key_matches = key_matches;
+    key_matches.sort_by_key(|m| std::cmp::Reverse(m.path.len()));
+
+    for key_match in key_matches {
+        let Some((_, parent_path)) = key_match.path.split_last() else {
+            continue;
+        };
+        let Some(parent) = object_at_mut(data, parent_path) else {
+            continue;
+        };
+
+        if parent.contains_key(&key_match.after) {
+            collisions.push(key_match.before.clone());
+            continue;
+        }
+
+        if let Some((index, _, value)) = parent.shift_remove_full(&key_match.before) {
+            parent.shift_insert(index, key_match.after.clone(), value);
+            replaced += 1;
+        }
+    }
+
+    (replaced, collisions)
+}
+
+#[derive(Debug)]
+pub enum FindReplaceError {
+    EmptyPattern,
+    InvalidRegex(String),
+}
+
+impl Display for FindReplaceError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            FindReplaceError::EmptyPattern => write!(f, "Enter something to find"),
+            FindReplaceError::InvalidRegex(e) => write!(f, "Invalid regex: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for FindReplaceError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::json_data_from_serde;
+
+    fn document(json: &str) -> JsonData {
+        match serde_json::from_str(json).unwrap() {
+            serde_json::Value::Object(data) => json_data_from_serde(data),
+            _ => panic!("test documents must be objects"),
+        }
+    }
+
+    /// Finds `find` in the whole document and accepts every match
+    fn accepted_matches(data: &JsonData, find: &str, replace: &str) -> Vec<FindMatch> {
+        let mut find_replace = FindReplace::new();
+        find_replace.find_input = find.to_string();
+        find_replace.replace_input = replace.to_string();
+        let mut matches = find_replace.find_matches(data, &[]).unwrap();
+        for find_match in &mut matches {
+            find_match.accepted = Some(true);
+        }
+        matches
+    }
+
+    #[test]
+    fn reports_keys_that_would_be_renamed_to_an_existing_key() {
+        let mut data = document(r#"{"old_a": 1, "new_a": 2, "old_b": 3}"#);
+        let matches = accepted_matches(&data, "old", "new");
+
+        let (replaced, collisions) = apply_matches(&mut data, &matches);
+
+        assert_eq!(replaced, 1);
+        assert_eq!(collisions, vec!["old_a".to_string()]);
+        assert_eq!(
+            serde_json::to_string(&data).unwrap(),
+            r#"{"old_a":1,"new_a":2,"new_b":3}"#
+        );
+    }
+
+    #[test]
+    fn renames_keys_nested_under_a_renamed_key() {
+        let mut data = document(r#"{"item": {"item_id": 1, "name": "item"}, "other": 2}"#);
+        let matches = accepted_matches(&data, "item", "entry");
+
+        let (replaced, collisions) = apply_matches(&mut data, &matches);
+
+        assert_eq!(replaced, 3);
+        assert!(collisions.is_empty());
+        assert_eq!(
+            serde_json::to_string(&data).unwrap(),
+            r#"{"entry":{"entry_id":1,"name":"entry"},"other":2}"#
+        );
+    }
+
+    #[test]
+    fn leaves_skipped_matches_unchanged() {
+        let mut data = document(r#"{"item": "item"}"#);
+        let mut matches = accepted_matches(&data, "item", "entry");
+        for find_match in &mut matches {
+            find_match.accepted = Some(find_match.location == MatchLocation::Value);
+        }
+
+        let (replaced, collisions) = apply_matches(&mut data, &matches);
+
+        assert_eq!(replaced, 1);
+        assert!(collisions.is_empty());
+        assert_eq!(serde_json::to_string(&data).unwrap(), r#"{"item":"entry"}"#);
+    }
+}
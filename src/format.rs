@@ -0,0 +1,900 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use clap::ValueEnum;
+use indexmap::IndexSet;
+use toml::value::Datetime;
+use yaml_rust2::parser::{MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::{Event, Yaml, YamlEmitter};
+
+use crate::app::{JsonData, JsonValue, JsonValueType};
+use crate::convert::convert_value;
+use crate::jsonc::{self, JsoncLayout};
+use crate::path::{display_path, is_identifier, PathSegment};
+
+/// The file formats documents can be read from and written to,
+/// all of them are edited as the same `JsonValue` model
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum FileFormat {
+    Json,
+    /// JSON with comments and trailing commas
+    Jsonc,
+    /// JSON with unquoted keys, single quoted strings, hex numbers, `Infinity` and more.
+    /// Keys are written unquoted wherever they can be, however they were quoted when read
+    Json5,
+    Yaml,
+    Toml,
+    /// JSON Lines, one JSON value per line
+    Ndjson,
+    /// A table with a header row, holding an array of flat objects.
+    /// Only read and written by `convert`, the editor cannot open it
+    Csv,
+}
+
+impl FileFormat {
+    /// The file extensions of the format, the first being the one new files are given
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            FileFormat::Json => &["json"],
+            FileFormat::Jsonc => &["jsonc"],
+            FileFormat::Json5 => &["json5"],
+            FileFormat::Yaml => &["yaml", "yml"],
+            FileFormat::Toml => &["toml"],
+            FileFormat::Ndjson => &["jsonl", "ndjson"],
+            FileFormat::Csv => &["csv"],
+        }
+    }
+
+    /// Guesses the format of a file from its extension, defaulting to JSON
+    pub fn from_path(path: &str) -> FileFormat {
+        let Some(extension) = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase)
+        else {
+            return FileFormat::Json;
+        };
+
+        FileFormat::value_variants()
+            .iter()
+            .copied()
+            .find(|format| format.extensions().contains(&extension.as_str()))
+            .unwrap_or(FileFormat::Json)
+    }
+
+    /// Whether the format holds a single document rather than a list of records
+    pub fn holds_document(self) -> bool {
+        !matches!(self, FileFormat::Ndjson | FileFormat::Csv)
+    }
+
+    /// The format `offset` places away in the list of formats that hold a
+    /// document, wrapping around at either end
+    pub fn cycle(self, offset: isize) -> FileFormat {
+        let formats = FileFormat::value_variants()
+            .iter()
+            .copied()
+            .filter(|format| format.holds_document())
+            .collect::<Vec<_>>();
+        let index = formats
+            .iter()
+            .position(|format| *format == self)
+            .unwrap_or(0);
+        formats[(index as isize + offset).rem_euclid(formats.len() as isize) as usize]
+    }
+
+    /// Gives a path of this format the extension of another, leaving
+    /// paths with an extension this format doesn't use unchanged
+    pub fn swap_extension(self, path: &str, format: FileFormat) -> String {
+        let path = Path::new(path);
+        let has_own_extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                self.extensions()
+                    .contains(&extension.to_ascii_lowercase().as_str())
+            });
+
+        if has_own_extension && self != format {
+            path.with_extension(format.extensions()[0])
+                .to_string_lossy()
+                .to_string()
+        } else {
+            path.to_string_lossy().to_string()
+        }
+    }
+
+    /// Reads a document, which must have an object at its root
+    pub fn parse(self, text: &str) -> Result<JsonData, FormatError> {
+        if !self.holds_document() {
+            return Err(FormatError::NotADocument(self));
+        }
+
+        match self.parse_value(text)? {
+            JsonValue::Object(data) => Ok(data),
+            _ => Err(FormatError::NotAnObject(self)),
+        }
+    }
+
+    /// Reads a document along with the layout of its comments, which only JSONC has
+    pub fn parse_with_layout(self, text: &str) -> Result<(JsonData, JsoncLayout), FormatError> {
+        match self {
+            FileFormat::Jsonc => jsonc::parse(text),
+            _ => Ok((self.parse(text)?, JsoncLayout::default())),
+        }
+    }
+
+    /// Reads a value of any kind, JSON Lines and CSV files being read as an array of their records
+    pub fn parse_value(self, text: &str) -> Result<JsonValue, FormatError> {
+        match self {
+            FileFormat::Json => serde_json::from_str(text)
+                .map(JsonValue::from_serde)
+                .map_err(FormatError::Json),
+            FileFormat::Jsonc => jsonc::parse_value(text).map(|(value, _)| value),
+            FileFormat::Json5 => json5::from_str(text)
+                .map_err(|e| FormatError::Syntax(FileFormat::Json5, e.to_string())),
+            FileFormat::Yaml => parse_yaml(text),
+            FileFormat::Toml => parse_toml(text),
+            FileFormat::Ndjson => parse_ndjson(text),
+            FileFormat::Csv => parse_csv(text),
+        }
+    }
+
+    /// Writes a document the way it is saved to disk
+    pub fn serialize(self, data: &JsonData) -> Result<String, FormatError> {
+        match self {
+            FileFormat::Json => {
+                self.check_numbers(data)?;
+                serde_json::to_string(data).map_err(FormatError::Json)
+            }
+            _ => self.serialize_value(&JsonValue::Object(data.clone())),
+        }
+    }
+
+    /// Writes a document the way it is saved to disk, putting the comments of
+    /// a JSONC document back in place
+    pub fn serialize_with_layout(
+        self,
+        data: &JsonData,
+        layout: &JsoncLayout,
+    ) -> Result<String, FormatError> {
+        match self {
+            FileFormat::Jsonc => {
+                self.check_numbers(data)?;
+                Ok(jsonc::write(&JsonValue::Object(data.clone()), layout))
+            }
+            _ => self.serialize(data),
+        }
+    }
+
+    /// Writes a value of any kind, an array being written to a JSON Lines file one
+    /// item per line and an array of objects to a CSV file one object per row
+    pub fn serialize_value(self, value: &JsonValue) -> Result<String, FormatError> {
+        self.check_finite(non_finite_number(value, &mut Vec::new()))?;
+        match self {
+            FileFormat::Json => serde_json::to_string(value).map_err(FormatError::Json),
+            FileFormat::Jsonc => Ok(jsonc::write(value, &JsoncLayout::default())),
+            FileFormat::Json5 => Ok(serialize_json5(value)),
+            FileFormat::Yaml => serialize_yaml(value),
+            FileFormat::Toml => serialize_toml(value),
+            FileFormat::Ndjson => serialize_ndjson(value),
+            FileFormat::Csv => serialize_csv(value),
+        }
+    }
+
+    /// Fails if the document holds numbers the format cannot write
+    pub fn check_numbers(self, data: &JsonData) -> Result<(), FormatError> {
+        self.check_finite(non_finite_in_object(data, &mut Vec::new()))
+    }
+
+    /// Fails if a value found at `path` holds numbers the format cannot write
+    pub fn check_value_numbers(
+        self,
+        value: &JsonValue,
+        path: &mut Vec<PathSegment>,
+    ) -> Result<(), FormatError> {
+        self.check_finite(non_finite_number(value, path))
+    }
+
+    /// Only JSON5 can write infinite and NaN numbers, which the others would have to
+    /// write as something else, such as the `null` JSON writes them as
+    fn check_finite(self, non_finite_path: Option<String>) -> Result<(), FormatError> {
+        match non_finite_path {
+            Some(path) if self != FileFormat::Json5 => Err(FormatError::Unrepresentable {
+                format: self,
+                path,
+                feature: "infinite and NaN numbers",
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes a document in a form meant to be read on screen
+    pub fn preview(self, data: &JsonData) -> Result<String, FormatError> {
+        match self {
+            FileFormat::Json => {
+                self.check_numbers(data)?;
+                serde_json::to_string_pretty(data).map_err(FormatError::Json)
+            }
+            _ => self.serialize(data),
+        }
+    }
+}
+
+/// The path of the first infinite or NaN number in the value, if it has one
+fn non_finite_number(value: &JsonValue, path: &mut Vec<PathSegment>) -> Option<String> {
+    match value {
+        JsonValue::Number(n) if !n.is_finite() => Some(display_path(path)),
+        JsonValue::Array(items) => items.iter().enumerate().find_map(|(index, item)| {
+            path.push(PathSegment::Index(index));
+            let found = non_finite_number(item, path);
+            path.pop();
+            found
+        }),
+        JsonValue::Object(data) => non_finite_in_object(data, path),
+        _ => None,
+    }
+}
+
+fn non_finite_in_object(data: &JsonData, path: &mut Vec<PathSegment>) -> Option<String> {
+    data.iter().find_map(|(key, value)| {
+        path.push(PathSegment::Key(key.clone()));
+        let found = non_finite_number(value, path);
+        path.pop();
+        found
+    })
+}
+
+/// Whether the text is a TOML date, time or datetime, such as `1979-05-27T07:32:00Z`
+pub fn is_datetime(text: &str) -> bool {
+    text.parse::<Datetime>().is_ok()
+}
+
+impl Display for FileFormat {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            FileFormat::Json => write!(f, "JSON"),
+            FileFormat::Jsonc => write!(f, "JSONC"),
+            FileFormat::Json5 => write!(f, "JSON5"),
+            FileFormat::Yaml => write!(f, "YAML"),
+            FileFormat::Toml => write!(f, "TOML"),
+            FileFormat::Ndjson => write!(f, "NDJSON"),
+            FileFormat::Csv => write!(f, "CSV"),
+        }
+    }
+}
+
+fn parse_ndjson(text: &str) -> Result<JsonValue, FormatError> {
+    let mut records = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(line).map_err(|e| {
+            FormatError::Syntax(FileFormat::Ndjson, format!("line {}: {e}", index + 1))
+        })?;
+        records.push(JsonValue::from_serde(record));
+    }
+    Ok(JsonValue::Array(records))
+}
+
+/// Anything other than an array is written as a file holding one record
+fn serialize_ndjson(value: &JsonValue) -> Result<String, FormatError> {
+    let records = match value {
+        JsonValue::Array(items) => items.iter().collect(),
+        _ => vec![value],
+    };
+
+    let mut output = String::new();
+    for record in records {
+        output.push_str(&serde_json::to_string(record).map_err(FormatError::Json)?);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+/// Each column's type is chosen from all of its cells, so a column of numbers
+/// with a few blank cells becomes numbers and nulls rather than strings
+fn parse_csv(text: &str) -> Result<JsonValue, FormatError> {
+    let csv_error = |e: csv::Error| FormatError::Syntax(FileFormat::Csv, e.to_string());
+
+    let mut reader = csv::Reader::from_reader(text.as_bytes());
+    let headers = reader.headers().map_err(csv_error)?.clone();
+    let rows = reader
+        .records()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(csv_error)?;
+
+    let column_types = (0..headers.len())
+        .map(|column| csv_column_type(rows.iter().map(|row| row.get(column).unwrap_or(""))))
+        .collect::<Vec<_>>();
+
+    let records = rows
+        .iter()
+        .map(|row| {
+            let data = headers
+                .iter()
+                .zip(row.iter())
+                .zip(&column_types)
+                .map(|((key, cell), value_type)| (key.to_string(), csv_cell(cell, *value_type)))
+                .collect();
+            JsonValue::Object(data)
+        })
+        .collect();
+    Ok(JsonValue::Array(records))
+}
+
+/// The narrowest type every non-blank cell of a column can be read as
+fn csv_column_type<'a>(cells: impl Iterator<Item = &'a str>) -> JsonValueType {
+    let filled = cells.filter(|cell| !cell.is_empty()).collect::<Vec<_>>();
+    if filled.is_empty() {
+        return JsonValueType::Null;
+    }
+
+    // codes such as `007` would lose their leading zeros as numbers
+    let has_leading_zero = |cell: &&str| {
+        let digits = cell.trim().trim_start_matches(['-', '+']);
+        digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.")
+    };
+
+    [JsonValueType::Number, JsonValueType::Boolean]
+        .into_iter()
+        .filter(|value_type| {
+            *value_type != JsonValueType::Number || !filled.iter().any(has_leading_zero)
+        })
+        .find(|value_type| {
+            filled.iter().all(|cell| {
+                convert_value(&JsonValue::String(cell.to_string()), *value_type).is_ok()
+            })
+        })
+        .unwrap_or(JsonValueType::String)
+}
+
+/// Blank cells are null, except in columns of strings where they are empty strings
+fn csv_cell(cell: &str, value_type: JsonValueType) -> JsonValue {
+    let text = JsonValue::String(cell.to_string());
+    match value_type {
+        JsonValueType::String => text,
+        _ if cell.is_empty() => JsonValue::Null,
+        _ => convert_value(&text, value_type).unwrap_or(text),
+    }
+}
+
+/// The columns are every key found in the records, in the order they are first seen
+fn serialize_csv(value: &JsonValue) -> Result<String, FormatError> {
+    let records = match value {
+        JsonValue::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| match item {
+                JsonValue::Object(data) => Ok(data),
+                _ => Err(FormatError::Unrepresentable {
+                    format: FileFormat::Csv,
+                    path: display_path(&[PathSegment::Index(index)]),
+                    feature: "records that are not objects",
+                }),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => return Err(FormatError::NotATable(FileFormat::Csv)),
+    };
+
+    let columns = records
+        .iter()
+        .flat_map(|data| data.keys().map(String::as_str))
+        .collect::<IndexSet<_>>();
+    if columns.is_empty() {
+        return Ok(String::new());
+    }
+
+    let csv_error = |e: csv::Error| FormatError::Syntax(FileFormat::Csv, e.to_string());
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(&columns).map_err(csv_error)?;
+    for data in records {
+        let row = columns
+            .iter()
+            .map(|column| match data.get(*column) {
+                None | Some(JsonValue::Null) => String::new(),
+                Some(JsonValue::String(s) | JsonValue::Datetime(s)) => s.clone(),
+                // nested values are written as their JSON source
+                Some(value) => serde_json::to_string(value).unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+        writer.write_record(&row).map_err(csv_error)?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| FormatError::Syntax(FileFormat::Csv, e.to_string()))?;
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+fn serialize_json5(value: &JsonValue) -> String {
+    let mut output = String::new();
+    write_json5(value, 0, &mut output);
+    output.push('\n');
+    output
+}
+
+/// Writes a value over indented lines, leaving keys that are identifiers unquoted.
+/// The parser does not say which keys were quoted, so quoted ones lose their quotes too.
+fn write_json5(value: &JsonValue, depth: usize, output: &mut String) {
+    let indent = "  ".repeat(depth + 1);
+    match value {
+        JsonValue::Object(data) if !data.is_empty() => {
+            output.push_str("{\n");
+            for (index, (key, item)) in data.iter().enumerate() {
+                output.push_str(&indent);
+                if is_identifier(key) {
+                    output.push_str(key);
+                } else {
+                    output.push_str(&serde_json::to_string(key).unwrap_or_default());
+                }
+                output.push_str(": ");
+                write_json5(item, depth + 1, output);
+                output.push_str(if index + 1 < data.len() { ",\n" } else { "\n" });
+            }
+            output.push_str(&"  ".repeat(depth));
+            output.push('}');
+        }
+        JsonValue::Array(items) if !items.is_empty() => {
+            output.push_str("[\n");
+            for (index, item) in items.iter().enumerate() {
+                output.push_str(&indent);
+                write_json5(item, depth + 1, output);
+                output.push_str(if index + 1 < items.len() { ",\n" } else { "\n" });
+            }
+            output.push_str(&"  ".repeat(depth));
+            output.push(']');
+        }
+        JsonValue::Number(n) if n.is_nan() => output.push_str("NaN"),
+        JsonValue::Number(n) if n.is_infinite() => {
+            output.push_str(if *n > 0.0 { "Infinity" } else { "-Infinity" })
+        }
+        _ => output.push_str(&serde_json::to_string(value).unwrap_or_default()),
+    }
+}
+
+fn parse_yaml(text: &str) -> Result<JsonValue, FormatError> {
+    let mut receiver = YamlReceiver::default();
+    Parser::new_from_str(text)
+        .load(&mut receiver, false)
+        .map_err(|e| FormatError::Syntax(FileFormat::Yaml, e.to_string()))?;
+
+    if let Some(error) = receiver.error {
+        return Err(error);
+    }
+    // an empty file holds no document
+    Ok(receiver
+        .root
+        .unwrap_or_else(|| JsonValue::Object(JsonData::new())))
+}
+
+/// A collection that is still being read
+enum YamlCollection {
+    Sequence(Vec<JsonValue>),
+    /// The pairs read so far, and the key waiting for its value
+    Mapping(JsonData, Option<String>),
+}
+
+/// Builds values from the parser's events, rejecting anything JSON has no equivalent for
+#[derive(Default)]
+struct YamlReceiver {
+    stack: Vec<YamlCollection>,
+    root: Option<JsonValue>,
+    error: Option<FormatError>,
+}
+
+impl YamlReceiver {
+    fn unsupported(&mut self, mark: Marker, feature: &'static str) {
+        self.error.get_or_insert(FormatError::Unsupported {
+            format: FileFormat::Yaml,
+            location: format!("line {} column {}", mark.line(), mark.col() + 1),
+            feature,
+        });
+    }
+
+    /// Whether the next value read would be used as a mapping key
+    fn expecting_key(&self) -> bool {
+        matches!(self.stack.last(), Some(YamlCollection::Mapping(_, None)))
+    }
+
+    fn push_value(&mut self, value: JsonValue) {
+        match self.stack.last_mut() {
+            None => self.root = Some(value),
+            Some(YamlCollection::Sequence(items)) => items.push(value),
+            Some(YamlCollection::Mapping(data, key)) => {
+                if let Some(key) = key.take() {
+                    data.insert(key, value);
+                }
+            }
+        }
+    }
+}
+
+impl MarkedEventReceiver for YamlReceiver {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        if self.error.is_some() {
+            return;
+        }
+
+        match event {
+            Event::Alias(_)
+            | Event::Scalar(_, _, 1.., _)
+            | Event::SequenceStart(1.., _)
+            | Event::MappingStart(1.., _) => {
+                self.unsupported(mark, "anchors and aliases");
+            }
+            Event::SequenceStart(..) | Event::MappingStart(..) if self.expecting_key() => {
+                self.unsupported(mark, "keys that are not strings");
+            }
+            Event::SequenceStart(..) => self.stack.push(YamlCollection::Sequence(Vec::new())),
+            Event::MappingStart(..) => self
+                .stack
+                .push(YamlCollection::Mapping(JsonData::new(), None)),
+            Event::SequenceEnd | Event::MappingEnd => {
+                let value = match self.stack.pop() {
+                    Some(YamlCollection::Sequence(items)) => JsonValue::Array(items),
+                    Some(YamlCollection::Mapping(data, _)) => JsonValue::Object(data),
+                    None => return,
+                };
+                self.push_value(value);
+            }
+            Event::Scalar(text, style, _, tag) => {
+                let is_string_tag = tag.is_some_and(|tag| tag.suffix == "str");
+                let value = match (style, is_string_tag) {
+                    (TScalarStyle::Plain, false) => match Yaml::from_str(&text) {
+                        Yaml::Integer(n) => JsonValue::Number(n as f64),
+                        Yaml::Real(n) => match n.parse::<f64>() {
+                            Ok(n) if n.is_finite() => JsonValue::Number(n),
+                            _ => {
+                                self.unsupported(mark, "infinite and NaN numbers");
+                                return;
+                            }
+                        },
+                        Yaml::Boolean(b) => JsonValue::Boolean(b),
+                        Yaml::Null => JsonValue::Null,
+                        _ => JsonValue::String(text),
+                    },
+                    _ => JsonValue::String(text),
+                };
+
+                if self.expecting_key() {
+                    let JsonValue::String(key) = value else {
+                        self.unsupported(mark, "keys that are not strings");
+                        return;
+                    };
+                    if let Some(YamlCollection::Mapping(_, pending_key)) = self.stack.last_mut() {
+                        *pending_key = Some(key);
+                    }
+                } else {
+                    self.push_value(value);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn serialize_yaml(value: &JsonValue) -> Result<String, FormatError> {
+    let mut output = String::new();
+    YamlEmitter::new(&mut output)
+        .dump(&to_yaml(value))
+        .map_err(|e| FormatError::Syntax(FileFormat::Yaml, format!("{e:?}")))?;
+
+    // the emitter always starts a document with a `---` marker, which a single document doesn't need
+    let body = output.strip_prefix("---").unwrap_or(&output).trim_start();
+    Ok(format!("{body}\n"))
+}
+
+fn to_yaml(value: &JsonValue) -> Yaml {
+    match value {
+        JsonValue::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => {
+            Yaml::Integer(*n as i64)
+        }
+        JsonValue::Number(n) => Yaml::Real(n.to_string()),
+        JsonValue::String(s) | JsonValue::Datetime(s) => Yaml::String(s.clone()),
+        JsonValue::Boolean(b) => Yaml::Boolean(*b),
+        JsonValue::Null => Yaml::Null,
+        JsonValue::Array(items) => Yaml::Array(items.iter().map(to_yaml).collect()),
+        JsonValue::Object(data) => Yaml::Hash(
+            data.iter()
+                .map(|(key, value)| (Yaml::String(key.clone()), to_yaml(value)))
+                .collect::<Hash>(),
+        ),
+    }
+}
+
+fn parse_toml(text: &str) -> Result<JsonValue, FormatError> {
+    let table: toml::Table =
+        toml::from_str(text).map_err(|e| FormatError::Syntax(FileFormat::Toml, e.to_string()))?;
+    from_toml(toml::Value::Table(table), &mut Vec::new())
+}
+
+/// Datetimes are read as their RFC 3339 text, which `to_toml` turns back into a datetime
+fn from_toml(value: toml::Value, path: &mut Vec<PathSegment>) -> Result<JsonValue, FormatError> {
+    Ok(match value {
+        toml::Value::String(s) => JsonValue::String(s),
+        toml::Value::Integer(n) => JsonValue::Number(n as f64),
+        toml::Value::Float(n) if n.is_finite() => JsonValue::Number(n),
+        toml::Value::Float(_) => {
+            return Err(FormatError::Unsupported {
+                format: FileFormat::Toml,
+                location: display_path(path),
+                feature: "infinite and NaN numbers",
+            })
+        }
+        toml::Value::Boolean(b) => JsonValue::Boolean(b),
+        toml::Value::Datetime(datetime) => JsonValue::Datetime(datetime.to_string()),
+        toml::Value::Array(items) => {
+            let mut result = Vec::with_capacity(items.len());
+            for (index, item) in items.into_iter().enumerate() {
+                path.push(PathSegment::Index(index));
+                result.push(from_toml(item, path)?);
+                path.pop();
+            }
+            JsonValue::Array(result)
+        }
+        toml::Value::Table(table) => {
+            let mut result = JsonData::new();
+            for (key, item) in table {
+                path.push(PathSegment::Key(key.clone()));
+                result.insert(key, from_toml(item, path)?);
+                path.pop();
+            }
+            JsonValue::Object(result)
+        }
+    })
+}
+
+/// A TOML file is a table, so only objects can be written at its root
+fn serialize_toml(value: &JsonValue) -> Result<String, FormatError> {
+    let toml::Value::Table(table) = to_toml(value, &mut Vec::new())? else {
+        return Err(FormatError::NotAnObject(FileFormat::Toml));
+    };
+    toml::to_string(&table).map_err(|e| FormatError::Syntax(FileFormat::Toml, e.to_string()))
+}
+
+fn to_toml(value: &JsonValue, path: &mut Vec<PathSegment>) -> Result<toml::Value, FormatError> {
+    Ok(match value {
+        JsonValue::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => {
+            toml::Value::Integer(*n as i64)
+        }
+        JsonValue::Number(n) => toml::Value::Float(*n),
+        JsonValue::String(s) => toml::Value::String(s.clone()),
+        JsonValue::Datetime(text) => match text.parse::<Datetime>() {
+            Ok(datetime) => toml::Value::Datetime(datetime),
+            Err(_) => {
+                return Err(FormatError::Unrepresentable {
+                    format: FileFormat::Toml,
+                    path: display_path(path),
+                    feature: "datetimes that are not RFC 3339",
+                })
+            }
+        },
+        JsonValue::Boolean(b) => toml::Value::Boolean(*b),
+        JsonValue::Null => {
+            return Err(FormatError::Unrepresentable {
+                format: FileFormat::Toml,
+                path: display_path(path),
+                feature: "null values",
+            })
+        }
+        JsonValue::Array(items) => {
+            let mut result = Vec::with_capacity(items.len());
+            for (index, item) in items.iter().enumerate() {
+                path.push(PathSegment::Index(index));
+                result.push(to_toml(item, path)?);
+                path.pop();
+            }
+            toml::Value::Array(result)
+        }
+        JsonValue::Object(data) => {
+            let mut result = toml::Table::new();
+            for (key, item) in data {
+                path.push(PathSegment::Key(key.clone()));
+                result.insert(key.clone(), to_toml(item, path)?);
+                path.pop();
+            }
+            toml::Value::Table(result)
+        }
+    })
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    Json(serde_json::Error),
+    Syntax(FileFormat, String),
+    NotAnObject(FileFormat),
+    /// The format holds many values rather than a single document
+    NotADocument(FileFormat),
+    /// The format can only hold an array of objects
+    NotATable(FileFormat),
+    /// Something the format allows that the editor's value model cannot hold
+    Unsupported {
+        format: FileFormat,
+        location: String,
+        feature: &'static str,
+    },
+    /// A value in the document the format has no way to write
+    Unrepresentable {
+        format: FileFormat,
+        path: String,
+        feature: &'static str,
+    },
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            FormatError::Json(e) => write!(f, "Invalid JSON: {e}"),
+            FormatError::Syntax(format, e) => write!(f, "Invalid {format}: {e}"),
+            FormatError::NotAnObject(format) => {
+                write!(f, "The {format} document must be an object at its root")
+            }
+            FormatError::NotADocument(FileFormat::Csv) => write!(
+                f,
+                "CSV files hold a table rather than a single document, use the `convert` command to turn one into JSON"
+            ),
+            FormatError::NotADocument(format) => write!(
+                f,
+                "{format} files hold a list of records rather than a single document"
+            ),
+            FormatError::NotATable(format) => {
+                write!(f, "{format} files can only hold an array of objects")
+            }
+            FormatError::Unsupported {
+                format,
+                location,
+                feature,
+            } => write!(
+                f,
+                "{format} at {location} uses {feature}, which JSON cannot represent"
+            ),
+            FormatError::Unrepresentable {
+                format,
+                path,
+                feature,
+            } => write!(
+                f,
+                "{format} cannot represent {feature}, found one at {path}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json5(text: &str) -> JsonValue {
+        FileFormat::Json5.parse_value(text).unwrap()
+    }
+
+    #[test]
+    fn cycles_through_the_formats_that_hold_a_document() {
+        let mut format = FileFormat::Json;
+        let mut seen = Vec::new();
+        for _ in 0..5 {
+            format = format.cycle(1);
+            seen.push(format.to_string());
+        }
+        assert_eq!(seen, ["JSONC", "JSON5", "YAML", "TOML", "JSON"]);
+        assert_eq!(FileFormat::Json.cycle(-1), FileFormat::Toml);
+    }
+
+    #[test]
+    fn writes_json5_that_reads_back_the_same() {
+        let value = json5("{a: [1, 'two', {b: null}], \"c d\": {}, e: [], f: -0.5, g: 0x10}");
+        let written = FileFormat::Json5.serialize_value(&value).unwrap();
+        assert_eq!(
+            written,
+            "{\n  a: [\n    1,\n    \"two\",\n    {\n      b: null\n    }\n  ],\n  \"c d\": {},\n  e: [],\n  f: -0.5,\n  g: 16\n}\n"
+        );
+        assert!(json5(&written) == value);
+    }
+
+    #[test]
+    fn unquotes_keys_that_are_identifiers() {
+        let value = json5("{'quoted': 1, \"_also\": 2, '1st': 3, 'a-b': 4}");
+        assert_eq!(
+            FileFormat::Json5.serialize_value(&value).unwrap(),
+            "{\n  quoted: 1,\n  _also: 2,\n  \"1st\": 3,\n  \"a-b\": 4\n}\n"
+        );
+    }
+
+    #[test]
+    fn writes_infinite_and_nan_numbers_only_to_json5() {
+        let value = json5("{a: {b: [1, -Infinity]}, c: NaN}");
+        assert_eq!(
+            FileFormat::Json5.serialize_value(&value).unwrap(),
+            "{\n  a: {\n    b: [\n      1,\n      -Infinity\n    ]\n  },\n  c: NaN\n}\n"
+        );
+
+        let JsonValue::Object(data) = &value else {
+            unreachable!()
+        };
+        for format in [
+            FileFormat::Json,
+            FileFormat::Jsonc,
+            FileFormat::Yaml,
+            FileFormat::Toml,
+            FileFormat::Ndjson,
+            FileFormat::Csv,
+        ] {
+            assert_eq!(
+                format.serialize(data).unwrap_err().to_string(),
+                format!("{format} cannot represent infinite and NaN numbers, found one at a.b[1]")
+            );
+        }
+        assert!(FileFormat::Json.preview(data).is_err());
+    }
+
+    fn csv(text: &str) -> String {
+        let value = FileFormat::Csv.parse_value(text).unwrap();
+        serde_json::to_string(&value).unwrap()
+    }
+
+    #[test]
+    fn infers_csv_column_types_from_every_cell() {
+        assert_eq!(
+            csv("id,count,flag,name\n007,1,true,a\n010,2.5,false,\n"),
+            r#"[{"id":"007","count":1,"flag":true,"name":"a"},{"id":"010","count":2.5,"flag":false,"name":""}]"#
+        );
+        assert_eq!(
+            csv("n,mixed\n1,1\n,x\n"),
+            r#"[{"n":1,"mixed":"1"},{"n":null,"mixed":"x"}]"#
+        );
+        assert_eq!(
+            csv("zero,empty\n0,\n-0.5,\n"),
+            r#"[{"zero":0,"empty":null},{"zero":-0.5,"empty":null}]"#
+        );
+    }
+
+    #[test]
+    fn writes_csv_that_reads_back_the_same() {
+        let text = "id,count,note\n007,1,\"a, b\"\n010,,\n";
+        let value = FileFormat::Csv.parse_value(text).unwrap();
+        let written = FileFormat::Csv.serialize_value(&value).unwrap();
+        assert_eq!(written, text);
+        assert!(FileFormat::Csv.parse_value(&written).unwrap() == value);
+    }
+
+    #[test]
+    fn writes_csv_columns_in_the_order_they_are_first_seen() {
+        let value = FileFormat::Json
+            .parse_value(r#"[{"a": 1}, {"b": [1], "a": null}]"#)
+            .unwrap();
+        assert_eq!(
+            FileFormat::Csv.serialize_value(&value).unwrap(),
+            "a,b\n1,\n,[1]\n"
+        );
+    }
+
+    #[test]
+    fn refuses_csv_that_is_not_an_array_of_objects() {
+        let error = |json: &str| {
+            let value = FileFormat::Json.parse_value(json).unwrap();
+            FileFormat::Csv
+                .serialize_value(&value)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error(r#"{"a": 1}"#),
+            "CSV files can only hold an array of objects"
+        );
+        assert_eq!(
+            error(r#"[{"a": 1}, 2]"#),
+            "CSV cannot represent records that are not objects, found one at [1]"
+        );
+        assert!(matches!(
+            FileFormat::Csv.parse("a\n1\n"),
+            Err(FormatError::NotADocument(FileFormat::Csv))
+        ));
+        assert!(FileFormat::Csv.parse_value("a,b\n1,2,3\n").is_err());
+    }
+}
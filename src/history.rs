@@ -0,0 +1,62 @@
+/// The most snapshots kept for undoing, older ones are discarded
+const MAX_HISTORY_LENGTH: usize = 100;
+
+/// Snapshots taken before each change, so that changes can be undone and
+/// redone. A snapshot is whatever is needed to put things back as they were,
+/// such as the whole document or a description of how to reverse the change.
+pub struct History<T> {
+    undo_stack: Vec<T>,
+    redo_stack: Vec<T>,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        History {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+        }
+    }
+}
+
+impl<T> History<T> {
+    /// Must be called with a snapshot from before a change is made.
+    /// Making a new change means the undone changes can no longer be redone.
+    pub fn record(&mut self, snapshot: T) {
+        if self.undo_stack.len() == MAX_HISTORY_LENGTH {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push(snapshot);
+        self.redo_stack.clear();
+    }
+
+    /// Puts back the snapshot from before the last change with `restore`, which
+    /// returns a snapshot of what it replaced. Returns whether there was anything to undo.
+    pub fn undo(&mut self, restore: impl FnOnce(T) -> T) -> bool {
+        match self.undo_stack.pop() {
+            Some(snapshot) => {
+                self.redo_stack.push(restore(snapshot));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reapplies the last undone change, returns whether there was anything to redo
+    pub fn redo(&mut self, restore: impl FnOnce(T) -> T) -> bool {
+        match self.redo_stack.pop() {
+            Some(snapshot) => {
+                self.undo_stack.push(restore(snapshot));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+}
@@ -0,0 +1,674 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::app::{JsonData, JsonValue};
+use crate::format::{FileFormat, FormatError};
+use crate::path::{to_json_pointer, PathSegment};
+
+/// The comments of a JSONC document and how it was laid out, so they can be
+/// written back in place. Comments are attached to the JSON Pointer of the
+/// nearest value as it was read, and are moved to wherever that value is when
+/// the document is written, so renamed keys and moved array items keep them.
+#[derive(Clone)]
+pub struct JsoncLayout {
+    /// The document as it was read, to find where its values have moved to
+    document: JsonValue,
+    /// Comments on the lines before a value
+    leading: HashMap<String, Vec<String>>,
+    /// A comment on the same line as the end of a value
+    trailing: HashMap<String, String>,
+    /// Comments after the last value of an object or array
+    closing: HashMap<String, Vec<String>>,
+    /// Comments after the end of the document
+    footer: Vec<String>,
+    indent: String,
+    /// The objects and arrays that had a comma after their last value
+    trailing_commas: HashSet<String>,
+}
+
+impl Default for JsoncLayout {
+    fn default() -> Self {
+        JsoncLayout {
+            document: JsonValue::Null,
+            leading: HashMap::new(),
+            trailing: HashMap::new(),
+            closing: HashMap::new(),
+            footer: Vec::new(),
+            indent: "  ".to_string(),
+            trailing_commas: HashSet::new(),
+        }
+    }
+}
+
+/// Reads a JSON document that may hold `//` and `/* */` comments and trailing commas
+pub fn parse(text: &str) -> Result<(JsonData, JsoncLayout), FormatError> {
+    match parse_value(text)? {
+        (JsonValue::Object(data), layout) => Ok((data, layout)),
+        _ => Err(FormatError::NotAnObject(FileFormat::Jsonc)),
+    }
+}
+
+/// Reads a value of any kind, along with the comments and layout around it
+pub fn parse_value(text: &str) -> Result<(JsonValue, JsoncLayout), FormatError> {
+    let mut parser = Parser {
+        text,
+        pos: 0,
+        path: Vec::new(),
+        pending_comments: Vec::new(),
+        layout: JsoncLayout::default(),
+    };
+    if let Some(indent) = detect_indent(text) {
+        parser.layout.indent = indent;
+    }
+
+    parser.skip_trivia()?;
+    parser.attach_pending(|layout| &mut layout.leading, String::new());
+    let value = parser.parse_value()?;
+    parser.take_trailing_comment(String::new())?;
+    parser.skip_trivia()?;
+    if parser.pos < text.len() {
+        return Err(parser.error("the end of the document"));
+    }
+    parser.layout.footer = std::mem::take(&mut parser.pending_comments);
+    parser.layout.document = value.clone();
+
+    Ok((value, parser.layout))
+}
+
+/// The whitespace the first indented line starts with
+fn detect_indent(text: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let content = line.trim_start_matches([' ', '\t']);
+        let indent = &line[..line.len() - content.len()];
+        if indent.is_empty() || content.is_empty() {
+            None
+        } else {
+            Some(indent.to_string())
+        }
+    })
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    /// The byte offset of the next character to read
+    pos: usize,
+    /// The path of the value being read
+    path: Vec<PathSegment>,
+    /// Comments read since the last value, waiting for a value to attach to
+    pending_comments: Vec<String>,
+    layout: JsoncLayout,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    fn error(&self, expected: &str) -> FormatError {
+        let before = &self.text[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |index| index + 1) + 1;
+        let found = match self.rest().chars().next() {
+            Some(c) => format!("`{c}`"),
+            None => "the end of the file".to_string(),
+        };
+        FormatError::Syntax(
+            FileFormat::Jsonc,
+            format!("expected {expected} at line {line} column {column}, found {found}"),
+        )
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), FormatError> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("`{}`", c as char)))
+        }
+    }
+
+    fn pointer(&self) -> String {
+        to_json_pointer(&self.path)
+    }
+
+    fn attach_pending(
+        &mut self,
+        comments: fn(&mut JsoncLayout) -> &mut HashMap<String, Vec<String>>,
+        pointer: String,
+    ) {
+        if !self.pending_comments.is_empty() {
+            let pending = std::mem::take(&mut self.pending_comments);
+            comments(&mut self.layout)
+                .entry(pointer)
+                .or_default()
+                .extend(pending);
+        }
+    }
+
+    /// Reads a comment starting at the current position, if there is one
+    fn read_comment(&mut self) -> Result<Option<String>, FormatError> {
+        let rest = self.rest();
+        let length = if rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            match body.find("*/") {
+                Some(end) => end + 4,
+                None => return Err(self.error("`*/` to close the comment")),
+            }
+        } else {
+            return Ok(None);
+        };
+
+        let comment = rest[..length].trim_end().to_string();
+        self.pos += length;
+        Ok(Some(comment))
+    }
+
+    /// Skips whitespace and comments, keeping the comments for the next value
+    fn skip_trivia(&mut self) -> Result<(), FormatError> {
+        loop {
+            let whitespace = self.rest().len() - self.rest().trim_start().len();
+            self.pos += whitespace;
+            match self.read_comment()? {
+                Some(comment) => self.pending_comments.push(comment),
+                None => return Ok(()),
+            }
+        }
+    }
+
+    fn skip_inline_whitespace(&mut self) {
+        let whitespace = self.rest().len() - self.rest().trim_start_matches([' ', '\t']).len();
+        self.pos += whitespace;
+    }
+
+    /// Attaches a comment on the same line as the value just read to it
+    fn take_trailing_comment(&mut self, pointer: String) -> Result<(), FormatError> {
+        self.skip_inline_whitespace();
+        if let Some(comment) = self.read_comment()? {
+            self.layout.trailing.insert(pointer, comment);
+        }
+        Ok(())
+    }
+
+    /// Reads the separator after a value in an object or array, returning whether it was a comma
+    fn finish_member(&mut self) -> Result<bool, FormatError> {
+        let pointer = self.pointer();
+        self.take_trailing_comment(pointer.clone())?;
+        self.skip_inline_whitespace();
+
+        if self.peek() != Some(b',') {
+            return Ok(false);
+        }
+        self.pos += 1;
+        if !self.layout.trailing.contains_key(&pointer) {
+            self.take_trailing_comment(pointer)?;
+        }
+        Ok(true)
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue, FormatError> {
+        match self.peek() {
+            Some(b'{') => self.parse_object(),
+            Some(b'[') => self.parse_array(),
+            Some(b'"') => self.parse_string().map(JsonValue::String),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            _ => {
+                for (literal, value) in [
+                    ("true", JsonValue::Boolean(true)),
+                    ("false", JsonValue::Boolean(false)),
+                    ("null", JsonValue::Null),
+                ] {
+                    if self.rest().starts_with(literal) {
+                        self.pos += literal.len();
+                        return Ok(value);
+                    }
+                }
+                Err(self.error("a value"))
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValue, FormatError> {
+        self.expect(b'{')?;
+        let pointer = self.pointer();
+        let mut data = JsonData::new();
+
+        self.skip_trivia()?;
+        let mut had_comma = false;
+        while self.peek() != Some(b'}') {
+            if !data.is_empty() && !had_comma {
+                return Err(self.error("`,` or `}`"));
+            }
+
+            let key = self.parse_string()?;
+            self.path.push(PathSegment::Key(key.clone()));
+            self.skip_trivia()?;
+            self.expect(b':')?;
+            self.skip_trivia()?;
+            // comments between a key and its value are kept with the ones before the key
+            self.attach_pending(|layout| &mut layout.leading, self.pointer());
+
+            let value = self.parse_value()?;
+            data.insert(key, value);
+            had_comma = self.finish_member()?;
+            self.path.pop();
+            self.skip_trivia()?;
+        }
+        self.pos += 1;
+
+        if had_comma {
+            self.layout.trailing_commas.insert(pointer.clone());
+        }
+        self.attach_pending(|layout| &mut layout.closing, pointer);
+        Ok(JsonValue::Object(data))
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValue, FormatError> {
+        self.expect(b'[')?;
+        let pointer = self.pointer();
+        let mut items = Vec::new();
+
+        self.skip_trivia()?;
+        let mut had_comma = false;
+        while self.peek() != Some(b']') {
+            if !items.is_empty() && !had_comma {
+                return Err(self.error("`,` or `]`"));
+            }
+
+            self.path.push(PathSegment::Index(items.len()));
+            self.attach_pending(|layout| &mut layout.leading, self.pointer());
+            let value = self.parse_value()?;
+            items.push(value);
+            had_comma = self.finish_member()?;
+            self.path.pop();
+            self.skip_trivia()?;
+        }
+        self.pos += 1;
+
+        if had_comma {
+            self.layout.trailing_commas.insert(pointer.clone());
+        }
+        self.attach_pending(|layout| &mut layout.closing, pointer);
+        Ok(JsonValue::Array(items))
+    }
+
+    fn parse_string(&mut self) -> Result<String, FormatError> {
+        if self.peek() != Some(b'"') {
+            return Err(self.error("a string"));
+        }
+
+        let bytes = self.text.as_bytes();
+        let mut end = self.pos + 1;
+        while end < bytes.len() && bytes[end] != b'"' {
+            end += if bytes[end] == b'\\' { 2 } else { 1 };
+        }
+        if end >= bytes.len() {
+            return Err(self.error("a closing `\"`"));
+        }
+
+        // the escapes are the same as JSON's
+        let literal = &self.text[self.pos..=end];
+        let string = serde_json::from_str(literal).map_err(|_| self.error("a valid string"))?;
+        self.pos = end + 1;
+        Ok(string)
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue, FormatError> {
+        let length = self
+            .rest()
+            .find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+            .unwrap_or(self.rest().len());
+
+        match serde_json::from_str::<serde_json::Number>(&self.rest()[..length]) {
+            Ok(n) => {
+                self.pos += length;
+                Ok(JsonValue::Number(n.as_f64().unwrap_or(0.0)))
+            }
+            Err(_) => Err(self.error("a number")),
+        }
+    }
+}
+
+impl JsoncLayout {
+    pub fn has_comments(&self) -> bool {
+        !(self.leading.is_empty()
+            && self.trailing.is_empty()
+            && self.closing.is_empty()
+            && self.footer.is_empty())
+    }
+
+    /// The layout with its comments moved from the paths of the document as it
+    /// was read to the paths the same values have in `value`
+    fn realign(&self, value: &JsonValue) -> JsoncLayout {
+        let mut moves = Vec::new();
+        match_paths(
+            &self.document,
+            value,
+            &mut Vec::new(),
+            &mut Vec::new(),
+            &mut moves,
+        );
+
+        let mut layout = JsoncLayout {
+            document: value.clone(),
+            leading: HashMap::new(),
+            trailing: HashMap::new(),
+            closing: HashMap::new(),
+            footer: self.footer.clone(),
+            indent: self.indent.clone(),
+            trailing_commas: HashSet::new(),
+        };
+        for (from, to) in moves {
+            if let Some(comments) = self.leading.get(&from) {
+                layout.leading.insert(to.clone(), comments.clone());
+            }
+            if let Some(comment) = self.trailing.get(&from) {
+                layout.trailing.insert(to.clone(), comment.clone());
+            }
+            if let Some(comments) = self.closing.get(&from) {
+                layout.closing.insert(to.clone(), comments.clone());
+            }
+            if self.trailing_commas.contains(&from) {
+                layout.trailing_commas.insert(to);
+            }
+        }
+        layout
+    }
+}
+
+/// Pairs the pointer of each value in `old` with the pointer of the same value
+/// in `new`. A key missing from `new` is taken to be renamed to a new key with
+/// an equal value, and array items are matched to equal items before falling
+/// back to the item at the same index.
+fn match_paths(
+    old: &JsonValue,
+    new: &JsonValue,
+    old_path: &mut Vec<PathSegment>,
+    new_path: &mut Vec<PathSegment>,
+    moves: &mut Vec<(String, String)>,
+) {
+    moves.push((to_json_pointer(old_path), to_json_pointer(new_path)));
+
+    let pairs: Vec<(PathSegment, &JsonValue, PathSegment, &JsonValue)> = match (old, new) {
+        (JsonValue::Object(old_data), JsonValue::Object(new_data)) => {
+            let mut renamed: Vec<&String> = old_data
+                .keys()
+                .filter(|key| !new_data.contains_key(*key))
+                .collect();
+            new_data
+                .iter()
+                .filter_map(|(key, new_value)| {
+                    let old_key = if old_data.contains_key(key) {
+                        key
+                    } else {
+                        let position = renamed
+                            .iter()
+                            .position(|old_key| old_data[*old_key] == *new_value)?;
+                        renamed.remove(position)
+                    };
+                    Some((
+                        PathSegment::Key(old_key.clone()),
+                        &old_data[old_key],
+                        PathSegment::Key(key.clone()),
+                        new_value,
+                    ))
+                })
+                .collect()
+        }
+        (JsonValue::Array(old_items), JsonValue::Array(new_items)) => {
+            let mut used = vec![false; old_items.len()];
+            let mut matches: Vec<Option<usize>> = new_items
+                .iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    let same_index = old_items.get(index) == Some(item) && !used[index];
+                    let found = if same_index {
+                        Some(index)
+                    } else {
+                        (0..old_items.len()).find(|&old| !used[old] && old_items[old] == *item)
+                    };
+                    if let Some(old) = found {
+                        used[old] = true;
+                    }
+                    found
+                })
+                .collect();
+            // items that were edited keep the comments of the item that was at their index
+            for (index, found) in matches.iter_mut().enumerate() {
+                if found.is_none() && index < old_items.len() && !used[index] {
+                    used[index] = true;
+                    *found = Some(index);
+                }
+            }
+            matches
+                .into_iter()
+                .enumerate()
+                .filter_map(|(index, found)| {
+                    let old = found?;
+                    Some((
+                        PathSegment::Index(old),
+                        &old_items[old],
+                        PathSegment::Index(index),
+                        &new_items[index],
+                    ))
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+
+    for (old_segment, old_value, new_segment, new_value) in pairs {
+        old_path.push(old_segment);
+        new_path.push(new_segment);
+        match_paths(old_value, new_value, old_path, new_path, moves);
+        old_path.pop();
+        new_path.pop();
+    }
+}
+
+/// Writes a document with the comments and layout it was read with
+pub fn write(value: &JsonValue, layout: &JsoncLayout) -> String {
+    let layout = &layout.realign(value);
+    let mut writer = Writer {
+        layout,
+        output: String::new(),
+        path: Vec::new(),
+    };
+
+    writer.write_comments(layout.leading.get(""), 0);
+    writer.write_value(value, 0);
+    if let Some(comment) = layout.trailing.get("") {
+        writer.output.push(' ');
+        writer.output.push_str(comment);
+    }
+    writer.output.push('\n');
+    writer.write_comments(Some(&layout.footer), 0);
+
+    writer.output
+}
+
+struct Writer<'a> {
+    layout: &'a JsoncLayout,
+    output: String,
+    path: Vec<PathSegment>,
+}
+
+impl Writer<'_> {
+    fn write_indent(&mut self, depth: usize) {
+        for _ in 0..depth {
+            self.output.push_str(&self.layout.indent);
+        }
+    }
+
+    fn write_comments(&mut self, comments: Option<&Vec<String>>, depth: usize) {
+        for comment in comments.into_iter().flatten() {
+            self.write_indent(depth);
+            self.output.push_str(comment);
+            self.output.push('\n');
+        }
+    }
+
+    fn write_value(&mut self, value: &JsonValue, depth: usize) {
+        let pointer = to_json_pointer(&self.path);
+        let closing = self.layout.closing.get(&pointer);
+
+        let (open, close, length) = match value {
+            JsonValue::Object(data) => ('{', '}', data.len()),
+            JsonValue::Array(items) => ('[', ']', items.len()),
+            _ => {
+                let literal = serde_json::to_string(value).unwrap_or_default();
+                self.output.push_str(&literal);
+                return;
+            }
+        };
+
+        self.output.push(open);
+        if length == 0 && closing.is_none() {
+            self.output.push(close);
+            return;
+        }
+        self.output.push('\n');
+
+        let members: Vec<(Option<&String>, &JsonValue)> = match value {
+            JsonValue::Object(data) => data.iter().map(|(key, value)| (Some(key), value)).collect(),
+            JsonValue::Array(items) => items.iter().map(|item| (None, item)).collect(),
+            _ => Vec::new(),
+        };
+        for (index, (key, member)) in members.into_iter().enumerate() {
+            self.path.push(match key {
+                Some(key) => PathSegment::Key(key.clone()),
+                None => PathSegment::Index(index),
+            });
+            let member_pointer = to_json_pointer(&self.path);
+
+            self.write_comments(self.layout.leading.get(&member_pointer), depth + 1);
+            self.write_indent(depth + 1);
+            if let Some(key) = key {
+                self.output
+                    .push_str(&serde_json::to_string(key).unwrap_or_default());
+                self.output.push_str(": ");
+            }
+            self.write_value(member, depth + 1);
+            if index + 1 < length || self.layout.trailing_commas.contains(&pointer) {
+                self.output.push(',');
+            }
+            if let Some(comment) = self.layout.trailing.get(&member_pointer) {
+                self.output.push(' ');
+                self.output.push_str(comment);
+            }
+            self.output.push('\n');
+
+            self.path.pop();
+        }
+
+        self.write_comments(closing, depth + 1);
+        self.write_indent(depth);
+        self.output.push(close);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = r#"// settings
+{
+  // the name
+  "name": "app", // trailing
+  "list": [
+    // first
+    1,
+    2, // second
+    3,
+  ],
+  "nested": {
+    "a": true
+    // closing
+  }
+}
+// footer
+"#;
+
+    fn edit(text: &str, change: impl FnOnce(&mut JsonData)) -> String {
+        let (mut data, layout) = parse(text).unwrap();
+        change(&mut data);
+        write(&JsonValue::Object(data), &layout)
+    }
+
+    fn error(text: &str) -> String {
+        match parse(text) {
+            Ok(_) => panic!("{text} should not parse"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn writes_an_unchanged_document_as_it_was_read() {
+        assert_eq!(edit(DOCUMENT, |_| {}), DOCUMENT);
+    }
+
+    #[test]
+    fn keeps_comments_on_renamed_keys() {
+        let written = edit(DOCUMENT, |data| {
+            let value = data.shift_remove("name").unwrap();
+            data.shift_insert(0, "title".to_string(), value);
+        });
+        assert!(written.contains("  // the name\n  \"title\": \"app\", // trailing\n"));
+    }
+
+    #[test]
+    fn keeps_comments_on_moved_and_remaining_array_items() {
+        let written = edit(DOCUMENT, |data| {
+            let Some(JsonValue::Array(items)) = data.get_mut("list") else {
+                unreachable!()
+            };
+            items.remove(0);
+            items.reverse();
+        });
+        assert!(written.contains("  \"list\": [\n    3,\n    2, // second\n  ],\n"));
+        assert!(!written.contains("// first"));
+    }
+
+    #[test]
+    fn keeps_trailing_commas_per_container() {
+        let written = edit(DOCUMENT, |data| {
+            data.insert("added".to_string(), JsonValue::Array(vec![JsonValue::Null]));
+        });
+        assert!(written.contains("    3,\n  ],"));
+        assert!(written.contains("    \"a\": true\n    // closing\n"));
+        assert!(written.contains("  \"added\": [\n    null\n  ]\n}"));
+    }
+
+    #[test]
+    fn drops_the_comments_of_removed_values() {
+        let written = edit(DOCUMENT, |data| {
+            data.shift_remove("name");
+        });
+        assert!(!written.contains("the name"));
+        assert!(!written.contains("// trailing"));
+        assert!(written.starts_with("// settings\n{\n  \"list\""));
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_their_position() {
+        assert_eq!(
+            error("{\n  \"a\": 1\n  \"b\": 2\n}"),
+            "Invalid JSONC: expected `,` or `}` at line 3 column 3, found `\"`"
+        );
+        assert_eq!(
+            error("{ /* open"),
+            "Invalid JSONC: expected `*/` to close the comment at line 1 column 3, found `/`"
+        );
+        assert_eq!(
+            error("{} extra"),
+            "Invalid JSONC: expected the end of the document at line 1 column 4, found `e`"
+        );
+        assert_eq!(
+            error("[1]"),
+            FormatError::NotAnObject(FileFormat::Jsonc).to_string()
+        );
+    }
+}
@@ -1,11 +1,20 @@
+use std::time::Duration;
 use std::{error::Error, io};
 
 use app::{
-    ActionBinding, App, AppError, AppScreen, Binding, EditFocus, ExitFocus, InputAction, TextField,
+    format_byte_size, ActionBinding, App, AppError, AppScreen, Binding, EditFocus, ExitFocus,
+    InputAction, JsonData, JsonValue, Severity, TextField,
 };
 use clap::Parser;
+use clipboard::copy_to_system_clipboard;
+use commands::{run_command, Command, CommandOutcome};
+use diff_view::DiffView;
+use external_editor::edit_externally;
+use find_replace::{FindReplace, FindReplaceField, SearchScope};
+use format::FileFormat;
 use ratatui::crossterm::event::{
-    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent,
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+    MouseButton, MouseEvent, MouseEventKind,
 };
 use ratatui::crossterm::execute;
 use ratatui::crossterm::terminal::{
@@ -13,57 +22,125 @@ use ratatui::crossterm::terminal::{
 };
 use ratatui::prelude::{Backend, CrosstermBackend};
 use ratatui::Terminal;
+use records::RecordList;
+use sort::SortOrder;
 use ui::ui;
 
 mod app;
+mod clipboard;
+mod commands;
+mod convert;
+mod diff_view;
+mod external_editor;
+mod find_replace;
+mod format;
+mod history;
+mod jsonc;
+mod merge;
+mod patch;
+mod path;
+mod query;
+mod records;
+mod sort;
+mod text_editor;
 mod ui;
 
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
 #[derive(Parser)]
-#[command(about)]
+#[command(about, args_conflicts_with_subcommands = true)]
 struct CliArgs {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The input file to read from
     input_file: Option<String>,
 
+    /// The format of the input file, guessed from its extension if not given
+    #[arg(long, value_name = "FORMAT")]
+    format: Option<FileFormat>,
+
     /// Whether to run in "dry" mode (no changes will be written to the output file)
     #[arg(long)]
     dry: bool,
+
+    /// Sort the keys of every object in the document whenever it is saved
+    #[arg(long, value_name = "ORDER")]
+    sort_on_save: Option<SortOrder>,
+
+    /// Also copy pairs to the system clipboard using OSC 52 escape
+    /// sequences, which works over SSH in terminals that support it
+    #[arg(long)]
+    osc52: bool,
+
+    /// Open with the value at this RFC 6901 JSON Pointer (eg: /servers/0/host) selected
+    #[arg(long, value_name = "POINTER")]
+    goto: Option<String>,
+
+    /// Also write the changes made in the session to this file as an RFC 6902
+    /// JSON Patch when saving, this still happens in dry mode. Not available
+    /// for JSON Lines files, which are edited one record at a time
+    #[arg(long, value_name = "FILE")]
+    emit_patch: Option<String>,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = CliArgs::parse();
 
-    let mut app = App::new(args.input_file)
-        .map_err(|e| {
-            eprintln!("{e}");
-            std::process::exit(1);
-        })
-        .unwrap();
+    let mut app = match args.command {
+        Some(command) => match run_command(command) {
+            Ok(CommandOutcome::Done(message)) => {
+                println!("{message}");
+                return Ok(());
+            }
+            Ok(CommandOutcome::OpenEditor(app)) => *app,
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        },
+        None => {
+            let mut app = App::new(args.input_file, args.format)
+                .map_err(|e| {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                })
+                .unwrap();
+            app.dry_run = args.dry;
+            app.sort_on_save = args.sort_on_save;
+            app.sync_system_clipboard = args.osc52;
+            if let Err(e) = app.set_patch_output_file(args.emit_patch) {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
 
-    // Prepare the terminal for the application
-    enable_raw_mode()?;
-    let mut stderr = io::stderr();
-    execute!(stderr, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stderr);
+            if let Some(pointer) = args.goto {
+                if let Err(e) = app.goto_pointer(&pointer) {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+            }
+
+            app
+        }
+    };
+
+    enter_tui()?;
+    let backend = CrosstermBackend::new(io::stderr());
     let mut terminal = Terminal::new(backend)?;
 
     // Run the application
     let app_result = run_app(&mut terminal, &mut app);
 
-    // Restore the terminal to its original state
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
+    leave_tui()?;
     terminal.show_cursor()?;
 
     match app_result {
-        Ok(should_save) => {
-            if !args.dry && should_save {
-                app.write()?;
+        Ok(saved) => {
+            if let (true, Some(status_message)) = (saved, &app.status_message) {
+                println!("{}", status_message.text);
             }
-            return Ok(());
+            Ok(())
         }
         Err(err) => {
             eprintln!("{:?}", err);
@@ -72,15 +149,83 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
 }
 
+/// Prepare the terminal for the application
+fn enter_tui() -> Result<(), io::Error> {
+    enable_raw_mode()?;
+    execute!(io::stderr(), EnterAlternateScreen, EnableMouseCapture)
+}
+
+/// Restore the terminal to its original state
+fn leave_tui() -> Result<(), io::Error> {
+    disable_raw_mode()?;
+    execute!(io::stderr(), LeaveAlternateScreen, DisableMouseCapture)
+}
+
+/// Hands the terminal over to the user's editor for the pending
+/// external edit, then applies whatever text it returns
+fn run_external_editor<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+) -> Result<(), AppError> {
+    leave_tui().map_err(AppError::FailedToSuspend)?;
+    let edit_result = app
+        .external_edit
+        .as_ref()
+        .map(|edit| edit_externally(&edit.text));
+    enter_tui().map_err(AppError::FailedToSuspend)?;
+
+    // the alternate screen starts out blank, so everything has to be redrawn
+    terminal.clear().map_err(AppError::FailedToSuspend)?;
+
+    match edit_result {
+        Some(Ok(text)) => {
+            if let Some(edit) = &mut app.external_edit {
+                edit.text = text;
+            }
+            app.apply_external_edit();
+        }
+        Some(Err(e)) => {
+            app.external_edit = None;
+            app.notify(Severity::Error, format!("Failed to run editor: {e}"));
+        }
+        None => {}
+    }
+
+    Ok(())
+}
+
 fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<bool, AppError> {
     loop {
+        let external_edit_pending = app
+            .external_edit
+            .as_ref()
+            .is_some_and(|edit| edit.error.is_none());
+        if external_edit_pending {
+            run_external_editor(terminal, app)?;
+        }
+
+        if let Some(text) = app.pending_system_clipboard.take() {
+            if let Err(e) = copy_to_system_clipboard(&text) {
+                app.notify(
+                    Severity::Warn,
+                    format!("Failed to copy to the system clipboard: {e}"),
+                );
+            }
+        }
+
         app.update_state();
         terminal
             .try_draw(|frame| ui(frame, app))
             .map_err(AppError::FailedToDraw)?;
 
-        if let Event::Key(key_event) = event::read().map_err(AppError::FailedToReadEvent)? {
-            match handle_input(app, key_event) {
+        // Poll instead of blocking on `read` so that the UI is redrawn when
+        // a status message expires, even if no key has been pressed
+        if !event::poll(EVENT_POLL_INTERVAL).map_err(AppError::FailedToReadEvent)? {
+            continue;
+        }
+
+        match event::read().map_err(AppError::FailedToReadEvent)? {
+            Event::Key(key_event) => match handle_input(app, key_event) {
                 Ok(Some(should_save)) => {
                     return Ok(should_save);
                 }
@@ -88,15 +233,58 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<bool
                     return Err(err);
                 }
                 _ => {}
+            },
+            Event::Mouse(mouse_event) => handle_mouse(app, mouse_event),
+            _ => {}
+        }
+    }
+}
+
+/// Clicking a pair selects it, dragging it moves it to wherever the mouse is released
+fn handle_mouse(app: &mut App, mouse_event: MouseEvent) {
+    // the mouse can only interact with the list when it is accepting key input
+    let list_is_interactive = app
+        .available_bindings
+        .iter()
+        .any(|(_, action)| matches!(action, InputAction::CursorSelect));
+    if !list_is_interactive {
+        app.dragged_pair_index = None;
+        return;
+    }
+
+    let hovered_index = app.pair_index_at(mouse_event.column, mouse_event.row);
+
+    match mouse_event.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            app.dragged_pair_index = hovered_index;
+            app.drag_moved_pair = false;
+            if hovered_index.is_some() {
+                app.list_ui_state.select(hovered_index);
             }
         }
+        MouseEventKind::Drag(MouseButton::Left) => {
+            if let (Some(from), Some(to)) = (app.dragged_pair_index, hovered_index) {
+                if from != to {
+                    if !app.drag_moved_pair {
+                        app.record_change();
+                        app.drag_moved_pair = true;
+                    }
+                    app.move_pair(from, to);
+                    app.dragged_pair_index = Some(to);
+                }
+            }
+        }
+        MouseEventKind::Up(MouseButton::Left) => {
+            app.dragged_pair_index = None;
+        }
+        _ => {}
     }
 }
 
 /// Interpreting `Ok` return values
 /// - `None` - continue running the app
 /// - `Some(bool)` - Exit the app, the bool value
-/// indicates whether changes should be saved
+///   indicates whether changes were saved
 fn handle_input(app: &mut App, key_event: KeyEvent) -> Result<Option<bool>, AppError> {
     if key_event.kind == event::KeyEventKind::Release {
         // we only want to listen to `Press` events
@@ -104,13 +292,26 @@ fn handle_input(app: &mut App, key_event: KeyEvent) -> Result<Option<bool>, AppE
     }
 
     let mut text_entry_action: Option<InputAction> = None;
+    let mut chord_action_binding_res: Option<ActionBinding> = None;
     let mut matching_action_binding_res: Option<ActionBinding> = None;
+    // so that eg: Ctrl+r with nothing to redo doesn't act as `r`
+    let has_command_modifier = key_event
+        .modifiers
+        .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
 
     for (binding, action) in app.available_bindings.iter() {
         match binding {
             Binding::Static(key_code) => {
-                if key_code == &key_event.code {
+                if key_code == &key_event.code
+                    && !has_command_modifier
+                    && matching_action_binding_res.is_none()
+                {
                     matching_action_binding_res = Some((*binding, *action));
+                }
+            }
+            Binding::Chord(key_code, modifiers) => {
+                if key_code == &key_event.code && modifiers == &key_event.modifiers {
+                    chord_action_binding_res = Some((*binding, *action));
                     break;
                 }
             }
@@ -122,15 +323,11 @@ fn handle_input(app: &mut App, key_event: KeyEvent) -> Result<Option<bool>, AppE
         }
     }
 
-    // We only want to use the text entry binding if no binding
-    // was found for the current key event
-    matching_action_binding_res = matching_action_binding_res.or_else(|| {
-        if let Some(action) = text_entry_action {
-            Some((Binding::TextEntry, action))
-        } else {
-            None
-        }
-    });
+    // Chords are the most specific match, and we only want to use the
+    // text entry binding if no other binding was found for the key event
+    matching_action_binding_res = chord_action_binding_res
+        .or(matching_action_binding_res)
+        .or(text_entry_action.map(|action| (Binding::TextEntry, action)));
 
     if let Some((_, action)) = matching_action_binding_res {
         match action {
@@ -152,6 +349,37 @@ fn handle_input(app: &mut App, key_event: KeyEvent) -> Result<Option<bool>, AppE
                                 None => Some(character.to_string()),
                             }
                         }
+                        TextField::Editor => {
+                            if let Some(editor) = &mut app.text_editor {
+                                editor.insert_char(character);
+                            }
+                        }
+                        TextField::Find => {
+                            if let Some(find_replace) = &mut app.find_replace {
+                                find_replace.find_input.push(character);
+                            }
+                        }
+                        TextField::Replace => {
+                            if let Some(find_replace) = &mut app.find_replace {
+                                find_replace.replace_input.push(character);
+                            }
+                        }
+                        TextField::Query => {
+                            if let Some(query) = &mut app.query {
+                                query.input.push(character);
+                            }
+                            app.refresh_query();
+                        }
+                        TextField::PatchFile => {
+                            if let Some(export) = &mut app.patch_export {
+                                export.output_file.push(character);
+                            }
+                        }
+                        TextField::ApplyFile => {
+                            if let Some(patch_apply) = &mut app.patch_apply {
+                                patch_apply.patch_file.push(character);
+                            }
+                        }
                     }
                 }
             }
@@ -163,16 +391,45 @@ fn handle_input(app: &mut App, key_event: KeyEvent) -> Result<Option<bool>, AppE
                     app.value_input.pop();
                 }
                 TextField::OutputFile => {
-                    app.target_write_file = match app.target_write_file.clone() {
-                        Some(mut file_path) => {
+                    app.target_write_file =
+                        app.target_write_file.clone().and_then(|mut file_path| {
                             file_path.pop();
                             if file_path.is_empty() {
                                 None
                             } else {
                                 Some(file_path)
                             }
-                        }
-                        None => None,
+                        })
+                }
+                TextField::Editor => {
+                    if let Some(editor) = &mut app.text_editor {
+                        editor.backspace();
+                    }
+                }
+                TextField::Find => {
+                    if let Some(find_replace) = &mut app.find_replace {
+                        find_replace.find_input.pop();
+                    }
+                }
+                TextField::Replace => {
+                    if let Some(find_replace) = &mut app.find_replace {
+                        find_replace.replace_input.pop();
+                    }
+                }
+                TextField::Query => {
+                    if let Some(query) = &mut app.query {
+                        query.input.pop();
+                    }
+                    app.refresh_query();
+                }
+                TextField::PatchFile => {
+                    if let Some(export) = &mut app.patch_export {
+                        export.output_file.pop();
+                    }
+                }
+                TextField::ApplyFile => {
+                    if let Some(patch_apply) = &mut app.patch_apply {
+                        patch_apply.patch_file.pop();
                     }
                 }
             },
@@ -183,7 +440,8 @@ fn handle_input(app: &mut App, key_event: KeyEvent) -> Result<Option<bool>, AppE
             //     return Ok(Some(false));
             // }
             InputAction::ExitCancel => {
-                app.goto_screen(AppScreen::Main);
+                app.exit_error = None;
+                app.goto_screen(app.home_screen());
             }
             InputAction::Quit => {
                 app.goto_screen(AppScreen::Exiting);
@@ -225,11 +483,21 @@ fn handle_input(app: &mut App, key_event: KeyEvent) -> Result<Option<bool>, AppE
                         Some(EditFocus::Key) => {
                             app.edit_popup_focus = Some(EditFocus::Value);
                         }
-                        Some(EditFocus::Value) => {
-                            app.save_key_value();
-                            app.clear_editing_state();
-                            app.goto_screen(AppScreen::Main);
-                        }
+                        Some(EditFocus::Value) => match app.save_key_value() {
+                            Ok(()) => {
+                                if app.key_input.is_empty() {
+                                    app.notify(Severity::Warn, "Saved a pair with an empty key");
+                                } else {
+                                    app.notify(
+                                        Severity::Info,
+                                        format!("Saved key {}", app.key_input),
+                                    );
+                                }
+                                app.clear_editing_state();
+                                app.goto_screen(AppScreen::Main);
+                            }
+                            Err(e) => app.notify(Severity::Error, e.to_string()),
+                        },
                         Some(EditFocus::Type) => {
                             app.type_list_open = true;
                         }
@@ -258,13 +526,8 @@ fn handle_input(app: &mut App, key_event: KeyEvent) -> Result<Option<bool>, AppE
             InputAction::EditingUp => {
                 if app.type_list_open {
                     app.type_list_ui_state.select_previous();
-                } else {
-                    match app.edit_popup_focus {
-                        Some(EditFocus::Type) => {
-                            app.edit_popup_focus = Some(EditFocus::Key);
-                        }
-                        _ => {}
-                    }
+                } else if let Some(EditFocus::Type) = app.edit_popup_focus {
+                    app.edit_popup_focus = Some(EditFocus::Key);
                 }
             }
             InputAction::EditingDown => {
@@ -285,40 +548,43 @@ fn handle_input(app: &mut App, key_event: KeyEvent) -> Result<Option<bool>, AppE
             InputAction::EditingBoolToggle => {
                 app.value_input = (!(app.value_input.parse::<bool>().unwrap())).to_string();
             }
-            InputAction::ExitLeft => {
-                app.exit_popup_focus = match app.exit_popup_focus.clone() {
-                    Some(focus) => match focus {
-                        ExitFocus::Positive => Some(ExitFocus::Negative),
-                        ExitFocus::Negative => Some(ExitFocus::Positive),
-                        ExitFocus::Input => Some(ExitFocus::Input),
-                    },
-                    None => None,
+            InputAction::ExitLeft => match app.exit_popup_focus {
+                Some(ExitFocus::Format) => app.cycle_save_format(-1),
+                focus => {
+                    app.exit_popup_focus = focus.map(|focus| match focus {
+                        ExitFocus::Positive => ExitFocus::Negative,
+                        ExitFocus::Negative => ExitFocus::Positive,
+                        other => other,
+                    })
                 }
-            }
-            InputAction::ExitRight => {
-                app.exit_popup_focus = match app.exit_popup_focus.clone() {
-                    Some(focus) => match focus {
-                        ExitFocus::Positive => Some(ExitFocus::Negative),
-                        ExitFocus::Negative => Some(ExitFocus::Positive),
-                        ExitFocus::Input => Some(ExitFocus::Input),
-                    },
-                    None => None,
+            },
+            InputAction::ExitRight => match app.exit_popup_focus {
+                Some(ExitFocus::Format) => app.cycle_save_format(1),
+                focus => {
+                    app.exit_popup_focus = focus.map(|focus| match focus {
+                        ExitFocus::Positive => ExitFocus::Negative,
+                        ExitFocus::Negative => ExitFocus::Positive,
+                        other => other,
+                    })
                 }
-            }
+            },
             InputAction::ExitUp => {
-                app.exit_popup_focus = match app.exit_popup_focus.clone() {
-                    Some(_) => Some(ExitFocus::Input),
-                    None => None,
-                }
+                // JSON Lines files keep their format, so their popup has no format to pick
+                let can_change_format = app.records.is_none();
+                app.exit_popup_focus = app.exit_popup_focus.map(|focus| match focus {
+                    ExitFocus::Positive | ExitFocus::Negative if can_change_format => {
+                        ExitFocus::Format
+                    }
+                    _ => ExitFocus::Input,
+                })
             }
             InputAction::ExitDown => {
-                app.exit_popup_focus = match app.exit_popup_focus.clone() {
-                    Some(focus) => match focus {
-                        ExitFocus::Input => Some(ExitFocus::Negative),
-                        other => Some(other),
-                    },
-                    None => None,
-                }
+                let can_change_format = app.records.is_none();
+                app.exit_popup_focus = app.exit_popup_focus.map(|focus| match focus {
+                    ExitFocus::Input if can_change_format => ExitFocus::Format,
+                    ExitFocus::Input | ExitFocus::Format => ExitFocus::Negative,
+                    other => other,
+                })
             }
             InputAction::ExitCursorSelect => match app.exit_popup_focus {
                 None => {}
@@ -326,7 +592,54 @@ fn handle_input(app: &mut App, key_event: KeyEvent) -> Result<Option<bool>, AppE
                     return Ok(Some(false));
                 }
                 _ => {
-                    return Ok(Some(true));
+                    if let Some(merge) = &mut app.merge {
+                        if !merge.save_confirmed {
+                            merge.save_confirmed = true;
+                            app.exit_error = Some(merge.unresolved_warning());
+                            return Ok(None);
+                        }
+                    }
+
+                    if !app.lossy_save_confirmed {
+                        if let Some(warning) = app.lossy_save_warning() {
+                            app.lossy_save_confirmed = true;
+                            app.exit_error = Some(warning);
+                            return Ok(None);
+                        }
+                    }
+
+                    if let Some(patch_file) = app.patch_output_file.clone() {
+                        if let Err(e) = app.write_patch(&patch_file) {
+                            app.exit_error = Some(e.to_string());
+                            app.exit_popup_focus = Some(ExitFocus::Input);
+                            return Ok(None);
+                        }
+                    }
+
+                    if app.dry_run {
+                        return Ok(Some(false));
+                    }
+
+                    // Saving happens while the UI is still up so that a failure
+                    // can be reported and retried without losing any changes
+                    match app.write() {
+                        Ok(bytes_written) => {
+                            app.exit_error = None;
+                            app.notify(
+                                Severity::Info,
+                                format!(
+                                    "Saved {} to {}",
+                                    format_byte_size(bytes_written),
+                                    app.target_write_file.clone().unwrap_or_default()
+                                ),
+                            );
+                            return Ok(Some(true));
+                        }
+                        Err(e) => {
+                            app.exit_error = Some(e.to_string());
+                            app.exit_popup_focus = Some(ExitFocus::Input);
+                        }
+                    }
                 }
             },
             InputAction::CursorUp => {
@@ -338,38 +651,539 @@ fn handle_input(app: &mut App, key_event: KeyEvent) -> Result<Option<bool>, AppE
             InputAction::CursorCancel => {
                 app.list_ui_state.select(None);
             }
+            InputAction::MovePairUp => {
+                app.move_target_pairs(-1);
+            }
+            InputAction::MovePairDown => {
+                app.move_target_pairs(1);
+            }
+            InputAction::ToggleMark => {
+                app.toggle_mark();
+            }
+            InputAction::MarkRange => {
+                app.mark_range();
+            }
+            InputAction::MarkAll => {
+                app.mark_all();
+            }
+            InputAction::ClearMarks => {
+                app.clear_marks();
+            }
             InputAction::CursorSelect => {
                 if let Some(selected_index) = app.list_ui_state.selected() {
-                    app.open_item_edit(selected_index)
-                        .map_err(AppError::FailedToOpenPairEdit)?;
+                    let selected_object_key = match app.current_pairs().get_index(selected_index) {
+                        Some((key, JsonValue::Object(_))) => Some(key.clone()),
+                        _ => None,
+                    };
+
+                    if let Some(key) = selected_object_key {
+                        app.enter_object(key);
+                    } else if let Err(e) = app.open_item_edit(selected_index) {
+                        app.notify(
+                            Severity::Error,
+                            AppError::FailedToOpenPairEdit(e).to_string(),
+                        );
+                    }
                 }
             }
             InputAction::RequestPairDelete => {
-                if let Some(selected_index) = app.list_ui_state.selected() {
-                    let entry = match app.pairs.get_index_entry(selected_index) {
-                        Some(entry) => entry,
-                        None => return Err(AppError::NoEntryAtIndex(selected_index)),
-                    };
-                    let key = entry.key();
-
-                    app.target_delete_key = Some(key.into());
+                let keys = app.target_keys();
+                if keys.is_empty() {
+                    if let Some(selected_index) = app.list_ui_state.selected() {
+                        app.notify(
+                            Severity::Error,
+                            AppError::NoEntryAtIndex(selected_index).to_string(),
+                        );
+                    }
+                } else {
+                    app.target_delete_keys = Some(keys);
                 }
             }
             InputAction::DeleteYes => {
-                if let Some(target_key) = &app.target_delete_key {
-                    app.pairs.shift_remove(target_key.as_str());
-                    app.target_delete_key = None;
+                if let Some(target_keys) = app.target_delete_keys.take() {
+                    app.remove_pairs(&target_keys);
+                    app.notify(
+                        Severity::Info,
+                        match target_keys.as_slice() {
+                            [key] => format!("Deleted key {key}"),
+                            _ => format!("Deleted {} keys", target_keys.len()),
+                        },
+                    );
                 }
             }
             InputAction::DeleteNo => {
-                app.target_delete_key = None;
+                app.target_delete_keys = None;
+            }
+            InputAction::NavigateOut => {
+                app.leave_object();
+            }
+            InputAction::RequestConvert => {
+                app.open_convert_popup();
+            }
+            InputAction::ConvertUp => {
+                app.convert_list_ui_state.select_previous();
+            }
+            InputAction::ConvertDown => {
+                app.convert_list_ui_state.select_next();
+            }
+            InputAction::ConvertSubmit => {
+                if let Err(e) = app.apply_conversion() {
+                    app.notify(Severity::Error, e.to_string());
+                }
+            }
+            InputAction::ConvertCancel => {
+                app.convert_target_keys = None;
+            }
+            InputAction::Undo => {
+                if app.undo() {
+                    app.notify(Severity::Info, "Undid the last change");
+                }
+            }
+            InputAction::Redo => {
+                if app.redo() {
+                    app.notify(Severity::Info, "Redid the last undone change");
+                }
+            }
+            InputAction::OpenSortPopup => {
+                app.sort_popup_open = true;
+                app.sort_list_ui_state.select_first();
+            }
+            InputAction::SortUp => {
+                app.sort_list_ui_state.select_previous();
+            }
+            InputAction::SortDown => {
+                app.sort_list_ui_state.select_next();
+            }
+            InputAction::SortToggleRecursive => {
+                app.sort_recursive = !app.sort_recursive;
+            }
+            InputAction::SortSubmit => {
+                app.apply_sort();
+            }
+            InputAction::SortCancel => {
+                app.sort_popup_open = false;
             }
+            InputAction::OpenFindReplace => {
+                app.find_replace = Some(FindReplace::new());
+            }
+            InputAction::FindToggleField => {
+                if let Some(find_replace) = &mut app.find_replace {
+                    find_replace.focus = match find_replace.focus {
+                        FindReplaceField::Find => FindReplaceField::Replace,
+                        FindReplaceField::Replace => FindReplaceField::Find,
+                    };
+                }
+            }
+            InputAction::FindToggleRegex => {
+                if let Some(find_replace) = &mut app.find_replace {
+                    find_replace.use_regex = !find_replace.use_regex;
+                }
+            }
+            InputAction::FindCycleTarget => {
+                if let Some(find_replace) = &mut app.find_replace {
+                    find_replace.target = find_replace.target.next();
+                }
+            }
+            InputAction::FindToggleScope => {
+                if let Some(find_replace) = &mut app.find_replace {
+                    find_replace.scope = match find_replace.scope {
+                        SearchScope::Document => SearchScope::Subtree,
+                        SearchScope::Subtree => SearchScope::Document,
+                    };
+                }
+            }
+            InputAction::FindSearch => {
+                app.run_find();
+            }
+            InputAction::FindCancel => {
+                app.find_replace = None;
+            }
+            InputAction::FindMatchUp => {
+                if let Some(find_replace) = &mut app.find_replace {
+                    find_replace.match_list_ui_state.select_previous();
+                }
+            }
+            InputAction::FindMatchDown => {
+                if let Some(find_replace) = &mut app.find_replace {
+                    find_replace.match_list_ui_state.select_next();
+                }
+            }
+            InputAction::FindAccept => {
+                app.decide_find_match(true);
+            }
+            InputAction::FindSkip => {
+                app.decide_find_match(false);
+            }
+            InputAction::FindAcceptAll => {
+                app.accept_all_find_matches();
+            }
+            InputAction::FindApply => {
+                app.apply_find_replace();
+            }
+            InputAction::FindBack => {
+                if let Some(find_replace) = &mut app.find_replace {
+                    find_replace.matches = None;
+                }
+            }
+            InputAction::OpenQuery => {
+                app.open_query();
+            }
+            InputAction::CloseQuery => {
+                app.goto_screen(AppScreen::Main);
+            }
+            InputAction::QueryToggleFocus => {
+                if let Some(query) = &mut app.query {
+                    query.results_focused = !query.results_focused;
+                }
+            }
+            InputAction::QueryResultUp => {
+                if let Some(query) = &mut app.query {
+                    query.result_list_ui_state.select_previous();
+                }
+            }
+            InputAction::QueryResultDown => {
+                if let Some(query) = &mut app.query {
+                    query.result_list_ui_state.select_next();
+                }
+            }
+            InputAction::QueryJump => {
+                app.jump_to_query_result();
+            }
+            InputAction::QueryEdit => {
+                if let Err(e) = app.edit_query_result() {
+                    app.notify(Severity::Error, format!("Failed to open the editor: {e}"));
+                }
+            }
+            InputAction::OpenCopyPath => {
+                app.open_copy_path_popup();
+            }
+            InputAction::CopyPathUp => {
+                app.copy_path_list_ui_state.select_previous();
+            }
+            InputAction::CopyPathDown => {
+                app.copy_path_list_ui_state.select_next();
+            }
+            InputAction::CopyPathSubmit => {
+                app.copy_highlighted_path();
+            }
+            InputAction::CopyPathCancel => {
+                app.copy_path_options = None;
+            }
+            InputAction::OpenPatchExport => {
+                app.open_patch_export();
+            }
+            InputAction::PatchExportSubmit => {
+                app.export_patch();
+            }
+            InputAction::PatchExportCancel => {
+                app.patch_export = None;
+            }
+            InputAction::OpenPatchApply => {
+                app.open_patch_apply();
+            }
+            InputAction::PatchApplyLoad => {
+                app.preview_patch_apply();
+            }
+            InputAction::PatchApplyConfirm => {
+                app.confirm_patch_apply();
+            }
+            InputAction::PatchApplyBack => {
+                if let Some(patch_apply) = &mut app.patch_apply {
+                    patch_apply.preview = None;
+                }
+            }
+            InputAction::PatchApplyCancel => {
+                app.patch_apply = None;
+            }
+            InputAction::DiffUp => {
+                if let Some(diff_view) = &mut app.diff_view {
+                    diff_view.row_list_ui_state.select_previous();
+                }
+            }
+            InputAction::DiffDown => {
+                if let Some(diff_view) = &mut app.diff_view {
+                    diff_view.row_list_ui_state.select_next();
+                }
+            }
+            InputAction::DiffNextDifference => {
+                if let Some(diff_view) = &mut app.diff_view {
+                    diff_view.select_next_difference();
+                }
+            }
+            InputAction::DiffToggleSide => {
+                if let Some(diff_view) = &mut app.diff_view {
+                    diff_view.focus = diff_view.focus.other();
+                }
+            }
+            InputAction::DiffEnter => {
+                if let Some(diff_view) = &mut app.diff_view {
+                    diff_view.enter_selected();
+                }
+            }
+            InputAction::DiffLeave => {
+                if let Some(diff_view) = &mut app.diff_view {
+                    diff_view.leave();
+                }
+            }
+            InputAction::DiffCopyAcross => {
+                if let Some(diff_view) = &mut app.diff_view {
+                    match diff_view.copy_selected_to_other_side() {
+                        Ok(key) => app.notify(Severity::Info, format!("Copied key {key} across")),
+                        Err(e) => app.notify(Severity::Warn, e.to_string()),
+                    }
+                }
+            }
+            InputAction::DiffUndo => {
+                if app.diff_view.as_mut().is_some_and(DiffView::undo) {
+                    app.notify(Severity::Info, "Undid the last copy");
+                }
+            }
+            InputAction::DiffRedo => {
+                if app.diff_view.as_mut().is_some_and(DiffView::redo) {
+                    app.notify(Severity::Info, "Redid the last undone copy");
+                }
+            }
+            InputAction::DiffSave => {
+                if let Some(diff_view) = &mut app.diff_view {
+                    let side = diff_view.focus;
+                    let file = diff_view.document(side).file.clone();
+                    match diff_view.save(side) {
+                        Ok(bytes_written) => app.notify(
+                            Severity::Info,
+                            format!("Saved {} to {file}", format_byte_size(bytes_written)),
+                        ),
+                        Err(e) => app.notify(Severity::Error, e.to_string()),
+                    }
+                }
+            }
+            InputAction::DiffQuit => {
+                if let Some(diff_view) = &mut app.diff_view {
+                    if diff_view.has_unsaved_changes() && !diff_view.quit_requested {
+                        diff_view.quit_requested = true;
+                        app.notify(
+                            Severity::Warn,
+                            "There are unsaved changes, press q again to quit without saving",
+                        );
+                        return Ok(None);
+                    }
+                }
+                return Ok(Some(false));
+            }
+            InputAction::PatchApplyUp => {
+                if let Some(patch_apply) = &mut app.patch_apply {
+                    patch_apply.preview_list_ui_state.select_previous();
+                }
+            }
+            InputAction::PatchApplyDown => {
+                if let Some(patch_apply) = &mut app.patch_apply {
+                    patch_apply.preview_list_ui_state.select_next();
+                }
+            }
+            InputAction::CopyPair => match app.copy_target_pairs() {
+                0 => {}
+                1 => app.notify(Severity::Info, "Copied 1 pair"),
+                count => app.notify(Severity::Info, format!("Copied {count} pairs")),
+            },
+            InputAction::CutPair => match app.cut_target_pairs() {
+                0 => {}
+                1 => app.notify(Severity::Info, "Cut 1 pair"),
+                count => app.notify(Severity::Info, format!("Cut {count} pairs")),
+            },
+            InputAction::PastePair => match app.paste_pairs().as_slice() {
+                [] => {}
+                [key] => app.notify(Severity::Info, format!("Pasted as key {key}")),
+                keys => app.notify(Severity::Info, format!("Pasted {} pairs", keys.len())),
+            },
+            InputAction::DuplicatePair => match app.duplicate_target_pairs().as_slice() {
+                [] => {}
+                [key] => app.notify(Severity::Info, format!("Duplicated as key {key}")),
+                keys => app.notify(Severity::Info, format!("Duplicated {} pairs", keys.len())),
+            },
             InputAction::Preview => {
                 app.goto_screen(AppScreen::Preview);
             }
             InputAction::ExitPreview => {
                 app.goto_screen(AppScreen::Main);
             }
+            InputAction::RecordUp => {
+                if let Some(records) = &mut app.records {
+                    records.select_previous();
+                }
+            }
+            InputAction::RecordDown => {
+                if let Some(records) = &mut app.records {
+                    records.select_next();
+                }
+            }
+            InputAction::RecordOpen => {
+                if let Err(e) = app.open_selected_record() {
+                    app.notify(Severity::Error, e.to_string());
+                }
+            }
+            InputAction::CloseRecord => {
+                app.close_record();
+            }
+            InputAction::RecordAdd => {
+                if let Some(records) = &mut app.records {
+                    records.insert_after_selected(JsonValue::Object(JsonData::new()));
+                }
+                if let Err(e) = app.open_selected_record() {
+                    app.notify(Severity::Error, e.to_string());
+                }
+            }
+            InputAction::RecordDelete => {
+                if let Some(records) = &mut app.records {
+                    records.delete_requested = true;
+                }
+            }
+            InputAction::RecordDeleteYes => {
+                if let Some(records) = &mut app.records {
+                    records.delete_requested = false;
+                    records.remove_selected();
+                }
+            }
+            InputAction::RecordDeleteNo => {
+                if let Some(records) = &mut app.records {
+                    records.delete_requested = false;
+                }
+            }
+            InputAction::RecordMoveUp => {
+                if let Some(records) = &mut app.records {
+                    records.move_selected(-1);
+                }
+            }
+            InputAction::RecordMoveDown => {
+                if let Some(records) = &mut app.records {
+                    records.move_selected(1);
+                }
+            }
+            InputAction::RecordUndo => {
+                if app.records.as_mut().is_some_and(RecordList::undo) {
+                    app.notify(Severity::Info, "Undid the last change to the records");
+                }
+            }
+            InputAction::RecordRedo => {
+                if app.records.as_mut().is_some_and(RecordList::redo) {
+                    app.notify(
+                        Severity::Info,
+                        "Redid the last undone change to the records",
+                    );
+                }
+            }
+            InputAction::OpenHelp => {
+                app.help_open = true;
+                app.help_scroll = 0;
+            }
+            InputAction::CloseHelp => {
+                app.help_open = false;
+            }
+            InputAction::HelpScrollUp => {
+                app.help_scroll = app.help_scroll.saturating_sub(1);
+            }
+            InputAction::HelpScrollDown => {
+                app.help_scroll = app.help_scroll.saturating_add(1);
+            }
+            InputAction::OpenStringEditor => {
+                app.open_string_editor();
+            }
+            InputAction::OpenExternalEditor => {
+                if let Err(e) = app.request_external_edit() {
+                    app.notify(Severity::Error, format!("Failed to serialize value: {e}"));
+                }
+            }
+            InputAction::OpenRawEditor => {
+                if let Err(e) = app.open_raw_editor() {
+                    app.notify(Severity::Error, format!("Failed to serialize value: {e}"));
+                }
+            }
+            InputAction::MergeKeepOurs => {
+                let ours = app
+                    .merge
+                    .as_ref()
+                    .and_then(|merge| merge.current_conflict())
+                    .and_then(|conflict| conflict.ours.clone());
+                app.resolve_merge_conflict(ours);
+            }
+            InputAction::MergeTakeTheirs => {
+                let theirs = app
+                    .merge
+                    .as_ref()
+                    .and_then(|merge| merge.current_conflict())
+                    .and_then(|conflict| conflict.theirs.clone());
+                app.resolve_merge_conflict(theirs);
+            }
+            InputAction::MergeEdit => {
+                if let Err(e) = app.edit_merge_conflict() {
+                    app.notify(Severity::Error, format!("Failed to serialize value: {e}"));
+                }
+            }
+            InputAction::ExternalEditRetry => {
+                if let Some(edit) = &mut app.external_edit {
+                    edit.error = None;
+                }
+            }
+            InputAction::ExternalEditDiscard => {
+                app.external_edit = None;
+                app.notify(Severity::Warn, "Discarded changes from the external editor");
+            }
+            InputAction::TextEditorSave => match app.get_current_screen() {
+                AppScreen::RawEditor => {
+                    if let Err(e) = app.save_raw_editor() {
+                        app.notify(Severity::Error, format!("Invalid JSON: {e}"));
+                    }
+                }
+                _ => {
+                    if let Err(e) = app.save_string_editor() {
+                        app.notify(Severity::Error, format!("Invalid escaped string: {e}"));
+                    }
+                }
+            },
+            InputAction::TextEditorGotoError => {
+                app.goto_raw_editor_error();
+            }
+            InputAction::TextEditorCancel => {
+                app.close_text_editor();
+            }
+            InputAction::TextEditorToggleEscaped => {
+                if let Err(e) = app.toggle_editor_escaping() {
+                    app.notify(Severity::Error, format!("Invalid escaped string: {e}"));
+                }
+            }
+            InputAction::TextEditorNewline => {
+                if let Some(editor) = &mut app.text_editor {
+                    editor.insert_newline();
+                }
+            }
+            InputAction::TextEditorUp => {
+                if let Some(editor) = &mut app.text_editor {
+                    editor.move_up();
+                }
+            }
+            InputAction::TextEditorDown => {
+                if let Some(editor) = &mut app.text_editor {
+                    editor.move_down();
+                }
+            }
+            InputAction::TextEditorLeft => {
+                if let Some(editor) = &mut app.text_editor {
+                    editor.move_left();
+                }
+            }
+            InputAction::TextEditorRight => {
+                if let Some(editor) = &mut app.text_editor {
+                    editor.move_right();
+                }
+            }
+            InputAction::TextEditorHome => {
+                if let Some(editor) = &mut app.text_editor {
+                    editor.move_home();
+                }
+            }
+            InputAction::TextEditorEnd => {
+                if let Some(editor) = &mut app.text_editor {
+                    editor.move_end();
+                }
+            }
         }
     };
 
@@ -0,0 +1,287 @@
+use crate::app::{JsonData, JsonValue};
+use crate::path::PathSegment;
+
+/// A key both sides changed in different ways, `None` means the key is absent
+pub struct MergeConflict {
+    pub path: Vec<PathSegment>,
+    pub base: Option<JsonValue>,
+    pub ours: Option<JsonValue>,
+    pub theirs: Option<JsonValue>,
+    /// The keys before this one in their object, nearest first, which place
+    /// the key if their value is chosen after we removed it
+    pub preceding_keys: Vec<String>,
+}
+
+/// The conflicts of a merge that are still being worked through in the editor
+pub struct MergeState {
+    pub conflicts: Vec<MergeConflict>,
+    /// The index of the conflict being shown
+    pub current: usize,
+    /// Set once saving with conflicts still unresolved has been warned about
+    pub save_confirmed: bool,
+}
+
+impl MergeState {
+    pub fn new(conflicts: Vec<MergeConflict>) -> MergeState {
+        MergeState {
+            conflicts,
+            current: 0,
+            save_confirmed: false,
+        }
+    }
+
+    pub fn current_conflict(&self) -> Option<&MergeConflict> {
+        self.conflicts.get(self.current)
+    }
+
+    /// Explains what saving now does to the conflicts that are left
+    pub fn unresolved_warning(&self) -> String {
+        match self.conflicts.len() - self.current {
+            1 => "1 conflict is unresolved and will keep our value, save again to save anyway"
+                .to_string(),
+            count => format!(
+                "{count} conflicts are unresolved and will keep our values, save again to save anyway"
+            ),
+        }
+    }
+}
+
+/// Inserts a pair after the nearest of `preceding_keys` the object has, or first if
+/// it has none of them. A key the object already has keeps its position.
+pub fn insert_after(data: &mut JsonData, key: String, value: JsonValue, preceding_keys: &[String]) {
+    if let Some(existing) = data.get_mut(&key) {
+        *existing = value;
+        return;
+    }
+
+    let index = preceding_keys
+        .iter()
+        .find_map(|preceding_key| data.get_index_of(preceding_key))
+        .map_or(0, |index| index + 1);
+    data.shift_insert(index, key, value);
+}
+
+/// The keys before `key` in the object, nearest first
+fn keys_preceding(data: &JsonData, key: &str) -> Vec<String> {
+    let index = data.get_index_of(key).unwrap_or(data.len());
+    data.keys().take(index).rev().cloned().collect()
+}
+
+/// Merges the changes `ours` and `theirs` each made to `base`. Objects are merged
+/// key by key, while arrays and other values are only taken whole from one side.
+/// Conflicting keys keep our value in the result until they are resolved. Keys
+/// only they have are placed after the key they follow in their document.
+pub fn merge_documents(
+    base: &JsonData,
+    ours: &JsonData,
+    theirs: &JsonData,
+) -> (JsonData, Vec<MergeConflict>) {
+    let mut conflicts = Vec::new();
+    let merged = merge_objects(base, ours, theirs, &mut Vec::new(), &mut conflicts);
+    (merged, conflicts)
+}
+
+fn merge_objects(
+    base: &JsonData,
+    ours: &JsonData,
+    theirs: &JsonData,
+    path: &mut Vec<PathSegment>,
+    conflicts: &mut Vec<MergeConflict>,
+) -> JsonData {
+    let keys = ours
+        .keys()
+        .chain(theirs.keys().filter(|key| !ours.contains_key(key.as_str())));
+
+    let mut merged = JsonData::new();
+    for key in keys {
+        let base_value = base.get(key);
+        let our_value = ours.get(key);
+        let their_value = theirs.get(key);
+
+        let merged_value = if our_value == their_value || their_value == base_value {
+            our_value.cloned()
+        } else if our_value == base_value {
+            their_value.cloned()
+        } else if let (Some(JsonValue::Object(our_object)), Some(JsonValue::Object(their_object))) =
+            (our_value, their_value)
+        {
+            // keys added on both sides are merged as though they were empty to begin with
+            let empty = JsonData::new();
+            let base_object = match base_value {
+                Some(JsonValue::Object(base_object)) => base_object,
+                _ => &empty,
+            };
+
+            path.push(PathSegment::Key(key.clone()));
+            let merged_object =
+                merge_objects(base_object, our_object, their_object, path, conflicts);
+            path.pop();
+            Some(JsonValue::Object(merged_object))
+        } else {
+            let mut conflict_path = path.clone();
+            conflict_path.push(PathSegment::Key(key.clone()));
+            conflicts.push(MergeConflict {
+                path: conflict_path,
+                base: base_value.cloned(),
+                ours: our_value.cloned(),
+                theirs: their_value.cloned(),
+                preceding_keys: keys_preceding(theirs, key),
+            });
+            our_value.cloned()
+        };
+
+        match merged_value {
+            Some(value) if ours.contains_key(key) => {
+                merged.insert(key.clone(), value);
+            }
+            Some(value) => insert_after(
+                &mut merged,
+                key.clone(),
+                value,
+                &keys_preceding(theirs, key),
+            ),
+            None => {}
+        }
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{json_data_from_serde, App};
+    use crate::path::display_path;
+
+    fn document(json: &str) -> JsonData {
+        match serde_json::from_str(json).unwrap() {
+            serde_json::Value::Object(data) => json_data_from_serde(data),
+            _ => panic!("test documents must be objects"),
+        }
+    }
+
+    /// The merged document's JSON source and the paths of its conflicts
+    fn merge(base: &str, ours: &str, theirs: &str) -> (String, Vec<String>) {
+        let (merged, conflicts) =
+            merge_documents(&document(base), &document(ours), &document(theirs));
+        (
+            serde_json::to_string(&merged).unwrap(),
+            conflicts
+                .iter()
+                .map(|conflict| display_path(&conflict.path))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn takes_changes_made_on_one_side() {
+        let (merged, conflicts) = merge(
+            r#"{"a": 1, "b": 1, "c": 1}"#,
+            r#"{"a": 2, "b": 1, "c": 1}"#,
+            r#"{"a": 1, "b": 1}"#,
+        );
+        assert_eq!(merged, r#"{"a":2,"b":1}"#);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn identical_changes_do_not_conflict() {
+        let (merged, conflicts) = merge(r#"{"a": 1}"#, r#"{"a": [2]}"#, r#"{"a": [2]}"#);
+        assert_eq!(merged, r#"{"a":[2]}"#);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn merges_objects_changed_on_both_sides_key_by_key() {
+        let (merged, conflicts) = merge(
+            r#"{"o": {"x": 1, "y": 1}}"#,
+            r#"{"o": {"x": 2, "y": 1}}"#,
+            r#"{"o": {"x": 1, "y": 2}}"#,
+        );
+        assert_eq!(merged, r#"{"o":{"x":2,"y":2}}"#);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn conflicting_changes_keep_our_value() {
+        let (merged, conflicts) = merge(
+            r#"{"a": 1, "list": [1], "o": {"x": 1}}"#,
+            r#"{"a": 2, "list": [1, 2], "o": {"x": 2}}"#,
+            r#"{"a": 3, "list": [1, 3], "o": {"x": 3}}"#,
+        );
+        assert_eq!(merged, r#"{"a":2,"list":[1,2],"o":{"x":2}}"#);
+        assert_eq!(conflicts, ["a", "list", "o.x"]);
+    }
+
+    #[test]
+    fn removing_a_key_the_other_side_changed_conflicts() {
+        let (merged, conflicts) =
+            merge(r#"{"a": 1, "b": 1}"#, r#"{"b": 1}"#, r#"{"a": 2, "b": 1}"#);
+        assert_eq!(merged, r#"{"b":1}"#);
+        assert_eq!(conflicts, ["a"]);
+    }
+
+    #[test]
+    fn keys_only_they_added_keep_their_position() {
+        let (merged, _) = merge(
+            r#"{"a": 1, "c": 1}"#,
+            r#"{"a": 1, "c": 1, "d": 1}"#,
+            r#"{"a": 1, "b": 1, "c": 1}"#,
+        );
+        assert_eq!(merged, r#"{"a":1,"b":1,"c":1,"d":1}"#);
+    }
+
+    #[test]
+    fn inserts_after_the_nearest_preceding_key() {
+        let mut data = document(r#"{"a": 1, "c": 1}"#);
+        let preceding = ["missing".to_string(), "a".to_string()];
+
+        insert_after(&mut data, "b".to_string(), JsonValue::Null, &preceding);
+        insert_after(&mut data, "first".to_string(), JsonValue::Null, &[]);
+        insert_after(&mut data, "c".to_string(), JsonValue::Null, &[]);
+        assert_eq!(
+            serde_json::to_string(&data).unwrap(),
+            r#"{"first":null,"a":1,"b":null,"c":null}"#
+        );
+    }
+
+    #[test]
+    fn warns_about_each_unresolved_conflict() {
+        let (_, conflicts) = merge_documents(
+            &document(r#"{"a": 1, "b": 1}"#),
+            &document(r#"{"a": 2, "b": 2}"#),
+            &document(r#"{"a": 3, "b": 3}"#),
+        );
+
+        let mut state = MergeState::new(conflicts);
+        assert!(state.unresolved_warning().starts_with("2 conflicts are"));
+        state.current = 1;
+        assert!(state.unresolved_warning().starts_with("1 conflict is"));
+    }
+
+    #[test]
+    fn resolutions_cannot_be_undone() {
+        let base = document(r#"{"a": 1, "b": 1}"#);
+        let (merged, conflicts) = merge_documents(
+            &base,
+            &document(r#"{"a": 2, "b": 2}"#),
+            &document(r#"{"a": 3, "b": 3}"#),
+        );
+        let mut app = App::new(None, None).unwrap();
+        app.pairs = merged;
+        app.merge = Some(MergeState::new(conflicts));
+
+        app.record_change();
+        app.resolve_merge_conflict(Some(JsonValue::Number(3.0)));
+        assert!(!app.undo());
+        assert_eq!(app.merge.as_ref().map(|merge| merge.current), Some(1));
+
+        app.resolve_merge_conflict(Some(JsonValue::Number(3.0)));
+        assert!(!app.undo());
+        assert!(app.merge.is_none());
+        assert_eq!(
+            serde_json::to_string(&app.pairs).unwrap(),
+            r#"{"a":3,"b":3}"#
+        );
+    }
+}
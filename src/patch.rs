@@ -0,0 +1,636 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use ratatui::widgets::ListState;
+use serde::Serialize;
+
+use crate::app::{json_data_from_serde, JsonData, JsonValue};
+use crate::path::{pointer_tokens, to_json_pointer, PathSegment};
+
+/// A single RFC 6902 JSON Patch operation, paths are JSON Pointers
+#[derive(Serialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add { path: String, value: JsonValue },
+    Remove { path: String },
+    Replace { path: String, value: JsonValue },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: JsonValue },
+}
+
+impl PatchOperation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PatchOperation::Add { .. } => "add",
+            PatchOperation::Remove { .. } => "remove",
+            PatchOperation::Replace { .. } => "replace",
+            PatchOperation::Move { .. } => "move",
+            PatchOperation::Copy { .. } => "copy",
+            PatchOperation::Test { .. } => "test",
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        match self {
+            PatchOperation::Add { path, .. }
+            | PatchOperation::Remove { path }
+            | PatchOperation::Replace { path, .. }
+            | PatchOperation::Move { path, .. }
+            | PatchOperation::Copy { path, .. }
+            | PatchOperation::Test { path, .. } => path,
+        }
+    }
+}
+
+impl Display for PatchOperation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            PatchOperation::Move { from, path } | PatchOperation::Copy { from, path } => {
+                write!(f, "{} {from} -> {path}", self.name())
+            }
+            _ => write!(f, "{} {}", self.name(), self.path()),
+        }
+    }
+}
+
+/// A patch read from a file, JSON arrays are RFC 6902 JSON Patches
+/// and objects are RFC 7386 JSON Merge Patches
+pub enum Patch {
+    Json(Vec<PatchOperation>),
+    Merge(JsonValue),
+}
+
+/// The state of the popup for writing the session's changes as a JSON Patch
+pub struct PatchExport {
+    pub output_file: String,
+    pub operation_count: usize,
+    /// The reason the last write attempt failed
+    pub error: Option<String>,
+}
+
+/// The state of the popup for applying a patch file to the document
+pub struct PatchApply {
+    pub patch_file: String,
+    /// The patched document and how it differs from the current one,
+    /// set once the patch has been read and applied successfully
+    pub preview: Option<(JsonData, Vec<PatchOperation>)>,
+    pub preview_list_ui_state: ListState,
+    /// Why the patch could not be read or applied
+    pub error: Option<String>,
+}
+
+/// Computes the JSON Patch that turns `before` into `after`.
+/// A key that disappears while a new key with the same value appears next
+/// to it is treated as a rename, and becomes a `move` rather than a remove and add.
+pub fn diff(before: &JsonData, after: &JsonData) -> Vec<PatchOperation> {
+    let mut operations = Vec::new();
+    diff_objects(before, after, &mut Vec::new(), &mut operations);
+    operations
+}
+
+fn diff_values(
+    before: &JsonValue,
+    after: &JsonValue,
+    path: &mut Vec<PathSegment>,
+    operations: &mut Vec<PatchOperation>,
+) {
+    match (before, after) {
+        _ if before == after => {}
+        (JsonValue::Object(before), JsonValue::Object(after)) => {
+            diff_objects(before, after, path, operations)
+        }
+        (JsonValue::Array(before), JsonValue::Array(after)) => {
+            diff_arrays(before, after, path, operations)
+        }
+        _ => operations.push(PatchOperation::Replace {
+            path: to_json_pointer(path),
+            value: after.clone(),
+        }),
+    }
+}
+
+fn diff_objects(
+    before: &JsonData,
+    after: &JsonData,
+    path: &mut Vec<PathSegment>,
+    operations: &mut Vec<PatchOperation>,
+) {
+    let pointer_to = |path: &mut Vec<PathSegment>, key: &str| {
+        path.push(PathSegment::Key(key.to_string()));
+        let pointer = to_json_pointer(path);
+        path.pop();
+        pointer
+    };
+
+    let mut added_keys: Vec<&String> = after
+        .keys()
+        .filter(|key| !before.contains_key(key.as_str()))
+        .collect();
+
+    for (key, value) in before {
+        if after.contains_key(key) {
+            continue;
+        }
+
+        let renamed_to = added_keys
+            .iter()
+            .position(|added_key| after.get(added_key.as_str()) == Some(value))
+            .map(|index| added_keys.remove(index));
+
+        operations.push(match renamed_to {
+            Some(new_key) => PatchOperation::Move {
+                from: pointer_to(path, key),
+                path: pointer_to(path, new_key),
+            },
+            None => PatchOperation::Remove {
+                path: pointer_to(path, key),
+            },
+        });
+    }
+
+    for (key, before_value) in before {
+        if let Some(after_value) = after.get(key) {
+            path.push(PathSegment::Key(key.clone()));
+            diff_values(before_value, after_value, path, operations);
+            path.pop();
+        }
+    }
+
+    for key in added_keys {
+        operations.push(PatchOperation::Add {
+            path: pointer_to(path, key),
+            value: after[key.as_str()].clone(),
+        });
+    }
+}
+
+/// Items are compared after skipping the run of equal items at both ends,
+/// so a single insertion or deletion does not shift every later index
+fn diff_arrays(
+    before: &[JsonValue],
+    after: &[JsonValue],
+    path: &mut Vec<PathSegment>,
+    operations: &mut Vec<PatchOperation>,
+) {
+    let shorter_length = before.len().min(after.len());
+    let prefix_length = before
+        .iter()
+        .zip(after)
+        .take_while(|(before, after)| before == after)
+        .count();
+    let suffix_length = before
+        .iter()
+        .rev()
+        .zip(after.iter().rev())
+        .take(shorter_length - prefix_length)
+        .take_while(|(before, after)| before == after)
+        .count();
+
+    let before_middle = &before[prefix_length..before.len() - suffix_length];
+    let after_middle = &after[prefix_length..after.len() - suffix_length];
+
+    for (offset, (before_item, after_item)) in before_middle.iter().zip(after_middle).enumerate() {
+        path.push(PathSegment::Index(prefix_length + offset));
+        diff_values(before_item, after_item, path, operations);
+        path.pop();
+    }
+
+    let mut push_item_operation = |index: usize, value: Option<&JsonValue>| {
+        path.push(PathSegment::Index(index));
+        let pointer = to_json_pointer(path);
+        path.pop();
+        operations.push(match value {
+            Some(value) => PatchOperation::Add {
+                path: pointer,
+                value: value.clone(),
+            },
+            None => PatchOperation::Remove { path: pointer },
+        });
+    };
+
+    let common_length = before_middle.len().min(after_middle.len());
+    // later indexes are removed first so earlier ones stay valid
+    for index in (common_length..before_middle.len()).rev() {
+        push_item_operation(prefix_length + index, None);
+    }
+    for (index, value) in after_middle.iter().enumerate().skip(common_length) {
+        push_item_operation(prefix_length + index, Some(value));
+    }
+}
+
+pub fn parse_patch(text: &str) -> Result<Patch, PatchError> {
+    match serde_json::from_str(text).map_err(PatchError::Syntax)? {
+        serde_json::Value::Array(operations) => operations
+            .into_iter()
+            .enumerate()
+            .map(|(index, operation)| {
+                parse_operation(operation).map_err(|reason| PatchError::Malformed(index, reason))
+            })
+            .collect::<Result<_, _>>()
+            .map(Patch::Json),
+        serde_json::Value::Object(data) => {
+            Ok(Patch::Merge(JsonValue::Object(json_data_from_serde(data))))
+        }
+        _ => Err(PatchError::NotAPatch),
+    }
+}
+
+fn parse_operation(operation: serde_json::Value) -> Result<PatchOperation, String> {
+    let serde_json::Value::Object(mut fields) = operation else {
+        return Err("operations must be objects".to_string());
+    };
+
+    let mut take_string = |name: &str| match fields.remove(name) {
+        Some(serde_json::Value::String(s)) => Ok(s),
+        Some(_) => Err(format!("\"{name}\" must be a string")),
+        None => Err(format!("\"{name}\" is missing")),
+    };
+
+    let op = take_string("op")?;
+    let path = take_string("path")?;
+
+    let operation = match op.as_str() {
+        "remove" => PatchOperation::Remove { path },
+        "move" => PatchOperation::Move {
+            from: take_string("from")?,
+            path,
+        },
+        "copy" => PatchOperation::Copy {
+            from: take_string("from")?,
+            path,
+        },
+        "add" | "replace" | "test" => {
+            let value = fields
+                .remove("value")
+                .map(JsonValue::from_serde)
+                .ok_or_else(|| "\"value\" is missing".to_string())?;
+            match op.as_str() {
+                "add" => PatchOperation::Add { path, value },
+                "replace" => PatchOperation::Replace { path, value },
+                _ => PatchOperation::Test { path, value },
+            }
+        }
+        _ => return Err(format!("unknown operation \"{op}\"")),
+    };
+
+    Ok(operation)
+}
+
+/// Applies a patch to a copy of the document. Every JSON Patch operation
+/// must succeed for the patch to be applied, as required by RFC 6902.
+pub fn apply_patch(data: &JsonData, patch: &Patch) -> Result<JsonData, PatchError> {
+    let mut document = JsonValue::Object(data.clone());
+
+    match patch {
+        Patch::Json(operations) => {
+            for (index, operation) in operations.iter().enumerate() {
+                apply_operation(&mut document, operation).map_err(|reason| {
+                    PatchError::OperationFailed(index, operation.to_string(), reason)
+                })?;
+            }
+        }
+        Patch::Merge(merge_patch) => apply_merge_patch(&mut document, merge_patch),
+    }
+
+    match document {
+        JsonValue::Object(data) => Ok(data),
+        _ => Err(PatchError::RootNotAnObject),
+    }
+}
+
+fn apply_operation(document: &mut JsonValue, operation: &PatchOperation) -> Result<(), String> {
+    match operation {
+        PatchOperation::Add { path, value } => add_value(document, path, value.clone()),
+        PatchOperation::Remove { path } => remove_value(document, path).map(|_| ()),
+        PatchOperation::Replace { path, value } => {
+            *value_at_pointer_mut(document, path)? = value.clone();
+            Ok(())
+        }
+        PatchOperation::Move { from, path } => {
+            if path.starts_with(&format!("{from}/")) {
+                return Err("a value cannot be moved into itself".to_string());
+            }
+            let value = remove_value(document, from)?;
+            add_value(document, path, value)
+        }
+        PatchOperation::Copy { from, path } => {
+            let value = value_at_pointer_mut(document, from)?.clone();
+            add_value(document, path, value)
+        }
+        PatchOperation::Test { path, value } => {
+            if value_at_pointer_mut(document, path)? == value {
+                Ok(())
+            } else {
+                Err("the value does not match".to_string())
+            }
+        }
+    }
+}
+
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, String> {
+    pointer_tokens(pointer).ok_or_else(|| format!("\"{pointer}\" is not a JSON Pointer"))
+}
+
+fn parse_index(token: &str, length: usize) -> Result<usize, String> {
+    token
+        .parse::<usize>()
+        .ok()
+        .filter(|index| *index < length)
+        .ok_or_else(|| format!("no array item at index {token}"))
+}
+
+fn child_mut<'a>(value: &'a mut JsonValue, token: &str) -> Result<&'a mut JsonValue, String> {
+    match value {
+        JsonValue::Object(data) => data
+            .get_mut(token)
+            .ok_or_else(|| format!("no key \"{token}\"")),
+        JsonValue::Array(items) => {
+            let index = parse_index(token, items.len())?;
+            Ok(&mut items[index])
+        }
+        _ => Err(format!(
+            "cannot look up \"{token}\" in a {}",
+            value.value_type()
+        )),
+    }
+}
+
+fn value_at_pointer_mut<'a>(
+    document: &'a mut JsonValue,
+    pointer: &str,
+) -> Result<&'a mut JsonValue, String> {
+    parse_pointer(pointer)?
+        .iter()
+        .try_fold(document, |value, token| child_mut(value, token))
+}
+
+/// Finds the parent of the value a pointer refers to, along with the last token
+fn parent_at_pointer_mut<'a>(
+    document: &'a mut JsonValue,
+    pointer: &str,
+) -> Result<(&'a mut JsonValue, String), String> {
+    let mut tokens = parse_pointer(pointer)?;
+    let last_token = tokens
+        .pop()
+        .ok_or_else(|| "the whole document cannot be added to or removed".to_string())?;
+    let parent = tokens
+        .iter()
+        .try_fold(document, |value, token| child_mut(value, token))?;
+    Ok((parent, last_token))
+}
+
+fn add_value(document: &mut JsonValue, pointer: &str, value: JsonValue) -> Result<(), String> {
+    if pointer.is_empty() {
+        *document = value;
+        return Ok(());
+    }
+
+    let (parent, token) = parent_at_pointer_mut(document, pointer)?;
+    match parent {
+        JsonValue::Object(data) => {
+            data.insert(token, value);
+            Ok(())
+        }
+        JsonValue::Array(items) if token == "-" => {
+            items.push(value);
+            Ok(())
+        }
+        JsonValue::Array(items) => {
+            // adding may also append, so the index one past the end is allowed
+            let index = parse_index(&token, items.len() + 1)?;
+            items.insert(index, value);
+            Ok(())
+        }
+        _ => Err(format!("cannot add to a {}", parent.value_type())),
+    }
+}
+
+fn remove_value(document: &mut JsonValue, pointer: &str) -> Result<JsonValue, String> {
+    let (parent, token) = parent_at_pointer_mut(document, pointer)?;
+    match parent {
+        JsonValue::Object(data) => data
+            .shift_remove(&token)
+            .ok_or_else(|| format!("no key \"{token}\"")),
+        JsonValue::Array(items) => {
+            let index = parse_index(&token, items.len())?;
+            Ok(items.remove(index))
+        }
+        _ => Err(format!("cannot remove from a {}", parent.value_type())),
+    }
+}
+
+/// Merges `patch` into `target` as described in RFC 7386,
+/// where `null` removes a key and objects are merged recursively
+fn apply_merge_patch(target: &mut JsonValue, patch: &JsonValue) {
+    let JsonValue::Object(patch_data) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !matches!(target, JsonValue::Object(_)) {
+        *target = JsonValue::Object(JsonData::new());
+    }
+    let JsonValue::Object(target_data) = target else {
+        unreachable!("non-object targets were just replaced with an object");
+    };
+
+    for (key, value) in patch_data {
+        match value {
+            JsonValue::Null => {
+                target_data.shift_remove(key);
+            }
+            _ => apply_merge_patch(
+                target_data.entry(key.clone()).or_insert(JsonValue::Null),
+                value,
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PatchError {
+    Syntax(serde_json::Error),
+    NotAPatch,
+    /// An operation that is not valid JSON Patch, with its index in the patch
+    Malformed(usize, String),
+    /// An operation that could not be applied, with its index in the patch
+    OperationFailed(usize, String, String),
+    RootNotAnObject,
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            PatchError::Syntax(e) => write!(f, "The patch is not valid JSON: {e}"),
+            PatchError::NotAPatch => write!(
+                f,
+                "A patch must be an array of JSON Patch operations or a JSON Merge Patch object"
+            ),
+            PatchError::Malformed(index, reason) => {
+                write!(f, "Operation {index} is malformed: {reason}")
+            }
+            PatchError::OperationFailed(index, operation, reason) => {
+                write!(f, "Operation {index} ({operation}) failed: {reason}")
+            }
+            PatchError::RootNotAnObject => {
+                write!(f, "The patched document would no longer be a JSON object")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(json: &str) -> JsonData {
+        match serde_json::from_str(json).unwrap() {
+            serde_json::Value::Object(data) => json_data_from_serde(data),
+            _ => panic!("test documents must be objects"),
+        }
+    }
+
+    fn diff_json(before: &str, after: &str) -> String {
+        serde_json::to_string(&diff(&document(before), &document(after))).unwrap()
+    }
+
+    #[test]
+    fn identical_documents_have_no_changes() {
+        assert_eq!(
+            diff_json(r#"{"a": [1, {"b": 2}]}"#, r#"{"a": [1, {"b": 2}]}"#),
+            "[]"
+        );
+    }
+
+    #[test]
+    fn diffs_added_removed_and_replaced_keys() {
+        assert_eq!(
+            diff_json(r#"{"a": 1, "b": 2}"#, r#"{"a": 3, "c": 4}"#),
+            r#"[{"op":"remove","path":"/b"},{"op":"replace","path":"/a","value":3},{"op":"add","path":"/c","value":4}]"#
+        );
+    }
+
+    #[test]
+    fn renamed_keys_become_moves() {
+        assert_eq!(
+            diff_json(r#"{"old": {"x": 1}}"#, r#"{"new": {"x": 1}}"#),
+            r#"[{"op":"move","from":"/old","path":"/new"}]"#
+        );
+    }
+
+    #[test]
+    fn diffs_nested_values_with_escaped_pointers() {
+        assert_eq!(
+            diff_json(r#"{"a/b": {"c~d": 1}}"#, r#"{"a/b": {"c~d": 2}}"#),
+            r#"[{"op":"replace","path":"/a~1b/c~0d","value":2}]"#
+        );
+    }
+
+    #[test]
+    fn array_insertions_do_not_shift_later_items() {
+        assert_eq!(
+            diff_json(r#"{"a": [1, 2, 3]}"#, r#"{"a": [1, 9, 2, 3]}"#),
+            r#"[{"op":"add","path":"/a/1","value":9}]"#
+        );
+        assert_eq!(
+            diff_json(r#"{"a": [1, 2, 3, 4]}"#, r#"{"a": [1, 4]}"#),
+            r#"[{"op":"remove","path":"/a/2"},{"op":"remove","path":"/a/1"}]"#
+        );
+    }
+
+    fn apply_json(data: &str, patch: &str) -> Result<String, String> {
+        let patch = parse_patch(patch).map_err(|e| e.to_string())?;
+        apply_patch(&document(data), &patch)
+            .map(|patched| serde_json::to_string(&patched).unwrap())
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn applying_a_diff_gives_the_changed_document() {
+        let before = r#"{"a": 1, "old": [1, 2], "list": [1, 2, 3], "nested": {"x": true}}"#;
+        let after = r#"{"a": "one", "new": [1, 2], "list": [0, 1, 3, 4], "nested": {"x": false}}"#;
+
+        let patch = Patch::Json(diff(&document(before), &document(after)));
+        // moved keys end up last, so the documents are compared without regard to key order
+        assert!(apply_patch(&document(before), &patch).unwrap() == document(after));
+    }
+
+    #[test]
+    fn applies_every_operation() {
+        let patch = r#"[
+            {"op": "add", "path": "/list/-", "value": 3},
+            {"op": "add", "path": "/list/0", "value": 0},
+            {"op": "remove", "path": "/gone"},
+            {"op": "replace", "path": "/a", "value": {"b": 1}},
+            {"op": "move", "from": "/a/b", "path": "/b"},
+            {"op": "copy", "from": "/b", "path": "/c"},
+            {"op": "test", "path": "/c", "value": 1}
+        ]"#;
+        assert_eq!(
+            apply_json(r#"{"a": 0, "gone": null, "list": [1, 2]}"#, patch).unwrap(),
+            r#"{"a":{},"list":[0,1,2,3],"b":1,"c":1}"#
+        );
+    }
+
+    #[test]
+    fn a_failed_test_rejects_the_whole_patch() {
+        let patch = r#"[
+            {"op": "replace", "path": "/a", "value": 2},
+            {"op": "test", "path": "/a", "value": 3}
+        ]"#;
+        assert_eq!(
+            apply_json(r#"{"a": 1}"#, patch).unwrap_err(),
+            "Operation 1 (test /a) failed: the value does not match"
+        );
+    }
+
+    #[test]
+    fn reports_operations_that_cannot_apply() {
+        let failure = |patch| apply_json(r#"{"a": [1], "s": "x"}"#, patch).unwrap_err();
+
+        assert_eq!(
+            failure(r#"[{"op": "remove", "path": "/missing"}]"#),
+            "Operation 0 (remove /missing) failed: no key \"missing\""
+        );
+        assert_eq!(
+            failure(r#"[{"op": "move", "from": "/a", "path": "/a/0"}]"#),
+            "Operation 0 (move /a -> /a/0) failed: a value cannot be moved into itself"
+        );
+        assert!(failure(r#"[{"op": "add", "path": "/a/5", "value": 1}]"#)
+            .starts_with("Operation 0 (add /a/5) failed"));
+        assert_eq!(
+            failure(r#"[{"op": "replace", "path": "", "value": 3}]"#),
+            "The patched document would no longer be a JSON object"
+        );
+    }
+
+    #[test]
+    fn reports_malformed_patches() {
+        assert_eq!(
+            apply_json("{}", r#"[{"op": "jump", "path": "/a"}]"#).unwrap_err(),
+            "Operation 0 is malformed: unknown operation \"jump\""
+        );
+        assert_eq!(
+            apply_json("{}", r#"[{"op": "add", "path": "/a"}]"#).unwrap_err(),
+            "Operation 0 is malformed: \"value\" is missing"
+        );
+        assert!(apply_json("{}", "3")
+            .unwrap_err()
+            .starts_with("A patch must be"));
+    }
+
+    #[test]
+    fn merge_patches_replace_remove_and_merge_objects() {
+        assert_eq!(
+            apply_json(
+                r#"{"a": 1, "b": {"c": 2, "d": 3}, "e": [1]}"#,
+                r#"{"a": null, "b": {"c": 4}, "e": {"f": 5}}"#
+            )
+            .unwrap(),
+            r#"{"b":{"c":4,"d":3},"e":{"f":5}}"#
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::app::{JsonData, JsonValue};
+
+/// One step from a value to one of its children
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl Display for PathSegment {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            PathSegment::Key(key) => write!(f, "{key}"),
+            PathSegment::Index(index) => write!(f, "[{index}]"),
+        }
+    }
+}
+
+/// Formats a path for display, eg: `servers[0].host`
+pub fn display_path(path: &[PathSegment]) -> String {
+    let mut result = String::new();
+
+    for segment in path {
+        if let PathSegment::Key(_) = segment {
+            if !result.is_empty() {
+                result.push('.');
+            }
+        }
+        result.push_str(&segment.to_string());
+    }
+
+    result
+}
+
+/// Formats a path as a JSONPath expression, eg: `$.servers[0]['host name']`
+pub fn to_json_path(path: &[PathSegment]) -> String {
+    let mut result = "$".to_string();
+
+    for segment in path {
+        match segment {
+            PathSegment::Key(key) if is_ascii_identifier(key) => {
+                result.push('.');
+                result.push_str(key);
+            }
+            PathSegment::Key(key) => {
+                result.push_str(&format!(
+                    "['{}']",
+                    key.replace('\\', "\\\\").replace('\'', "\\'")
+                ));
+            }
+            PathSegment::Index(index) => result.push_str(&format!("[{index}]")),
+        }
+    }
+
+    result
+}
+
+/// Formats a path as an RFC 6901 JSON Pointer, eg: `/servers/0/host`
+pub fn to_json_pointer(path: &[PathSegment]) -> String {
+    path.iter()
+        .map(|segment| match segment {
+            PathSegment::Key(key) => format!("/{}", key.replace('~', "~0").replace('/', "~1")),
+            PathSegment::Index(index) => format!("/{index}"),
+        })
+        .collect()
+}
+
+/// Formats a path as a jq filter, eg: `.servers[0]["host name"]`
+pub fn to_jq(path: &[PathSegment]) -> String {
+    if path.is_empty() {
+        return ".".to_string();
+    }
+
+    let mut result = String::new();
+    for segment in path {
+        match segment {
+            PathSegment::Key(key) if is_ascii_identifier(key) => {
+                result.push('.');
+                result.push_str(key);
+            }
+            PathSegment::Key(key) => {
+                // jq only allows brackets straight after another segment
+                if result.is_empty() {
+                    result.push('.');
+                }
+                let quoted = serde_json::to_string(key).unwrap_or_default();
+                result.push_str(&format!("[{quoted}]"));
+            }
+            PathSegment::Index(index) => {
+                if result.is_empty() {
+                    result.push('.');
+                }
+                result.push_str(&format!("[{index}]"));
+            }
+        }
+    }
+
+    result
+}
+
+/// Finds the value an RFC 6901 JSON Pointer refers to, returning its path.
+/// The document is needed to tell whether a number is an array index or a key.
+pub fn resolve_json_pointer(data: &JsonData, pointer: &str) -> Option<Vec<PathSegment>> {
+    let mut path = Vec::new();
+    let mut object = Some(data);
+    let mut array: Option<&Vec<JsonValue>> = None;
+
+    for token in pointer_tokens(pointer)? {
+        let child = if let Some(data) = object {
+            path.push(PathSegment::Key(token.clone()));
+            data.get(&token)?
+        } else {
+            let index: usize = token.parse().ok()?;
+            path.push(PathSegment::Index(index));
+            array?.get(index)?
+        };
+
+        (object, array) = match child {
+            JsonValue::Object(data) => (Some(data), None),
+            JsonValue::Array(items) => (None, Some(items)),
+            _ => (None, None),
+        };
+    }
+
+    Some(path)
+}
+
+/// The value an RFC 6901 JSON Pointer refers to inside a value of any kind
+pub fn value_at_pointer<'a>(value: &'a JsonValue, pointer: &str) -> Option<&'a JsonValue> {
+    pointer_tokens(pointer)?
+        .iter()
+        .try_fold(value, |value, token| match value {
+            JsonValue::Object(data) => data.get(token),
+            JsonValue::Array(items) => items.get(token.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+/// Splits an RFC 6901 JSON Pointer into its unescaped reference tokens,
+/// returning `None` if it is not a valid pointer
+pub fn pointer_tokens(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+
+    Some(
+        pointer
+            .strip_prefix('/')?
+            .split('/')
+            .map(|token| token.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+    )
+}
+
+/// Whether a key can be written without quotes in a JSONPath or jq expression,
+/// whose identifiers are ASCII only
+fn is_ascii_identifier(key: &str) -> bool {
+    let mut characters = key.chars();
+    characters
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && characters.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether a key can be written without quotes in a JSON5 object, which allows Unicode letters
+pub fn is_identifier(key: &str) -> bool {
+    let mut characters = key.chars();
+    characters
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && characters.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// The path to the object being viewed in the main list
+pub fn path_from_keys(keys: &[String]) -> Vec<PathSegment> {
+    keys.iter().cloned().map(PathSegment::Key).collect()
+}
+
+pub fn value_at_mut<'a>(data: &'a mut JsonData, path: &[PathSegment]) -> Option<&'a mut JsonValue> {
+    let (first, rest) = path.split_first()?;
+    let PathSegment::Key(key) = first else {
+        return None;
+    };
+
+    rest.iter().try_fold(data.get_mut(key)?, |value, segment| {
+        match (value, segment) {
+            (JsonValue::Object(data), PathSegment::Key(key)) => data.get_mut(key),
+            (JsonValue::Array(items), PathSegment::Index(index)) => items.get_mut(*index),
+            _ => None,
+        }
+    })
+}
+
+/// The object at `path`, where an empty path is the document itself
+pub fn object_at_mut<'a>(data: &'a mut JsonData, path: &[PathSegment]) -> Option<&'a mut JsonData> {
+    if path.is_empty() {
+        return Some(data);
+    }
+
+    match value_at_mut(data, path)? {
+        JsonValue::Object(object) => Some(object),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::json_data_from_serde;
+
+    fn document(json: &str) -> JsonData {
+        match serde_json::from_str(json).unwrap() {
+            serde_json::Value::Object(data) => json_data_from_serde(data),
+            _ => panic!("test documents must be objects"),
+        }
+    }
+
+    fn path(segments: &[&str]) -> Vec<PathSegment> {
+        segments
+            .iter()
+            .map(|segment| match segment.parse() {
+                Ok(index) => PathSegment::Index(index),
+                Err(_) => PathSegment::Key(segment.to_string()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn formats_paths_in_each_syntax() {
+        let servers = path(&["servers", "0", "host"]);
+        assert_eq!(display_path(&servers), "servers[0].host");
+        assert_eq!(to_json_path(&servers), "$.servers[0].host");
+        assert_eq!(to_json_pointer(&servers), "/servers/0/host");
+        assert_eq!(to_jq(&servers), ".servers[0].host");
+
+        assert_eq!(to_json_path(&[]), "$");
+        assert_eq!(to_json_pointer(&[]), "");
+        assert_eq!(to_jq(&[]), ".");
+        assert_eq!(to_jq(&path(&["0", "a"])), ".[0].a");
+    }
+
+    #[test]
+    fn quotes_keys_that_are_not_ascii_identifiers() {
+        for (key, json_path, jq) in [
+            ("host name", "$['host name']", r#".["host name"]"#),
+            ("café", "$['café']", r#".["café"]"#),
+            ("1st", "$['1st']", r#".["1st"]"#),
+            ("it's", r"$['it\'s']", r#".["it's"]"#),
+            ("back\\slash", r"$['back\\slash']", r#".["back\\slash"]"#),
+            ("_private2", "$._private2", "._private2"),
+        ] {
+            let key_path = [PathSegment::Key(key.to_string())];
+            assert_eq!(to_json_path(&key_path), json_path);
+            assert_eq!(to_jq(&key_path), jq);
+        }
+        assert_eq!(to_jq(&path(&["a", "b c"])), r#".a["b c"]"#);
+
+        // JSON5 keys may be any Unicode identifier
+        assert!(is_identifier("café"));
+        assert!(!is_ascii_identifier("café"));
+    }
+
+    #[test]
+    fn escapes_json_pointer_tokens() {
+        let escaped = [PathSegment::Key("a/b~c".to_string())];
+        assert_eq!(to_json_pointer(&escaped), "/a~1b~0c");
+        assert_eq!(pointer_tokens("/a~1b~0c"), Some(vec!["a/b~c".to_string()]));
+        // `~01` is an escaped `~` followed by `1`, not an escaped `/`
+        assert_eq!(pointer_tokens("/~01"), Some(vec!["~1".to_string()]));
+        assert_eq!(pointer_tokens("/"), Some(vec![String::new()]));
+        assert_eq!(pointer_tokens("no-slash"), None);
+    }
+
+    #[test]
+    fn resolves_json_pointers_against_the_document() {
+        let data = document(r#"{"servers": [{"host": "a"}], "0": {"1": true}, "a/b": 1}"#);
+
+        assert_eq!(
+            resolve_json_pointer(&data, "/servers/0/host"),
+            Some(path(&["servers", "0", "host"]))
+        );
+        // numbers are keys inside objects and indexes inside arrays
+        assert_eq!(
+            resolve_json_pointer(&data, "/0/1"),
+            Some(vec![
+                PathSegment::Key("0".to_string()),
+                PathSegment::Key("1".to_string())
+            ])
+        );
+        assert_eq!(
+            resolve_json_pointer(&data, "/a~1b"),
+            Some(vec![PathSegment::Key("a/b".to_string())])
+        );
+        assert_eq!(resolve_json_pointer(&data, ""), Some(Vec::new()));
+
+        assert_eq!(resolve_json_pointer(&data, "/servers/1"), None);
+        assert_eq!(resolve_json_pointer(&data, "/servers/host"), None);
+        assert_eq!(resolve_json_pointer(&data, "/missing"), None);
+        assert_eq!(resolve_json_pointer(&data, "/a~1b/deeper"), None);
+        assert_eq!(resolve_json_pointer(&data, "servers"), None);
+    }
+
+    #[test]
+    fn finds_values_at_json_pointers() {
+        let value = JsonValue::Array(vec![JsonValue::Object(document(r#"{"a": [1, 2]}"#))]);
+        let found =
+            value_at_pointer(&value, "/0/a/1").map(|value| serde_json::to_string(value).unwrap());
+        assert_eq!(found.as_deref(), Some("2"));
+        assert!(value_at_pointer(&value, "/0/a/2").is_none());
+        assert!(value_at_pointer(&value, "/a").is_none());
+        assert!(value_at_pointer(&value, "") == Some(&value));
+    }
+}
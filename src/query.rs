@@ -0,0 +1,752 @@
+use core::fmt;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+use ratatui::widgets::ListState;
+
+use crate::app::{JsonData, JsonValue};
+use crate::path::PathSegment;
+
+/// A node matched by a query, along with the path leading to it from the document root
+pub struct QueryResult {
+    pub path: Vec<PathSegment>,
+    pub value: JsonValue,
+}
+
+/// Evaluates a JSONPath expression (eg: `$.services[*].ports`) against the document.
+///
+/// Supported syntax: `$`, `.key`, `['key']`, `[0]`, `[-1]`, `[1:3]`, `[*]` / `.*`,
+/// unions like `[0,2]`, recursive descent with `..key`, and filters of the form
+/// `[?(@.key)]` or `[?(@.key == 'value')]`. As in jq, the leading `$` may be left
+/// out and `[]` is the same as `[*]`.
+pub fn run_query(data: &JsonData, query: &str) -> Result<Vec<QueryResult>, QueryError> {
+    let steps = Parser::new(query).parse_query()?;
+
+    let mut nodes = vec![(Vec::new(), Node::Document(data))];
+    for step in &steps {
+        nodes = nodes
+            .into_iter()
+            .flat_map(|(path, node)| step.apply(path, node))
+            .collect();
+    }
+
+    Ok(nodes
+        .into_iter()
+        .map(|(path, node)| QueryResult {
+            path,
+            value: node.to_value(),
+        })
+        .collect())
+}
+
+/// The document root is not stored as a `JsonValue`, so it is wrapped
+/// to be treated like any other object
+#[derive(Clone, Copy)]
+enum Node<'a> {
+    Document(&'a JsonData),
+    Value(&'a JsonValue),
+}
+
+impl<'a> Node<'a> {
+    fn object(self) -> Option<&'a JsonData> {
+        match self {
+            Node::Document(data) | Node::Value(JsonValue::Object(data)) => Some(data),
+            _ => None,
+        }
+    }
+
+    fn array(self) -> Option<&'a Vec<JsonValue>> {
+        match self {
+            Node::Value(JsonValue::Array(items)) => Some(items),
+            _ => None,
+        }
+    }
+
+    fn children(self) -> Vec<(PathSegment, Node<'a>)> {
+        if let Some(data) = self.object() {
+            return data
+                .iter()
+                .map(|(key, value)| (PathSegment::Key(key.clone()), Node::Value(value)))
+                .collect();
+        }
+
+        self.array()
+            .map(|items| {
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, item)| (PathSegment::Index(index), Node::Value(item)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn child(self, segment: &PathSegment) -> Option<Node<'a>> {
+        match segment {
+            PathSegment::Key(key) => self.object()?.get(key).map(Node::Value),
+            PathSegment::Index(index) => self.array()?.get(*index).map(Node::Value),
+        }
+    }
+
+    fn to_value(self) -> JsonValue {
+        match self {
+            Node::Document(data) => JsonValue::Object(data.clone()),
+            Node::Value(value) => value.clone(),
+        }
+    }
+}
+
+type PathedNode<'a> = (Vec<PathSegment>, Node<'a>);
+
+enum Step {
+    Children(Vec<Selector>),
+    Descendants(Vec<Selector>),
+}
+
+impl Step {
+    fn apply<'a>(&self, path: Vec<PathSegment>, node: Node<'a>) -> Vec<PathedNode<'a>> {
+        match self {
+            Step::Children(selectors) => select(selectors, &path, node),
+            Step::Descendants(selectors) => {
+                let mut result = Vec::new();
+                let mut pending = vec![(path, node)];
+
+                while let Some((path, node)) = pending.pop() {
+                    result.extend(select(selectors, &path, node));
+
+                    // pushed in reverse so the document is walked in order
+                    for (segment, child) in node.children().into_iter().rev() {
+                        let mut child_path = path.clone();
+                        child_path.push(segment);
+                        pending.push((child_path, child));
+                    }
+                }
+
+                result
+            }
+        }
+    }
+}
+
+fn select<'a>(selectors: &[Selector], path: &[PathSegment], node: Node<'a>) -> Vec<PathedNode<'a>> {
+    selectors
+        .iter()
+        .flat_map(|selector| selector.matching_children(node))
+        .map(|(segment, child)| {
+            let mut child_path = path.to_vec();
+            child_path.push(segment);
+            (child_path, child)
+        })
+        .collect()
+}
+
+enum Selector {
+    Key(String),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Wildcard,
+    Filter(Filter),
+}
+
+impl Selector {
+    fn matching_children<'a>(&self, node: Node<'a>) -> Vec<(PathSegment, Node<'a>)> {
+        match self {
+            Selector::Key(key) => node
+                .object()
+                .and_then(|data| data.get(key))
+                .map(|value| vec![(PathSegment::Key(key.clone()), Node::Value(value))])
+                .unwrap_or_default(),
+            Selector::Index(index) => node
+                .array()
+                .and_then(|items| {
+                    let index = resolve_index(*index, items.len())?;
+                    items
+                        .get(index)
+                        .map(|item| vec![(PathSegment::Index(index), Node::Value(item))])
+                })
+                .unwrap_or_default(),
+            Selector::Slice(start, end) => node
+                .array()
+                .map(|items| {
+                    let length = items.len() as i64;
+                    let clamp = |bound: i64| {
+                        if bound < 0 {
+                            (length + bound).max(0)
+                        } else {
+                            bound.min(length)
+                        }
+                    };
+                    let start = start.map(clamp).unwrap_or(0) as usize;
+                    let end = end.map(clamp).unwrap_or(length) as usize;
+
+                    (start..end.max(start))
+                        .map(|index| (PathSegment::Index(index), Node::Value(&items[index])))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            Selector::Wildcard => node.children(),
+            Selector::Filter(filter) => node
+                .children()
+                .into_iter()
+                .filter(|(_, child)| filter.matches(*child))
+                .collect(),
+        }
+    }
+}
+
+/// Negative indexes count back from the end of the array
+fn resolve_index(index: i64, length: usize) -> Option<usize> {
+    if index < 0 {
+        length.checked_sub(index.unsigned_abs() as usize)
+    } else {
+        Some(index as usize)
+    }
+}
+
+struct Filter {
+    path: Vec<PathSegment>,
+    comparison: Option<(Comparison, JsonValue)>,
+}
+
+impl Filter {
+    fn matches(&self, node: Node) -> bool {
+        let Some(value) = self
+            .path
+            .iter()
+            .try_fold(node, |node, segment| node.child(segment))
+        else {
+            return false;
+        };
+
+        match &self.comparison {
+            None => true,
+            Some((comparison, expected)) => {
+                comparison.holds(compare_values(&value.to_value(), expected))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    /// `ordering` is `None` when the values cannot be compared, eg: a string and a number
+    fn holds(self, ordering: Option<Ordering>) -> bool {
+        match (self, ordering) {
+            (Comparison::NotEqual, ordering) => ordering != Some(Ordering::Equal),
+            (_, None) => false,
+            (Comparison::Equal, Some(ordering)) => ordering == Ordering::Equal,
+            (Comparison::Less, Some(ordering)) => ordering == Ordering::Less,
+            (Comparison::LessOrEqual, Some(ordering)) => ordering != Ordering::Greater,
+            (Comparison::Greater, Some(ordering)) => ordering == Ordering::Greater,
+            (Comparison::GreaterOrEqual, Some(ordering)) => ordering != Ordering::Less,
+        }
+    }
+}
+
+fn compare_values(a: &JsonValue, b: &JsonValue) -> Option<Ordering> {
+    match (a, b) {
+        (JsonValue::Number(a), JsonValue::Number(b)) => a.partial_cmp(b),
+        (
+            JsonValue::String(a) | JsonValue::Datetime(a),
+            JsonValue::String(b) | JsonValue::Datetime(b),
+        ) => Some(a.cmp(b)),
+        (JsonValue::Boolean(a), JsonValue::Boolean(b)) => (a == b).then_some(Ordering::Equal),
+        (JsonValue::Null, JsonValue::Null) => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+struct Parser<'a> {
+    query: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(query: &'a str) -> Parser<'a> {
+        Parser {
+            query: query.trim(),
+            position: 0,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.query[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, expected: &str) -> bool {
+        if self.rest().starts_with(expected) {
+            self.position += expected.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), QueryError> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{expected}`")))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.position = self.query.len() - trimmed.len();
+    }
+
+    fn error(&self, message: impl Into<String>) -> QueryError {
+        QueryError {
+            message: message.into(),
+            position: self.position,
+        }
+    }
+
+    fn parse_query(&mut self) -> Result<Vec<Step>, QueryError> {
+        let mut steps = Vec::new();
+
+        if !self.eat("$") {
+            // jq style queries start straight away with a key, eg: `services[0]`
+            if self.peek().is_some_and(is_name_character) {
+                steps.push(Step::Children(vec![Selector::Key(self.parse_name()?)]));
+            }
+        }
+
+        while self.peek().is_some() {
+            steps.push(self.parse_step()?);
+        }
+
+        Ok(steps)
+    }
+
+    fn parse_step(&mut self) -> Result<Step, QueryError> {
+        if self.eat("..") {
+            return Ok(Step::Descendants(match self.peek() {
+                Some('[') => self.parse_brackets()?,
+                _ => vec![self.parse_dot_selector()?],
+            }));
+        }
+
+        if self.eat(".") {
+            return Ok(match self.peek() {
+                // jq allows `.[0]` as well as `[0]`
+                Some('[') => Step::Children(self.parse_brackets()?),
+                _ => Step::Children(vec![self.parse_dot_selector()?]),
+            });
+        }
+
+        match self.peek() {
+            Some('[') => Ok(Step::Children(self.parse_brackets()?)),
+            _ => Err(self.error("expected `.` or `[`")),
+        }
+    }
+
+    fn parse_dot_selector(&mut self) -> Result<Selector, QueryError> {
+        if self.eat("*") {
+            Ok(Selector::Wildcard)
+        } else {
+            Ok(Selector::Key(self.parse_name()?))
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<String, QueryError> {
+        let length = self
+            .rest()
+            .find(|c: char| !is_name_character(c))
+            .unwrap_or(self.rest().len());
+
+        if length == 0 {
+            return Err(self.error("expected a key"));
+        }
+
+        let name = self.rest()[..length].to_string();
+        self.position += length;
+        Ok(name)
+    }
+
+    fn parse_brackets(&mut self) -> Result<Vec<Selector>, QueryError> {
+        self.expect("[")?;
+        self.skip_whitespace();
+
+        if self.eat("]") {
+            return Ok(vec![Selector::Wildcard]);
+        }
+
+        let mut selectors = vec![self.parse_bracket_selector()?];
+        self.skip_whitespace();
+        while self.eat(",") {
+            self.skip_whitespace();
+            selectors.push(self.parse_bracket_selector()?);
+            self.skip_whitespace();
+        }
+
+        self.expect("]")?;
+        Ok(selectors)
+    }
+
+    fn parse_bracket_selector(&mut self) -> Result<Selector, QueryError> {
+        match self.peek() {
+            Some('*') => {
+                self.position += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some('\'' | '"') => Ok(Selector::Key(self.parse_quoted()?)),
+            Some('?') => {
+                self.position += 1;
+                Ok(Selector::Filter(self.parse_filter()?))
+            }
+            _ => {
+                let start = self.parse_optional_integer()?;
+                if !self.eat(":") {
+                    return start
+                        .map(Selector::Index)
+                        .ok_or_else(|| self.error("expected an index, key, `*` or filter"));
+                }
+                let end = self.parse_optional_integer()?;
+                Ok(Selector::Slice(start, end))
+            }
+        }
+    }
+
+    fn parse_optional_integer(&mut self) -> Result<Option<i64>, QueryError> {
+        let length = self
+            .rest()
+            .char_indices()
+            .find(|(i, c)| !(c.is_ascii_digit() || (*i == 0 && *c == '-')))
+            .map(|(i, _)| i)
+            .unwrap_or(self.rest().len());
+
+        if length == 0 {
+            return Ok(None);
+        }
+
+        let integer = self.rest()[..length]
+            .parse()
+            .map_err(|_| self.error("invalid index"))?;
+        self.position += length;
+        Ok(Some(integer))
+    }
+
+    fn parse_quoted(&mut self) -> Result<String, QueryError> {
+        let quote = self.peek().ok_or_else(|| self.error("expected a quote"))?;
+        self.position += 1;
+
+        let mut result = String::new();
+        let mut characters = self.rest().char_indices();
+        while let Some((i, c)) = characters.next() {
+            match c {
+                '\\' => {
+                    if let Some((_, escaped)) = characters.next() {
+                        result.push(escaped);
+                    }
+                }
+                c if c == quote => {
+                    self.position += i + 1;
+                    return Ok(result);
+                }
+                c => result.push(c),
+            }
+        }
+
+        Err(self.error("unterminated string"))
+    }
+
+    fn parse_filter(&mut self) -> Result<Filter, QueryError> {
+        let parenthesised = self.eat("(");
+        self.skip_whitespace();
+        self.expect("@")?;
+
+        let mut path = Vec::new();
+        loop {
+            if self.eat(".") {
+                path.push(PathSegment::Key(self.parse_name()?));
+            } else if self.eat("[") {
+                self.skip_whitespace();
+                let segment = match self.peek() {
+                    Some('\'' | '"') => PathSegment::Key(self.parse_quoted()?),
+                    _ => PathSegment::Index(
+                        self.parse_optional_integer()?
+                            .and_then(|index| usize::try_from(index).ok())
+                            .ok_or_else(|| self.error("expected an index or key"))?,
+                    ),
+                };
+                self.skip_whitespace();
+                self.expect("]")?;
+                path.push(segment);
+            } else {
+                break;
+            }
+        }
+
+        self.skip_whitespace();
+        let comparison = [
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+        ]
+        .into_iter()
+        .find(|(operator, _)| self.eat(operator));
+
+        let comparison = match comparison {
+            Some((_, comparison)) => {
+                self.skip_whitespace();
+                Some((comparison, self.parse_literal()?))
+            }
+            None => None,
+        };
+
+        self.skip_whitespace();
+        if parenthesised {
+            self.expect(")")?;
+        }
+
+        Ok(Filter { path, comparison })
+    }
+
+    fn parse_literal(&mut self) -> Result<JsonValue, QueryError> {
+        if let Some('\'' | '"') = self.peek() {
+            return Ok(JsonValue::String(self.parse_quoted()?));
+        }
+
+        for (keyword, value) in [
+            ("true", JsonValue::Boolean(true)),
+            ("false", JsonValue::Boolean(false)),
+            ("null", JsonValue::Null),
+        ] {
+            if self.eat(keyword) {
+                return Ok(value);
+            }
+        }
+
+        let length = self
+            .rest()
+            .find(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')))
+            .unwrap_or(self.rest().len());
+        let number = self.rest()[..length]
+            .parse()
+            .map_err(|_| self.error("expected a string, number, boolean or null"))?;
+        self.position += length;
+        Ok(JsonValue::Number(number))
+    }
+}
+
+fn is_name_character(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+#[derive(Debug)]
+pub struct QueryError {
+    message: String,
+    /// The byte offset into the query where parsing failed
+    position: usize,
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position + 1)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// The state of the query screen
+pub struct QueryView {
+    pub input: String,
+    pub results: Vec<QueryResult>,
+    pub result_list_ui_state: ListState,
+    pub error: Option<String>,
+    /// Whether keys move through the results rather than typing into the query bar
+    pub results_focused: bool,
+}
+
+impl QueryView {
+    pub fn new() -> QueryView {
+        QueryView {
+            input: String::new(),
+            results: Vec::new(),
+            result_list_ui_state: ListState::default(),
+            error: None,
+            results_focused: false,
+        }
+    }
+
+    /// Runs the query bar's contents again, eg: after the document has changed
+    pub fn refresh(&mut self, data: &JsonData) {
+        if self.input.trim().is_empty() {
+            self.results.clear();
+            self.error = None;
+        } else {
+            match run_query(data, &self.input) {
+                Ok(results) => {
+                    self.results = results;
+                    self.error = None;
+                }
+                // keep showing the last results while a query is being typed
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
+        let selected = self.result_list_ui_state.selected();
+        self.result_list_ui_state
+            .select(match (selected, self.results.len()) {
+                (_, 0) => None,
+                (Some(index), length) => Some(index.min(length - 1)),
+                (None, _) => Some(0),
+            });
+        if self.results.is_empty() {
+            self.results_focused = false;
+        }
+    }
+
+    pub fn selected_result(&self) -> Option<&QueryResult> {
+        self.results.get(self.result_list_ui_state.selected()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::json_data_from_serde;
+    use crate::path::to_json_path;
+
+    fn document(json: &str) -> JsonData {
+        match serde_json::from_str(json).unwrap() {
+            serde_json::Value::Object(data) => json_data_from_serde(data),
+            _ => panic!("test documents must be objects"),
+        }
+    }
+
+    /// Each result as its path and its value's JSON source
+    fn query(json: &str, query: &str) -> Vec<(String, String)> {
+        run_query(&document(json), query)
+            .unwrap()
+            .into_iter()
+            .map(|result| {
+                (
+                    to_json_path(&result.path),
+                    serde_json::to_string(&result.value).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn error(query: &str) -> String {
+        run_query(&JsonData::new(), query)
+            .err()
+            .expect("the query should not parse")
+            .to_string()
+    }
+
+    const SERVICES: &str = r#"{
+        "services": [
+            {"name": "web", "port": 80, "tags": ["public"]},
+            {"name": "db", "port": 5432},
+            {"name": "cache", "port": 6379, "enabled": false}
+        ]
+    }"#;
+
+    #[test]
+    fn selects_keys_and_indexes() {
+        assert_eq!(
+            query(SERVICES, "$.services[0].name"),
+            [("$.services[0].name".to_string(), "\"web\"".to_string())]
+        );
+        assert_eq!(
+            query(SERVICES, "$['services'][-1].port"),
+            [("$.services[2].port".to_string(), "6379".to_string())]
+        );
+    }
+
+    #[test]
+    fn leading_dollar_and_empty_brackets_are_optional() {
+        assert_eq!(
+            query(SERVICES, ".services[].port"),
+            query(SERVICES, "$.services[*].port")
+        );
+    }
+
+    #[test]
+    fn selects_wildcards_slices_and_unions() {
+        let ports = |query_text| {
+            query(SERVICES, query_text)
+                .into_iter()
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ports("$.services[*].port"), ["80", "5432", "6379"]);
+        assert_eq!(ports("$.services[1:3].port"), ["5432", "6379"]);
+        assert_eq!(ports("$.services[0,2].port"), ["80", "6379"]);
+    }
+
+    #[test]
+    fn recursive_descent_finds_nested_keys() {
+        let results = query(r#"{"a": {"id": 1, "b": [{"id": 2}]}, "id": 3}"#, "$..id");
+        let mut values = results
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect::<Vec<_>>();
+        values.sort();
+        assert_eq!(values, ["1", "2", "3"]);
+    }
+
+    #[test]
+    fn filters_by_existence_and_comparison() {
+        let names = |query_text| {
+            query(SERVICES, query_text)
+                .into_iter()
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names("$.services[?(@.tags)].name"), ["\"web\""]);
+        assert_eq!(
+            names("$.services[?(@.port > 1000)].name"),
+            ["\"db\"", "\"cache\""]
+        );
+        assert_eq!(names("$.services[?(@.name == 'db')].port"), ["5432"]);
+        assert_eq!(
+            names("$.services[?(@.enabled == false)].name"),
+            ["\"cache\""]
+        );
+    }
+
+    #[test]
+    fn missing_keys_match_nothing() {
+        assert!(query(SERVICES, "$.nothing[0]").is_empty());
+        assert!(query(SERVICES, "$.services[7]").is_empty());
+    }
+
+    #[test]
+    fn reports_where_a_filter_is_malformed() {
+        assert_eq!(
+            error("$.a[?(@.b == )]"),
+            "expected a string, number, boolean or null at position 14"
+        );
+        assert_eq!(error("$.a[?(@.b == 1]"), "expected `)` at position 15");
+        assert_eq!(error("$.a[?(b)]"), "expected `@` at position 7");
+    }
+
+    #[test]
+    fn reports_unterminated_strings_and_brackets() {
+        assert_eq!(error("$['a"), "unterminated string at position 4");
+        assert_eq!(error("$.a[0"), "expected `]` at position 6");
+        assert_eq!(error("$a"), "expected `.` or `[` at position 2");
+    }
+}
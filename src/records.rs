@@ -0,0 +1,492 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
+use crate::app::JsonValue;
+use crate::format::{FileFormat, FormatError};
+use crate::history::History;
+use crate::path::PathSegment;
+
+/// How much of a record is read to summarise it in the list
+const SUMMARY_BYTES: usize = 256;
+
+/// One record of a JSON Lines file
+enum Record {
+    /// A line of the file that has not been changed, which is read again whenever it is needed
+    Line { offset: u64, length: u64 },
+    /// Boxed so the many unchanged lines each take as little memory as possible
+    Edited(Box<JsonValue>),
+}
+
+/// How to reverse a change to the list of records. Changes are kept rather than
+/// snapshots of the list, which may be too long to copy on every change.
+enum RecordChange {
+    Remove(usize),
+    Insert(usize, Record),
+    Swap(usize, usize),
+    Replace(usize, Record),
+}
+
+/// The records of a JSON Lines (NDJSON) file, one JSON value per line. Only
+/// where each line starts is kept in memory, so files far larger than memory
+/// can be browsed, edited and saved.
+pub struct RecordList {
+    pub file: String,
+    source: File,
+    records: Vec<Record>,
+    pub selected: Option<usize>,
+    /// The first record shown in the list
+    pub scroll_offset: usize,
+    /// The record open in the main editor
+    pub open_record: Option<usize>,
+    /// Set while waiting for the selected record's deletion to be confirmed
+    pub delete_requested: bool,
+    pub unsaved: bool,
+    history: History<RecordChange>,
+}
+
+impl RecordList {
+    pub fn open(path: &str) -> io::Result<RecordList> {
+        let source = File::open(path)?;
+        let records = index_lines(&source)?;
+
+        Ok(RecordList {
+            file: path.to_string(),
+            selected: if records.is_empty() { None } else { Some(0) },
+            source,
+            records,
+            scroll_offset: 0,
+            open_record: None,
+            delete_requested: false,
+            unsaved: false,
+            history: History::default(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.map(|index| index.saturating_sub(1));
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = self
+            .selected
+            .map(|index| (index + 1).min(self.records.len().saturating_sub(1)));
+    }
+
+    fn read_bytes(&self, offset: u64, length: u64) -> io::Result<Vec<u8>> {
+        let mut source = &self.source;
+        source.seek(SeekFrom::Start(offset))?;
+
+        let mut bytes = Vec::new();
+        source.take(length).read_to_end(&mut bytes)?;
+        bytes.truncate(bytes.trim_ascii_end().len());
+        Ok(bytes)
+    }
+
+    /// The start of a record's compact JSON, for showing in the list
+    pub fn summary(&self, index: usize) -> String {
+        let (text, truncated) = match self.records.get(index) {
+            Some(Record::Line { offset, length }) => {
+                match self.read_bytes(*offset, (*length).min(SUMMARY_BYTES as u64)) {
+                    Ok(bytes) => (
+                        String::from_utf8_lossy(&bytes).to_string(),
+                        *length > SUMMARY_BYTES as u64,
+                    ),
+                    Err(e) => return format!("Unable to read the record: {e}"),
+                }
+            }
+            Some(Record::Edited(value)) => {
+                let text = serde_json::to_string(value).unwrap_or_default();
+                match text.char_indices().nth(SUMMARY_BYTES) {
+                    Some((end, _)) => (text[..end].to_string(), true),
+                    None => (text, false),
+                }
+            }
+            None => return String::new(),
+        };
+
+        if truncated {
+            format!("{text}…")
+        } else {
+            text
+        }
+    }
+
+    pub fn read(&self, index: usize) -> Result<JsonValue, RecordError> {
+        match &self.records[index] {
+            Record::Line { offset, length } => {
+                let bytes = self.read_bytes(*offset, *length).map_err(RecordError::Io)?;
+                serde_json::from_slice(&bytes)
+                    .map(JsonValue::from_serde)
+                    .map_err(|e| RecordError::InvalidJson(index + 1, e))
+            }
+            Record::Edited(value) => Ok(value.as_ref().clone()),
+        }
+    }
+
+    pub fn replace(&mut self, index: usize, value: JsonValue) {
+        let previous = std::mem::replace(&mut self.records[index], Record::Edited(Box::new(value)));
+        self.record_change(RecordChange::Replace(index, previous));
+    }
+
+    /// Adds a record after the selected one and selects it
+    pub fn insert_after_selected(&mut self, value: JsonValue) {
+        let index = self.selected.map_or(self.records.len(), |index| index + 1);
+        self.records.insert(index, Record::Edited(Box::new(value)));
+        self.selected = Some(index);
+        self.record_change(RecordChange::Remove(index));
+    }
+
+    pub fn remove_selected(&mut self) {
+        let Some(index) = self.selected else {
+            return;
+        };
+
+        let removed = self.records.remove(index);
+        self.select_near(index);
+        self.record_change(RecordChange::Insert(index, removed));
+    }
+
+    /// Swaps the selected record with the one `offset` places away
+    pub fn move_selected(&mut self, offset: isize) {
+        let Some(index) = self.selected else {
+            return;
+        };
+        let Some(target) = index
+            .checked_add_signed(offset)
+            .filter(|target| *target < self.records.len())
+        else {
+            return;
+        };
+
+        self.records.swap(index, target);
+        self.selected = Some(target);
+        self.record_change(RecordChange::Swap(target, index));
+    }
+
+    /// Selects the record at `index`, or the last one if there are fewer records
+    fn select_near(&mut self, index: usize) {
+        self.selected = if self.records.is_empty() {
+            None
+        } else {
+            Some(index.min(self.records.len() - 1))
+        };
+    }
+
+    /// Must be called after each change with how to reverse it
+    fn record_change(&mut self, reverse: RecordChange) {
+        self.history.record(reverse);
+        self.unsaved = true;
+    }
+
+    /// Reverses the last change, returns whether there was anything to undo
+    pub fn undo(&mut self) -> bool {
+        let mut changed_index = None;
+        let undone = self.history.undo(|change| {
+            let (reverse, index) = apply(&mut self.records, change);
+            changed_index = Some(index);
+            reverse
+        });
+        if let Some(index) = changed_index {
+            self.select_near(index);
+            self.unsaved = true;
+        }
+        undone
+    }
+
+    /// Reapplies the last undone change, returns whether there was anything to redo
+    pub fn redo(&mut self) -> bool {
+        let mut changed_index = None;
+        let redone = self.history.redo(|change| {
+            let (reverse, index) = apply(&mut self.records, change);
+            changed_index = Some(index);
+            reverse
+        });
+        if let Some(index) = changed_index {
+            self.select_near(index);
+            self.unsaved = true;
+        }
+        redone
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    /// Fails if an edited record holds numbers JSON cannot write, which would be saved as `null`
+    pub fn check_numbers(&self) -> Result<(), FormatError> {
+        for (index, record) in self.records.iter().enumerate() {
+            if let Record::Edited(value) = record {
+                FileFormat::Ndjson
+                    .check_value_numbers(value, &mut vec![PathSegment::Index(index)])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the records to `path` one per line, streaming unchanged lines
+    /// from the source file. The records then refer to the new file, so the
+    /// changes made before saving can no longer be undone.
+    pub fn save(&mut self, path: &str) -> io::Result<usize> {
+        // the source may be the file being written, so write beside it and swap it in at the end
+        let temporary_path = format!("{path}.tmp");
+        let mut writer = BufWriter::new(File::create(&temporary_path)?);
+
+        let mut saved_records = Vec::with_capacity(self.records.len());
+        let mut bytes_written = 0;
+        for record in &self.records {
+            let line = match record {
+                Record::Line { offset, length } => self.read_bytes(*offset, *length)?,
+                Record::Edited(value) => serde_json::to_vec(value)?,
+            };
+            writer.write_all(&line)?;
+            writer.write_all(b"\n")?;
+
+            saved_records.push(Record::Line {
+                offset: bytes_written as u64,
+                length: line.len() as u64,
+            });
+            bytes_written += line.len() + 1;
+        }
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&temporary_path, path)?;
+
+        self.source = File::open(path)?;
+        self.records = saved_records;
+        self.file = path.to_string();
+        self.unsaved = false;
+        self.history = History::default();
+
+        Ok(bytes_written)
+    }
+}
+
+/// Makes a change, returning how to reverse it and the index of the record it affects
+fn apply(records: &mut Vec<Record>, change: RecordChange) -> (RecordChange, usize) {
+    match change {
+        RecordChange::Remove(index) => {
+            let removed = records.remove(index);
+            (RecordChange::Insert(index, removed), index)
+        }
+        RecordChange::Insert(index, record) => {
+            records.insert(index, record);
+            (RecordChange::Remove(index), index)
+        }
+        RecordChange::Swap(index, target) => {
+            records.swap(index, target);
+            (RecordChange::Swap(target, index), target)
+        }
+        RecordChange::Replace(index, record) => {
+            let previous = std::mem::replace(&mut records[index], record);
+            (RecordChange::Replace(index, previous), index)
+        }
+    }
+}
+
+/// Finds where each non-blank line of the file starts, without holding more
+/// than a buffer of it in memory at once
+fn index_lines(source: &File) -> io::Result<Vec<Record>> {
+    let mut reader = BufReader::new(source);
+    let mut records = Vec::new();
+
+    let mut line_start = 0;
+    let mut position = 0;
+    let mut line_has_content = false;
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+
+        let (consumed, line_ended) = match buffer.iter().position(|byte| *byte == b'\n') {
+            Some(index) => (index + 1, true),
+            None => (buffer.len(), false),
+        };
+        line_has_content |= buffer[..consumed]
+            .iter()
+            .any(|byte| !byte.is_ascii_whitespace());
+        reader.consume(consumed);
+        position += consumed as u64;
+
+        if line_ended {
+            if line_has_content {
+                records.push(Record::Line {
+                    offset: line_start,
+                    length: position - line_start,
+                });
+            }
+            line_start = position;
+            line_has_content = false;
+        }
+    }
+
+    // the last line may not end with a newline
+    if line_has_content {
+        records.push(Record::Line {
+            offset: line_start,
+            length: position - line_start,
+        });
+    }
+
+    Ok(records)
+}
+
+#[derive(Debug)]
+pub enum RecordError {
+    Io(io::Error),
+    /// The record number, counting from one, and why it could not be parsed
+    InvalidJson(usize, serde_json::Error),
+}
+
+impl Display for RecordError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            RecordError::Io(e) => write!(f, "Unable to read the record: {e}"),
+            RecordError::InvalidJson(number, e) => {
+                write!(f, "Record {number} is not valid JSON: {e}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// A record list over a file holding `text`, along with the directory the file is in
+    fn open(text: &str) -> (TempDir, RecordList) {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("records.jsonl");
+        fs::write(&path, text).unwrap();
+        let records = RecordList::open(path.to_str().unwrap()).unwrap();
+        (directory, records)
+    }
+
+    /// Each record's compact JSON
+    fn contents(records: &RecordList) -> Vec<String> {
+        (0..records.len())
+            .map(|index| serde_json::to_string(&records.read(index).unwrap()).unwrap())
+            .collect()
+    }
+
+    fn number(n: f64) -> JsonValue {
+        JsonValue::Number(n)
+    }
+
+    #[test]
+    fn indexes_non_blank_lines() {
+        let (_directory, records) = open("{\"a\": 1}\r\n\n   \n[2]\r\n3");
+        assert_eq!(contents(&records), ["{\"a\":1}", "[2]", "3"]);
+        assert_eq!(records.selected, Some(0));
+
+        let (_directory, records) = open("\n\n");
+        assert!(records.is_empty());
+        assert_eq!(records.selected, None);
+    }
+
+    #[test]
+    fn summarises_long_records() {
+        let long = format!("\"{}\"", "x".repeat(SUMMARY_BYTES));
+        let (_directory, records) = open(&format!("1\n{long}\n"));
+        assert_eq!(records.summary(0), "1");
+        assert!(records.summary(1).ends_with('…'));
+        assert_eq!(records.summary(1).chars().count(), SUMMARY_BYTES + 1);
+    }
+
+    #[test]
+    fn reports_the_number_of_an_invalid_record() {
+        let (_directory, records) = open("1\n{oops\n");
+        let Err(error) = records.read(1) else {
+            panic!("the record should not parse")
+        };
+        assert!(error.to_string().starts_with("Record 2 is not valid JSON"));
+    }
+
+    #[test]
+    fn saves_unchanged_and_edited_records() {
+        let (directory, mut records) = open("1\r\n\n{\"a\": 2}\n3");
+        records.replace(1, number(20.0));
+        records.selected = Some(2);
+        records.insert_after_selected(number(4.0));
+
+        let path = directory.path().join("saved.jsonl");
+        let path = path.to_str().unwrap();
+        let bytes_written = records.save(path).unwrap();
+
+        let saved = fs::read_to_string(path).unwrap();
+        assert_eq!(saved, "1\n20\n3\n4\n");
+        assert_eq!(bytes_written, saved.len());
+        assert_eq!(records.file, path);
+        assert!(!records.unsaved);
+        assert!(!records.can_undo());
+        // the records now refer to the lines of the saved file
+        assert_eq!(contents(&records), ["1", "20", "3", "4"]);
+    }
+
+    #[test]
+    fn undoes_and_redoes_each_kind_of_change() {
+        let (_directory, mut records) = open("1\n2\n3\n");
+
+        records.insert_after_selected(number(10.0));
+        assert_eq!(contents(&records), ["1", "10", "2", "3"]);
+        records.remove_selected();
+        assert_eq!(contents(&records), ["1", "2", "3"]);
+        records.move_selected(1);
+        assert_eq!(contents(&records), ["1", "3", "2"]);
+        assert_eq!(records.selected, Some(2));
+        records.replace(0, number(100.0));
+        assert_eq!(contents(&records), ["100", "3", "2"]);
+
+        assert!(records.undo());
+        assert_eq!(contents(&records), ["1", "3", "2"]);
+        assert_eq!(records.selected, Some(0));
+        assert!(records.undo());
+        assert_eq!(contents(&records), ["1", "2", "3"]);
+        assert_eq!(records.selected, Some(1));
+        assert!(records.undo());
+        assert_eq!(contents(&records), ["1", "10", "2", "3"]);
+        assert!(records.undo());
+        assert_eq!(contents(&records), ["1", "2", "3"]);
+        assert!(!records.undo());
+
+        while records.redo() {}
+        assert_eq!(contents(&records), ["100", "3", "2"]);
+
+        // a new change can no longer be followed by the undone ones
+        records.undo();
+        records.remove_selected();
+        assert!(!records.can_redo());
+    }
+
+    #[test]
+    fn refuses_to_save_numbers_json_cannot_write() {
+        let (_directory, mut records) = open("1\n2\n");
+        assert!(records.check_numbers().is_ok());
+
+        records.replace(1, JsonValue::Array(vec![number(f64::NAN)]));
+        assert_eq!(
+            records.check_numbers().unwrap_err().to_string(),
+            "NDJSON cannot represent infinite and NaN numbers, found one at [1][0]"
+        );
+    }
+}
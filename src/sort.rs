@@ -0,0 +1,173 @@
+use core::fmt;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+use clap::ValueEnum;
+
+use crate::app::{App, JsonData, JsonValue};
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum SortOrder {
+    /// Keys from A to Z
+    #[value(name = "asc")]
+    KeyAscending,
+    /// Keys from Z to A
+    #[value(name = "desc")]
+    KeyDescending,
+    /// Keys from A to Z, comparing runs of digits by their numeric value
+    Natural,
+    /// Group pairs by the type of their value, keeping the existing order within each group
+    Type,
+}
+
+impl SortOrder {
+    pub fn all() -> Vec<SortOrder> {
+        vec![
+            SortOrder::KeyAscending,
+            SortOrder::KeyDescending,
+            SortOrder::Natural,
+            SortOrder::Type,
+        ]
+    }
+}
+
+impl Display for SortOrder {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            SortOrder::KeyAscending => write!(f, "Key (A-Z)"),
+            SortOrder::KeyDescending => write!(f, "Key (Z-A)"),
+            SortOrder::Natural => write!(f, "Natural"),
+            SortOrder::Type => write!(f, "Type"),
+        }
+    }
+}
+
+/// Reorders the pairs of `data`, and when `recursive` is set, the pairs
+/// of every object nested inside it (including objects inside arrays)
+pub fn sort_pairs(data: &mut JsonData, order: SortOrder, recursive: bool) {
+    match order {
+        SortOrder::KeyAscending => data.sort_by(|a, _, b, _| a.cmp(b)),
+        SortOrder::KeyDescending => data.sort_by(|a, _, b, _| b.cmp(a)),
+        SortOrder::Natural => data.sort_by(|a, _, b, _| natural_cmp(a, b)),
+        SortOrder::Type => data.sort_by(|_, a, _, b| type_rank(a).cmp(&type_rank(b))),
+    }
+
+    if recursive {
+        for value in data.values_mut() {
+            sort_nested_pairs(value, order);
+        }
+    }
+}
+
+fn sort_nested_pairs(value: &mut JsonValue, order: SortOrder) {
+    match value {
+        JsonValue::Object(data) => sort_pairs(data, order, true),
+        JsonValue::Array(items) => {
+            for item in items {
+                sort_nested_pairs(item, order);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Types are grouped in the same order they are listed in the type selection popup
+fn type_rank(value: &JsonValue) -> usize {
+    App::all_value_types()
+        .iter()
+        .position(|value_type| *value_type == value.value_type())
+        .unwrap_or(usize::MAX)
+}
+
+/// Compares strings so that embedded numbers are ordered by value,
+/// eg: "item2" comes before "item10"
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chunks = chunks(a);
+    let mut b_chunks = chunks(b);
+
+    loop {
+        let ordering = match (a_chunks.next(), b_chunks.next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_chunk), Some(b_chunk)) => {
+                let a_is_number = a_chunk.starts_with(|c: char| c.is_ascii_digit());
+                let b_is_number = b_chunk.starts_with(|c: char| c.is_ascii_digit());
+
+                if a_is_number && b_is_number {
+                    let a_digits = a_chunk.trim_start_matches('0');
+                    let b_digits = b_chunk.trim_start_matches('0');
+                    // with leading zeros removed, a longer number is always larger
+                    a_digits
+                        .len()
+                        .cmp(&b_digits.len())
+                        .then_with(|| a_digits.cmp(b_digits))
+                } else {
+                    a_chunk.cmp(b_chunk)
+                }
+            }
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Splits a string into alternating runs of digits and non-digits
+fn chunks(s: &str) -> impl Iterator<Item = &str> {
+    let mut remaining = s;
+
+    std::iter::from_fn(move || {
+        let first = remaining.chars().next()?;
+        let is_digit = first.is_ascii_digit();
+        let end = remaining
+            .find(|c: char| c.is_ascii_digit() != is_digit)
+            .unwrap_or(remaining.len());
+        let (chunk, rest) = remaining.split_at(end);
+        remaining = rest;
+        Some(chunk)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_embedded_numbers_by_value() {
+        assert_eq!(natural_cmp("a2", "a10"), Ordering::Less);
+        assert_eq!(natural_cmp("a10", "a2"), Ordering::Greater);
+        assert_eq!(natural_cmp("a2b", "a2c"), Ordering::Less);
+        assert_eq!(natural_cmp("a", "a1"), Ordering::Less);
+        assert_eq!(natural_cmp("a10", "a10"), Ordering::Equal);
+    }
+
+    #[test]
+    fn ignores_leading_zeros_until_the_numbers_are_equal() {
+        assert_eq!(natural_cmp("a002", "a10"), Ordering::Less);
+        assert_eq!(natural_cmp("a010", "a9"), Ordering::Greater);
+        // equal numbers fall back to comparing the text
+        assert_eq!(natural_cmp("a01", "a1"), Ordering::Less);
+        assert_eq!(natural_cmp("a0", "a00"), Ordering::Less);
+    }
+
+    #[test]
+    fn compares_numbers_too_long_for_an_integer() {
+        assert_eq!(
+            natural_cmp("a99999999999999999999999", "a100000000000000000000000"),
+            Ordering::Less
+        );
+        assert_eq!(
+            natural_cmp("a123456789012345678901", "a123456789012345678902"),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn orders_uppercase_before_lowercase() {
+        assert_eq!(natural_cmp("B", "a"), Ordering::Less);
+        assert_eq!(natural_cmp("a10", "A2"), Ordering::Greater);
+        assert_eq!(natural_cmp("Item2", "Item10"), Ordering::Less);
+    }
+}
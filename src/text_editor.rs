@@ -0,0 +1,161 @@
+/// A multi-line text buffer with a cursor, used by the full-screen editors.
+/// The cursor column is measured in characters rather than bytes.
+pub struct TextEditor {
+    pub lines: Vec<String>,
+    pub cursor_row: usize,
+    pub cursor_col: usize,
+    /// The first visual (wrapped) row shown in the viewport
+    pub scroll: usize,
+    /// Whether the buffer holds the JSON-escaped form of the text
+    pub escaped: bool,
+}
+
+impl TextEditor {
+    pub fn new(text: &str) -> TextEditor {
+        let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+
+        TextEditor {
+            lines,
+            cursor_row: 0,
+            cursor_col: 0,
+            scroll: 0,
+            escaped: false,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn insert_char(&mut self, character: char) {
+        let byte_index = self.cursor_byte_index();
+        self.lines[self.cursor_row].insert(byte_index, character);
+        self.cursor_col += 1;
+    }
+
+    pub fn insert_newline(&mut self) {
+        let byte_index = self.cursor_byte_index();
+        let remainder = self.lines[self.cursor_row].split_off(byte_index);
+        self.lines.insert(self.cursor_row + 1, remainder);
+        self.cursor_row += 1;
+        self.cursor_col = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor_col > 0 {
+            self.cursor_col -= 1;
+            let byte_index = self.cursor_byte_index();
+            self.lines[self.cursor_row].remove(byte_index);
+        } else if self.cursor_row > 0 {
+            let line = self.lines.remove(self.cursor_row);
+            self.cursor_row -= 1;
+            self.cursor_col = self.current_line_length();
+            self.lines[self.cursor_row].push_str(&line);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        if self.cursor_col > 0 {
+            self.cursor_col -= 1;
+        } else if self.cursor_row > 0 {
+            self.cursor_row -= 1;
+            self.cursor_col = self.current_line_length();
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if self.cursor_col < self.current_line_length() {
+            self.cursor_col += 1;
+        } else if self.cursor_row + 1 < self.lines.len() {
+            self.cursor_row += 1;
+            self.cursor_col = 0;
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        if self.cursor_row > 0 {
+            self.cursor_row -= 1;
+            self.cursor_col = self.cursor_col.min(self.current_line_length());
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        if self.cursor_row + 1 < self.lines.len() {
+            self.cursor_row += 1;
+            self.cursor_col = self.cursor_col.min(self.current_line_length());
+        }
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor_col = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor_col = self.current_line_length();
+    }
+
+    /// Moves the cursor to the given zero-based line and column, clamping
+    /// both to the contents of the buffer
+    pub fn move_to(&mut self, row: usize, col: usize) {
+        self.cursor_row = row.min(self.lines.len() - 1);
+        self.cursor_col = col.min(self.current_line_length());
+    }
+
+    /// Splits the buffer into rows no wider than `width` characters.
+    /// Returns the rows along with the visual position of the cursor.
+    pub fn wrap(&self, width: usize) -> (Vec<String>, (usize, usize)) {
+        let width = width.max(1);
+        let mut rows = Vec::new();
+        let mut cursor_position = (0, 0);
+
+        for (line_index, line) in self.lines.iter().enumerate() {
+            let characters: Vec<char> = line.chars().collect();
+
+            if line_index == self.cursor_row {
+                cursor_position = (
+                    rows.len() + self.cursor_col / width,
+                    self.cursor_col % width,
+                );
+            }
+
+            if characters.is_empty() {
+                rows.push(String::new());
+            } else {
+                rows.extend(characters.chunks(width).map(|c| c.iter().collect()));
+            }
+
+            // a cursor sitting right after a full row belongs on a new row
+            if line_index == self.cursor_row && cursor_position.0 >= rows.len() {
+                rows.push(String::new());
+            }
+        }
+
+        (rows, cursor_position)
+    }
+
+    /// Adjusts `scroll` so the cursor row is inside a viewport of the given height
+    pub fn scroll_to_cursor(&mut self, cursor_visual_row: usize, viewport_height: usize) {
+        let viewport_height = viewport_height.max(1);
+
+        if cursor_visual_row < self.scroll {
+            self.scroll = cursor_visual_row;
+        } else if cursor_visual_row >= self.scroll + viewport_height {
+            self.scroll = cursor_visual_row + 1 - viewport_height;
+        }
+    }
+
+    fn current_line_length(&self) -> usize {
+        self.lines[self.cursor_row].chars().count()
+    }
+
+    fn cursor_byte_index(&self) -> usize {
+        let line = &self.lines[self.cursor_row];
+        line.char_indices()
+            .nth(self.cursor_col)
+            .map(|(index, _)| index)
+            .unwrap_or(line.len())
+    }
+}
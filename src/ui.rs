@@ -1,27 +1,38 @@
+use std::collections::HashSet;
 use std::io;
 
 use ratatui::{
     crossterm::event::KeyCode,
-    layout::{Constraint, Direction, Flex, Layout, Rect},
+    layout::{Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Clear, List, ListItem, Padding, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Padding, Paragraph, Wrap},
     Frame,
 };
 
 use crate::app::{
-    App, AppScreen, Binding, EditFocus, ExitFocus, JsonData, JsonValue, JsonValueType,
+    ActionCategory, App, AppScreen, Binding, EditFocus, ExitFocus, JsonData, JsonValue, Severity,
+    StatusMessage,
 };
+use crate::diff_view::{DiffSide, DiffStatus};
+use crate::find_replace::{FindReplaceField, MatchLocation};
+use crate::format::FileFormat;
+use crate::patch::PatchOperation;
+use crate::path::{display_path, to_json_path};
+use crate::sort::SortOrder;
 
 const COLOR_ACCENT: Color = Color::LightYellow;
 const COLOR_SURFACE: Color = Color::DarkGray;
 
 pub fn ui(frame: &mut Frame, app: &mut App) -> Result<(), io::Error> {
+    let status_bar_height = if app.status_message.is_some() { 1 } else { 0 };
+
     let vertical_panels = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3),
             Constraint::Min(1),
+            Constraint::Length(status_bar_height),
             Constraint::Length(3),
         ])
         .split(frame.area());
@@ -29,17 +40,69 @@ pub fn ui(frame: &mut Frame, app: &mut App) -> Result<(), io::Error> {
     let header = compose_header(app);
     frame.render_widget(header, vertical_panels[0]); // render title to top panel
 
+    if let Some(status_message) = &app.status_message {
+        let status_bar = compose_status_bar(status_message);
+        frame.render_widget(status_bar, vertical_panels[2]);
+    }
+
     let footer = compose_footer(app);
-    frame.render_widget(footer, vertical_panels[2]);
+    frame.render_widget(footer, vertical_panels[3]);
 
-    let pairs_list = compose_pairs_list(&app.pairs);
+    let pairs_list = compose_pairs_list(app.current_pairs(), &app.marked_keys);
     frame.render_stateful_widget(pairs_list, vertical_panels[1], &mut app.list_ui_state);
+    app.pairs_list_area = vertical_panels[1];
+
+    if let Some(target_delete_keys) = &app.target_delete_keys {
+        let message = match target_delete_keys.as_slice() {
+            [target_delete_key] => {
+                format!("Are you sure you want to delete the key: \"{target_delete_key}\"?")
+            }
+            _ => format!(
+                "Are you sure you want to delete these {} keys?\n{}",
+                target_delete_keys.len(),
+                target_delete_keys.join(", ")
+            ),
+        };
+        render_delete_confirm_popup(frame, message);
+    }
+
+    if let Some(error) = app
+        .external_edit
+        .as_ref()
+        .and_then(|edit| edit.error.as_ref())
+    {
+        render_external_edit_error_popup(frame, error);
+    }
+
+    if app.convert_target_keys.is_some() {
+        render_convert_popup(frame, app);
+    }
+
+    if app.sort_popup_open {
+        render_sort_popup(frame, app);
+    }
 
-    if let Some(target_delete_key) = &app.target_delete_key {
-        render_delete_confirm_popup(frame, target_delete_key);
+    if app.find_replace.is_some() {
+        render_find_replace_popup(frame, app);
     }
 
-    if app.edit_popup_focus.is_some() {
+    if app.copy_path_options.is_some() {
+        render_copy_path_popup(frame, app);
+    }
+
+    if app.patch_export.is_some() {
+        render_patch_export_popup(frame, app);
+    }
+
+    if app.patch_apply.is_some() {
+        render_patch_apply_popup(frame, app);
+    }
+
+    if app.merge.is_some() {
+        render_merge_conflict_popup(frame, app);
+    }
+
+    if app.edit_popup_focus.is_some() && matches!(app.get_current_screen(), AppScreen::Editing) {
         if !app.type_list_open {
             render_editing_popup(frame, app)?;
         } else {
@@ -49,7 +112,7 @@ pub fn ui(frame: &mut Frame, app: &mut App) -> Result<(), io::Error> {
 
     match app.get_current_screen() {
         AppScreen::Preview => {
-            let preview = compose_preview_screen(app)?;
+            let preview = compose_preview_screen(app);
 
             frame.render_widget(Clear, vertical_panels[1]);
             frame.render_widget(preview, vertical_panels[1]);
@@ -58,40 +121,117 @@ pub fn ui(frame: &mut Frame, app: &mut App) -> Result<(), io::Error> {
             frame.render_widget(Clear, frame.area()); //this clears the entire screen and anything already drawn
             render_exit_popup(frame, app);
         }
+        AppScreen::Query => {
+            frame.render_widget(Clear, vertical_panels[1]);
+            render_query_screen(frame, app, vertical_panels[1]);
+        }
+        AppScreen::Diff => {
+            frame.render_widget(Clear, vertical_panels[1]);
+            render_diff_screen(frame, app, vertical_panels[1]);
+        }
+        AppScreen::Records => {
+            frame.render_widget(Clear, vertical_panels[1]);
+            render_records_screen(frame, app, vertical_panels[1]);
+        }
+        AppScreen::StringEditor => {
+            frame.render_widget(Clear, vertical_panels[1]);
+            render_text_editor(frame, app, vertical_panels[1]);
+        }
+        AppScreen::RawEditor => {
+            let [editor_panel, validation_panel] =
+                Layout::vertical([Constraint::Fill(1), Constraint::Length(1)])
+                    .areas(vertical_panels[1]);
+
+            let validation_text = match app.validate_raw_editor() {
+                Some(Err(e)) => Span::styled(format!(" {e}"), Style::default().fg(Color::Red)),
+                _ => Span::styled(" Valid JSON", Style::default().fg(Color::Green)),
+            };
+
+            frame.render_widget(Clear, vertical_panels[1]);
+            render_text_editor(frame, app, editor_panel);
+            frame.render_widget(
+                Paragraph::new(Line::from(validation_text)),
+                validation_panel,
+            );
+        }
         _ => {}
     }
 
-    return Ok(());
+    if app.help_open {
+        render_help_popup(frame, app);
+    }
+
+    Ok(())
 }
 
-fn compose_header(app: &App) -> Paragraph {
+fn compose_header(app: &App) -> Paragraph<'_> {
     let title_block = Block::default()
         .borders(Borders::ALL)
         .style(Style::default());
 
-    Paragraph::new(Text::styled(
+    let title = Span::styled(
         match app.get_current_screen() {
             AppScreen::Preview => "Preview",
+            AppScreen::StringEditor => "String Editor",
+            AppScreen::RawEditor => "Raw JSON Editor",
+            AppScreen::Query => "Query",
+            AppScreen::Diff => "Diff",
+            AppScreen::Records => "Records",
             _ => "JSON Editor",
         },
         Style::default().fg(Color::Green),
-    ))
-    .block(title_block)
+    );
+
+    let mut header_spans = vec![title];
+    if matches!(app.get_current_screen(), AppScreen::Preview) && app.format != FileFormat::Json {
+        header_spans.push(Span::styled(
+            format!(" ({})", app.format),
+            Style::default().fg(Color::Green),
+        ));
+    }
+    if !app.marked_keys.is_empty() && matches!(app.get_current_screen(), AppScreen::Main) {
+        header_spans.push(Span::styled(
+            format!("  {} marked", app.marked_keys.len()),
+            Style::default().fg(Color::Blue),
+        ));
+    }
+    if let Some(index) = app
+        .records
+        .as_ref()
+        .and_then(|records| records.open_record)
+        .filter(|_| matches!(app.get_current_screen(), AppScreen::Main))
+    {
+        header_spans.push(Span::styled(
+            format!("  record {}", index + 1),
+            Style::default().fg(Color::Blue),
+        ));
+    }
+    let path = match (app.get_current_screen(), &app.diff_view) {
+        (AppScreen::Diff, Some(diff_view)) => &diff_view.path,
+        _ => &app.current_path,
+    };
+    if !path.is_empty() {
+        header_spans.push(Span::styled(
+            format!("  root > {}", path.join(" > ")),
+            Style::default().fg(COLOR_ACCENT),
+        ));
+    }
+
+    Paragraph::new(Line::from(header_spans)).block(title_block)
 }
 
-fn compose_footer(app: &App) -> Paragraph {
+fn compose_footer(app: &App) -> Paragraph<'_> {
     let current_keys_hint = Span::styled(
         format!(
             " {}",
             app.available_bindings
                 .iter()
                 .filter_map(|(binding, action)| {
-                    let key_label = match binding {
-                        Binding::Static(KeyCode::Enter) => "Enter",
-                        kc => &format!("{kc}"),
-                    };
-
-                    return Some(format!("({}) {}", key_label, action.description()?));
+                    Some(format!(
+                        "({}) {}",
+                        compose_binding_label(binding),
+                        action.description()?
+                    ))
                 })
                 .collect::<Vec<_>>()
                 .join(" | ")
@@ -102,24 +242,96 @@ fn compose_footer(app: &App) -> Paragraph {
     Paragraph::new(Line::from(current_keys_hint)).block(Block::default().borders(Borders::ALL))
 }
 
-fn compose_pairs_list(pairs: &JsonData) -> List {
+fn compose_status_bar(status_message: &StatusMessage) -> Paragraph<'_> {
+    let color = match status_message.severity {
+        Severity::Info => Color::Green,
+        Severity::Warn => Color::Yellow,
+        Severity::Error => Color::Red,
+    };
+
+    Paragraph::new(Line::from(vec![
+        Span::styled(
+            format!(" {}: ", status_message.severity),
+            Style::default().fg(color),
+        ),
+        Span::raw(status_message.text.as_str()),
+    ]))
+}
+
+fn compose_binding_label(binding: &Binding) -> String {
+    match binding {
+        Binding::Static(KeyCode::Enter) => "Enter".to_string(),
+        kc => format!("{kc}"),
+    }
+}
+
+fn render_help_popup(frame: &mut Frame, app: &mut App) {
+    let popup_block = Block::default()
+        .title(" Help")
+        .borders(Borders::NONE)
+        .padding(Padding::horizontal(1))
+        .style(Style::default().bg(COLOR_SURFACE));
+
+    let area = compose_popup(
+        Constraint::Percentage(60),
+        Constraint::Percentage(70),
+        frame.area(),
+    );
+
+    let bindings = app.screen_bindings();
+    let mut lines = Vec::<Line>::new();
+
+    for category in ActionCategory::all() {
+        let category_bindings = bindings
+            .iter()
+            .filter(|(_, action)| action.category() == category)
+            .collect::<Vec<_>>();
+
+        if category_bindings.is_empty() {
+            continue;
+        }
+
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(Span::styled(
+            format!("{category}"),
+            Style::default().fg(COLOR_ACCENT),
+        )));
+
+        for (binding, action) in category_bindings {
+            lines.push(Line::from(format!(
+                "  {: <12} {}",
+                compose_binding_label(binding),
+                action.help_text()
+            )));
+        }
+    }
+
+    // the popup block has no borders, so only the top row is taken up by the title
+    let visible_height = area.height.saturating_sub(1);
+    let max_scroll = (lines.len() as u16).saturating_sub(visible_height);
+    app.help_scroll = app.help_scroll.min(max_scroll);
+
+    let help_text = Paragraph::new(lines)
+        .block(popup_block)
+        .scroll((app.help_scroll, 0));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(help_text, area);
+}
+
+fn compose_pairs_list(pairs: &JsonData, marked_keys: &HashSet<String>) -> List<'static> {
     let mut list_items = Vec::<ListItem>::new();
 
-    for key in pairs.keys() {
+    for (key, value) in pairs {
+        let marker = if marked_keys.contains(key) {
+            "* "
+        } else {
+            "  "
+        };
         list_items.push(ListItem::new(Line::from(Span::styled(
-            format!(
-                "\"{: <25}: {}",
-                format!("{key}\""),
-                match pairs.get(key) {
-                    Some(value) => match value {
-                        JsonValue::String(s) => format!("\"{}\"", s),
-                        JsonValue::Boolean(b) => format!("{}", b),
-                        JsonValue::Number(n) => format!("{}", n),
-                        JsonValue::Null => "null".to_string(),
-                    },
-                    None => "null".to_string(),
-                }
-            ),
+            compose_pair_text(marker, key, value),
             Style::default().fg(COLOR_ACCENT),
         ))))
     }
@@ -131,7 +343,31 @@ fn compose_pairs_list(pairs: &JsonData) -> List {
         .highlight_style(Style::default().bg(COLOR_ACCENT).fg(Color::Black))
 }
 
-fn render_delete_confirm_popup(frame: &mut Frame, target_delete_key: &str) {
+/// A single line representation of a pair, `marker` is a two character
+/// prefix flagging the pair (eg: as marked)
+fn compose_pair_text(marker: &str, key: &str, value: &JsonValue) -> String {
+    format!(
+        "{marker}\"{: <25}: {}",
+        format!("{key}\""),
+        compose_value_summary(value)
+    )
+}
+
+/// A single line representation of a value for use in lists
+fn compose_value_summary(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => format!("\"{}\"", s),
+        JsonValue::Datetime(text) => text.clone(),
+        JsonValue::Boolean(b) => format!("{}", b),
+        JsonValue::Number(n) => format!("{}", n),
+        JsonValue::Null => "null".to_string(),
+        JsonValue::Array(_) | JsonValue::Object(_) => {
+            serde_json::to_string(value).unwrap_or_default()
+        }
+    }
+}
+
+fn render_delete_confirm_popup(frame: &mut Frame, message: String) {
     let popup_block = Block::default()
         .title(" Delete?")
         .borders(Borders::NONE)
@@ -145,17 +381,13 @@ fn render_delete_confirm_popup(frame: &mut Frame, target_delete_key: &str) {
 
     let panels = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(1), Constraint::Fill(1)])
+        .constraints([Constraint::Fill(1), Constraint::Length(1)])
         .margin(1)
         .split(area);
 
-    let [control_hint_panel] = Layout::vertical([Constraint::Length(1)])
-        .flex(Flex::Center)
-        .areas(panels[1]);
+    let control_hint_panel = panels[1];
 
-    let message_paragraph = Paragraph::new(format!(
-        "Are you sure you want to delete the key: \"{target_delete_key}\"?"
-    ));
+    let message_paragraph = Paragraph::new(message).wrap(Wrap { trim: false });
 
     let control_hint_text = Paragraph::new("(y/n)").centered();
 
@@ -164,6 +396,662 @@ fn render_delete_confirm_popup(frame: &mut Frame, target_delete_key: &str) {
     frame.render_widget(control_hint_text, control_hint_panel);
 }
 
+fn render_external_edit_error_popup(frame: &mut Frame, error: &str) {
+    let popup_block = Block::default()
+        .title(" Invalid JSON")
+        .borders(Borders::NONE)
+        .style(Style::default().bg(COLOR_SURFACE));
+
+    let area = compose_popup(
+        Constraint::Percentage(50),
+        Constraint::Percentage(30),
+        frame.area(),
+    );
+
+    let panels = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Fill(1), Constraint::Length(1)])
+        .margin(1)
+        .split(area);
+
+    let message_paragraph = Paragraph::new(format!(
+        "The edited text could not be applied: {error}\n\nWould you like to edit it again?"
+    ))
+    .wrap(Wrap { trim: false });
+
+    let control_hint_text = Paragraph::new("(y/n)").centered();
+
+    frame.render_widget(popup_block, area);
+    frame.render_widget(message_paragraph, panels[0]);
+    frame.render_widget(control_hint_text, panels[1]);
+}
+
+fn render_convert_popup(frame: &mut Frame, app: &mut App) {
+    let value_types = App::all_value_types();
+
+    let popup_block = Block::default()
+        .title(match app.convert_target_keys.as_deref() {
+            Some([key]) => format!(" Convert \"{key}\" to"),
+            Some(keys) => format!(" Convert {} values to", keys.len()),
+            None => String::new(),
+        })
+        .borders(Borders::NONE)
+        .style(Style::default().bg(COLOR_SURFACE));
+
+    let area = compose_popup(
+        Constraint::Length(64),
+        Constraint::Length(value_types.len() as u16 + 2),
+        frame.area(),
+    );
+
+    let [type_list_panel, preview_panel] =
+        Layout::horizontal([Constraint::Length(12), Constraint::Fill(1)])
+            .margin(1)
+            .areas(area);
+
+    let type_list_ui = List::new(value_types.iter().map(|value_type| {
+        Line::from(Span::styled(
+            format!(" {value_type} "),
+            Style::default().fg(COLOR_ACCENT),
+        ))
+    }))
+    .highlight_style(Style::default().bg(COLOR_ACCENT).fg(COLOR_SURFACE));
+
+    let preview_text = match app.convert_preview() {
+        Some(Ok(converted_pairs)) => match converted_pairs.as_slice() {
+            [(_, converted)] => Text::from(vec![
+                Line::from("Result:"),
+                Line::styled(
+                    compose_value_summary(converted),
+                    Style::default().fg(Color::Green),
+                ),
+            ]),
+            _ => Text::from(
+                std::iter::once(Line::from("Results:"))
+                    .chain(converted_pairs.iter().map(|(key, converted)| {
+                        Line::styled(
+                            format!("{key}: {}", compose_value_summary(converted)),
+                            Style::default().fg(Color::Green),
+                        )
+                    }))
+                    .collect::<Vec<_>>(),
+            ),
+        },
+        Some(Err(e)) => Text::styled(e.to_string(), Style::default().fg(Color::Red)),
+        None => Text::from(""),
+    };
+    let preview = Paragraph::new(preview_text).wrap(Wrap { trim: false });
+
+    frame.render_widget(popup_block, area);
+    frame.render_stateful_widget(
+        type_list_ui,
+        type_list_panel,
+        &mut app.convert_list_ui_state,
+    );
+    frame.render_widget(preview, preview_panel);
+}
+
+fn render_sort_popup(frame: &mut Frame, app: &mut App) {
+    let sort_orders = SortOrder::all();
+
+    let popup_block = Block::default()
+        .title(" Sort by")
+        .borders(Borders::NONE)
+        .style(Style::default().bg(COLOR_SURFACE));
+
+    let area = compose_popup(
+        Constraint::Length(34),
+        Constraint::Length(sort_orders.len() as u16 + 4),
+        frame.area(),
+    );
+
+    let [order_list_panel, recursive_panel] =
+        Layout::vertical([Constraint::Fill(1), Constraint::Length(1)])
+            .margin(1)
+            .areas(area);
+
+    let order_list_ui = List::new(sort_orders.iter().map(|order| {
+        Line::from(Span::styled(
+            format!(" {order} "),
+            Style::default().fg(COLOR_ACCENT),
+        ))
+    }))
+    .highlight_style(Style::default().bg(COLOR_ACCENT).fg(COLOR_SURFACE));
+
+    let recursive_text = Paragraph::new(format!(
+        " [{}] include nested objects",
+        if app.sort_recursive { "x" } else { " " }
+    ));
+
+    frame.render_widget(popup_block, area);
+    frame.render_stateful_widget(order_list_ui, order_list_panel, &mut app.sort_list_ui_state);
+    frame.render_widget(recursive_text, recursive_panel);
+}
+
+fn render_find_replace_popup(frame: &mut Frame, app: &mut App) {
+    let Some(find_replace) = &mut app.find_replace else {
+        return;
+    };
+
+    let popup_block = Block::default()
+        .title(" Find and replace")
+        .borders(Borders::NONE)
+        .style(Style::default().bg(COLOR_SURFACE));
+
+    let area = compose_popup(
+        Constraint::Percentage(80),
+        Constraint::Percentage(80),
+        frame.area(),
+    );
+
+    let [fields_panel, options_panel, error_panel, matches_panel] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Fill(1),
+    ])
+    .margin(1)
+    .areas(area);
+    let [find_panel, replace_panel] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .areas(fields_panel);
+
+    let mut find_block = Block::default().title("Find").borders(Borders::ALL);
+    let mut replace_block = Block::default().title("Replace").borders(Borders::ALL);
+
+    // the fields can only be typed into before the search has been run
+    if find_replace.matches.is_none() {
+        let active_style = Style::default().bg(COLOR_ACCENT).fg(Color::Black);
+        match find_replace.focus {
+            FindReplaceField::Find => find_block = find_block.style(active_style),
+            FindReplaceField::Replace => replace_block = replace_block.style(active_style),
+        }
+    }
+
+    let options_text = Paragraph::new(format!(
+        "Pattern: {} | Search: {} | Scope: {}",
+        if find_replace.use_regex {
+            "regex"
+        } else {
+            "literal"
+        },
+        find_replace.target,
+        find_replace.scope,
+    ))
+    .style(Style::default().fg(COLOR_ACCENT));
+
+    let error_text = Paragraph::new(find_replace.error.clone().unwrap_or_default())
+        .style(Style::default().fg(Color::Red));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup_block, area);
+    frame.render_widget(
+        Paragraph::new(find_replace.find_input.clone()).block(find_block),
+        find_panel,
+    );
+    frame.render_widget(
+        Paragraph::new(find_replace.replace_input.clone()).block(replace_block),
+        replace_panel,
+    );
+    frame.render_widget(options_text, options_panel);
+    frame.render_widget(error_text, error_panel);
+
+    let Some(matches) = &find_replace.matches else {
+        return;
+    };
+
+    let match_list_ui = List::new(matches.iter().map(|find_match| {
+        let decision = match find_match.accepted {
+            Some(true) => Span::styled("[replace] ", Style::default().fg(Color::Green)),
+            Some(false) => Span::styled("[skip]    ", Style::default().fg(Color::Red)),
+            None => Span::raw("          "),
+        };
+        let location = match find_match.location {
+            MatchLocation::Key => "key",
+            MatchLocation::Value => "value",
+        };
+
+        Line::from(vec![
+            decision,
+            Span::raw(format!("{} ({location}): ", display_path(&find_match.path))),
+            Span::styled(find_match.before.clone(), Style::default().fg(Color::Red)),
+            Span::raw(" -> "),
+            Span::styled(find_match.after.clone(), Style::default().fg(Color::Green)),
+        ])
+    }))
+    .block(Block::default().title(format!("{} matches", matches.len())))
+    .highlight_style(Style::default().bg(Color::Black));
+
+    frame.render_stateful_widget(
+        match_list_ui,
+        matches_panel,
+        &mut find_replace.match_list_ui_state,
+    );
+}
+
+fn render_copy_path_popup(frame: &mut Frame, app: &mut App) {
+    let Some(options) = &app.copy_path_options else {
+        return;
+    };
+
+    let popup_block = Block::default()
+        .title(" Copy path as")
+        .borders(Borders::NONE)
+        .style(Style::default().bg(COLOR_SURFACE));
+
+    let area = compose_popup(
+        Constraint::Percentage(60),
+        Constraint::Length(options.len() as u16 + 2),
+        frame.area(),
+    );
+
+    let option_list_ui = List::new(options.iter().map(|(format_name, path)| {
+        Line::from(vec![
+            Span::styled(
+                format!(" {format_name: <14}"),
+                Style::default().fg(COLOR_ACCENT),
+            ),
+            Span::raw(path.clone()),
+        ])
+    }))
+    .highlight_style(Style::default().bg(COLOR_ACCENT).fg(COLOR_SURFACE));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup_block, area);
+    frame.render_stateful_widget(
+        option_list_ui,
+        area.inner(Margin::new(0, 1)),
+        &mut app.copy_path_list_ui_state,
+    );
+}
+
+fn render_patch_export_popup(frame: &mut Frame, app: &App) {
+    let Some(export) = &app.patch_export else {
+        return;
+    };
+
+    let mut row_heights = vec![1, 3];
+    if export.error.is_some() {
+        row_heights.push(2);
+    }
+
+    let area = compose_popup(
+        Constraint::Length(60),
+        Constraint::Length(row_heights.iter().sum()),
+        frame.area(),
+    );
+
+    let vertical_panels = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            row_heights
+                .iter()
+                .map(|h| Constraint::Length(*h))
+                .collect::<Vec<_>>(),
+        )
+        .split(area);
+
+    let summary = Paragraph::new(match export.operation_count {
+        0 => " Nothing has changed since the file was loaded".to_string(),
+        1 => " Export 1 patch operation to".to_string(),
+        count => format!(" Export {count} patch operations to"),
+    });
+
+    let input_text = Paragraph::new(export.output_file.clone()).block(
+        Block::default()
+            .title("Patch File")
+            .borders(Borders::ALL)
+            .style(Style::default().bg(COLOR_ACCENT).fg(Color::Black)),
+    );
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Block::default().style(Style::default().bg(COLOR_SURFACE)),
+        area,
+    );
+    frame.render_widget(summary, vertical_panels[0]);
+    frame.render_widget(input_text, vertical_panels[1]);
+
+    if let Some(error) = &export.error {
+        let error_text = Paragraph::new(Text::styled(
+            format!(" {error}"),
+            Style::default().fg(Color::Red),
+        ))
+        .wrap(Wrap { trim: false });
+        frame.render_widget(error_text, vertical_panels[2]);
+    }
+}
+
+fn render_patch_apply_popup(frame: &mut Frame, app: &mut App) {
+    let Some(patch_apply) = &mut app.patch_apply else {
+        return;
+    };
+
+    let Some((_, changes)) = &patch_apply.preview else {
+        let mut row_heights = vec![1, 3];
+        if patch_apply.error.is_some() {
+            row_heights.push(3);
+        }
+
+        let area = compose_popup(
+            Constraint::Length(60),
+            Constraint::Length(row_heights.iter().sum()),
+            frame.area(),
+        );
+
+        let vertical_panels = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                row_heights
+                    .iter()
+                    .map(|h| Constraint::Length(*h))
+                    .collect::<Vec<_>>(),
+            )
+            .split(area);
+
+        let input_text = Paragraph::new(patch_apply.patch_file.clone()).block(
+            Block::default()
+                .title("Patch File")
+                .borders(Borders::ALL)
+                .style(Style::default().bg(COLOR_ACCENT).fg(Color::Black)),
+        );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Block::default().style(Style::default().bg(COLOR_SURFACE)),
+            area,
+        );
+        frame.render_widget(
+            Paragraph::new(" Apply a JSON Patch or JSON Merge Patch from"),
+            vertical_panels[0],
+        );
+        frame.render_widget(input_text, vertical_panels[1]);
+
+        if let Some(error) = &patch_apply.error {
+            let error_text = Paragraph::new(Text::styled(
+                format!(" {error}"),
+                Style::default().fg(Color::Red),
+            ))
+            .wrap(Wrap { trim: false });
+            frame.render_widget(error_text, vertical_panels[2]);
+        }
+        return;
+    };
+
+    let popup_block = Block::default()
+        .title(match changes.len() {
+            0 => " The patch makes no changes".to_string(),
+            1 => " The patch makes 1 change".to_string(),
+            count => format!(" The patch makes {count} changes"),
+        })
+        .borders(Borders::NONE)
+        .style(Style::default().bg(COLOR_SURFACE));
+
+    let area = compose_popup(
+        Constraint::Percentage(70),
+        Constraint::Length((changes.len() as u16).clamp(1, 15) + 2),
+        frame.area(),
+    );
+
+    let change_list_ui = List::new(changes.iter().map(|change| {
+        let value = match change {
+            PatchOperation::Add { value, .. }
+            | PatchOperation::Replace { value, .. }
+            | PatchOperation::Test { value, .. } => format!(": {}", compose_value_summary(value)),
+            _ => String::new(),
+        };
+        Line::from(vec![
+            Span::styled(
+                format!(" {: <8}", change.name()),
+                Style::default().fg(COLOR_ACCENT),
+            ),
+            Span::raw(match change {
+                PatchOperation::Move { from, path } => format!("{from} -> {path}"),
+                _ => change.path().to_string(),
+            }),
+            Span::styled(value, Style::default().fg(Color::Gray)),
+        ])
+    }))
+    .highlight_style(Style::default().bg(COLOR_ACCENT).fg(COLOR_SURFACE));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup_block, area);
+    frame.render_stateful_widget(
+        change_list_ui,
+        area.inner(Margin::new(0, 1)),
+        &mut patch_apply.preview_list_ui_state,
+    );
+}
+
+fn render_merge_conflict_popup(frame: &mut Frame, app: &App) {
+    let Some(merge) = &app.merge else {
+        return;
+    };
+    let Some(conflict) = merge.current_conflict() else {
+        return;
+    };
+
+    let popup_block = Block::default()
+        .title(format!(
+            " Conflict {} of {} at {}",
+            merge.current + 1,
+            merge.conflicts.len(),
+            display_path(&conflict.path)
+        ))
+        .borders(Borders::NONE)
+        .style(Style::default().bg(COLOR_SURFACE));
+
+    let area = compose_popup(
+        Constraint::Percentage(70),
+        Constraint::Length(5),
+        frame.area(),
+    );
+
+    let compose_version_line = |label: &str, value: &Option<JsonValue>| {
+        Line::from(vec![
+            Span::styled(format!(" {label: <8}"), Style::default().fg(COLOR_ACCENT)),
+            match value {
+                Some(value) => Span::raw(compose_value_summary(value)),
+                None => Span::styled("(removed)", Style::default().fg(Color::Gray)),
+            },
+        ])
+    };
+
+    let versions = Paragraph::new(vec![
+        compose_version_line("base", &conflict.base),
+        compose_version_line("ours", &conflict.ours),
+        compose_version_line("theirs", &conflict.theirs),
+    ]);
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup_block, area);
+    frame.render_widget(versions, area.inner(Margin::new(0, 1)));
+}
+
+fn render_diff_screen(frame: &mut Frame, app: &mut App, area: Rect) {
+    let Some(diff_view) = &mut app.diff_view else {
+        return;
+    };
+
+    let [summary_panel, panes_panel] =
+        Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(area);
+    let [left_panel, right_panel] =
+        Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(panes_panel);
+
+    let summary = match diff_view.difference_count() {
+        0 => " No differences".to_string(),
+        1 => " 1 key differs".to_string(),
+        count => format!(" {count} keys differ"),
+    };
+    frame.render_widget(
+        Paragraph::new(Span::styled(summary, Style::default().fg(Color::Blue))),
+        summary_panel,
+    );
+
+    for (side, panel) in [(DiffSide::Left, left_panel), (DiffSide::Right, right_panel)] {
+        let document = diff_view.document(side);
+        let mut pane_block = Block::default()
+            .title(format!(
+                "{}{}",
+                document.file,
+                if document.unsaved { " (unsaved)" } else { "" }
+            ))
+            .borders(Borders::ALL);
+        if diff_view.focus == side {
+            pane_block = pane_block.border_style(Style::default().fg(COLOR_ACCENT));
+        }
+
+        let rows_ui = List::new(diff_view.rows.iter().map(|row| {
+            let (marker, color) = match row.status() {
+                DiffStatus::Unchanged => ("  ", Color::Gray),
+                DiffStatus::Added => ("+ ", Color::Green),
+                DiffStatus::Removed => ("- ", Color::Red),
+                DiffStatus::Changed => ("~ ", Color::Cyan),
+            };
+            let text = row
+                .value(side)
+                .map(|value| compose_pair_text(marker, &row.key, value))
+                .unwrap_or_default();
+            ListItem::new(Span::styled(text, Style::default().fg(color)))
+        }))
+        .block(pane_block)
+        .highlight_style(Style::default().bg(COLOR_ACCENT).fg(Color::Black));
+
+        // both panes have the same rows, so they share a selection and scroll position
+        let mut row_list_ui_state = diff_view.row_list_ui_state.clone();
+        frame.render_stateful_widget(rows_ui, panel, &mut row_list_ui_state);
+        if side == DiffSide::Right {
+            diff_view.row_list_ui_state = row_list_ui_state;
+        }
+    }
+}
+
+fn render_records_screen(frame: &mut Frame, app: &mut App, area: Rect) {
+    let Some(records) = &mut app.records else {
+        return;
+    };
+
+    let block = Block::default()
+        .title(format!(
+            "{} ({} records){}",
+            records.file,
+            records.len(),
+            if records.unsaved { " (unsaved)" } else { "" }
+        ))
+        .borders(Borders::ALL);
+
+    if records.is_empty() {
+        frame.render_widget(
+            Paragraph::new(Span::styled(
+                " No records",
+                Style::default().fg(Color::Gray),
+            ))
+            .block(block),
+            area,
+        );
+        return;
+    }
+
+    // files can hold millions of records, so only the visible ones are read. At least
+    // one row is scrolled to, so the selection stays in view even with no room for it
+    let visible_rows = (block.inner(area).height as usize).max(1);
+    if let Some(selected) = records.selected {
+        if selected < records.scroll_offset {
+            records.scroll_offset = selected;
+        } else if selected >= records.scroll_offset + visible_rows {
+            records.scroll_offset = selected + 1 - visible_rows;
+        }
+    }
+    let visible_end = (records.scroll_offset + visible_rows).min(records.len());
+
+    let number_width = records.len().to_string().len();
+    let items: Vec<ListItem> = (records.scroll_offset..visible_end)
+        .map(|index| {
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!(" {:>number_width$}  ", index + 1),
+                    Style::default().fg(Color::Gray),
+                ),
+                Span::raw(records.summary(index)),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().bg(COLOR_ACCENT).fg(Color::Black));
+    let mut list_state = ListState::default().with_selected(
+        records
+            .selected
+            .map(|selected| selected - records.scroll_offset),
+    );
+    frame.render_stateful_widget(list, area, &mut list_state);
+
+    if records.delete_requested {
+        if let Some(selected) = records.selected {
+            render_delete_confirm_popup(
+                frame,
+                format!("Are you sure you want to delete record {}?", selected + 1),
+            );
+        }
+    }
+}
+
+fn render_query_screen(frame: &mut Frame, app: &mut App, area: Rect) {
+    let Some(query) = &mut app.query else {
+        return;
+    };
+
+    let [query_bar_panel, status_panel, results_panel] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(1),
+        Constraint::Fill(1),
+    ])
+    .areas(area);
+
+    let mut query_bar_block = Block::default()
+        .title("JSONPath, eg: $.services[*].ports")
+        .borders(Borders::ALL);
+    if !query.results_focused {
+        query_bar_block = query_bar_block.style(Style::default().bg(COLOR_ACCENT).fg(Color::Black));
+    }
+
+    let status_text = match &query.error {
+        Some(error) => Span::styled(format!(" {error}"), Style::default().fg(Color::Red)),
+        None => Span::styled(
+            format!(" {} results", query.results.len()),
+            Style::default().fg(Color::Green),
+        ),
+    };
+
+    let result_list_ui = List::new(query.results.iter().map(|result| {
+        Line::from(vec![
+            Span::styled(
+                format!("{}: ", to_json_path(&result.path)),
+                Style::default().fg(COLOR_ACCENT),
+            ),
+            Span::raw(compose_value_summary(&result.value)),
+        ])
+    }))
+    .block(Block::default().padding(Padding::horizontal(1)))
+    .highlight_style(if query.results_focused {
+        Style::default().bg(COLOR_ACCENT).fg(Color::Black)
+    } else {
+        Style::default().bg(COLOR_SURFACE)
+    });
+
+    frame.render_widget(
+        Paragraph::new(query.input.clone()).block(query_bar_block),
+        query_bar_panel,
+    );
+    frame.render_widget(Paragraph::new(status_text), status_panel);
+    frame.render_stateful_widget(
+        result_list_ui,
+        results_panel,
+        &mut query.result_list_ui_state,
+    );
+}
+
 fn render_editing_popup(frame: &mut Frame, app: &App) -> Result<(), io::Error> {
     let popup_block = Block::default()
         .title(" Enter a new key-value pair")
@@ -208,16 +1096,10 @@ fn render_editing_popup(frame: &mut Frame, app: &App) -> Result<(), io::Error> {
     let value_text = Paragraph::new(app.value_input.clone()).block(value_block);
     frame.render_widget(value_text, popup_panels[1]);
 
-    let type_text = Paragraph::new(match app.selected_value_type {
-        JsonValueType::String => "String",
-        JsonValueType::Boolean => "Boolean",
-        JsonValueType::Number => "Number",
-        JsonValueType::Null => "null",
-    })
-    .block(type_block);
+    let type_text = Paragraph::new(app.selected_value_type.to_string()).block(type_block);
     frame.render_widget(type_text, popup_vertical_panels[1]);
 
-    return Ok(());
+    Ok(())
 }
 
 fn render_type_selection_popup(frame: &mut Frame, app: &mut App) {
@@ -258,25 +1140,57 @@ fn render_type_selection_popup(frame: &mut Frame, app: &mut App) {
     );
 }
 
-fn compose_preview_screen(app: &App) -> Result<Paragraph, io::Error> {
-    match serde_json::to_string_pretty(&app.pairs) {
-        Ok(serialized) => {
-            let text = Paragraph::new(serialized);
-            return Ok(text);
-        }
-        Err(e) => {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Failed to serialize JSON data: {}", e),
-            ));
-        }
+fn render_text_editor(frame: &mut Frame, app: &mut App, area: Rect) {
+    let Some(editor) = &mut app.text_editor else {
+        return;
+    };
+
+    let title = if editor.escaped {
+        " JSON-escaped"
+    } else if app.raw_edit_target.is_some() {
+        " JSON source"
+    } else {
+        " Raw text"
+    };
+    let editor_block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(COLOR_ACCENT));
+
+    let inner_area = editor_block.inner(area);
+    let (rows, (cursor_row, cursor_col)) = editor.wrap(inner_area.width as usize);
+    editor.scroll_to_cursor(cursor_row, inner_area.height as usize);
+
+    let visible_rows = rows
+        .into_iter()
+        .skip(editor.scroll)
+        .take(inner_area.height as usize)
+        .map(Line::from)
+        .collect::<Vec<_>>();
+
+    frame.render_widget(Paragraph::new(visible_rows).block(editor_block), area);
+    frame.set_cursor_position((
+        inner_area.x + cursor_col as u16,
+        inner_area.y + (cursor_row - editor.scroll) as u16,
+    ));
+}
+
+/// Shows the document as it will be saved, or why it can't be saved in its format
+fn compose_preview_screen(app: &App) -> Paragraph<'_> {
+    match app.preview() {
+        Ok(serialized) => Paragraph::new(serialized),
+        Err(e) => Paragraph::new(format!("The document can't be saved: {e}"))
+            .style(Style::default().fg(Color::Red)),
     }
 }
 
 fn render_exit_popup(frame: &mut Frame, app: &App) {
     let popup_block = Block::default().style(Style::default().bg(COLOR_SURFACE));
 
-    let row_heights = [1, 3, 1];
+    let mut row_heights = vec![1, 3, 1, 1];
+    if app.exit_error.is_some() {
+        row_heights.push(2);
+    }
     let total_height = row_heights.iter().sum::<u16>();
 
     let area = compose_popup(
@@ -302,6 +1216,13 @@ fn render_exit_popup(frame: &mut Frame, app: &App) {
 
     let mut input_block = Block::default().title("Save To").borders(Borders::ALL);
 
+    // JSON Lines files are always saved as JSON Lines, so there is no format to pick
+    let mut format_text = match app.records {
+        Some(_) => Paragraph::new(format!("Format: {}", app.format)),
+        None => Paragraph::new(format!("Format: ‹ {} ›", app.format)),
+    }
+    .centered();
+
     let mut positive_button = Block::default();
     let mut negative_button = Block::default();
 
@@ -309,6 +1230,7 @@ fn render_exit_popup(frame: &mut Frame, app: &App) {
 
     match app.exit_popup_focus {
         Some(ExitFocus::Input) => input_block = input_block.style(active_style),
+        Some(ExitFocus::Format) => format_text = format_text.style(active_style),
         Some(ExitFocus::Positive) => positive_button = positive_button.style(active_style),
         Some(ExitFocus::Negative) => negative_button = negative_button.style(active_style),
         None => {}
@@ -323,11 +1245,8 @@ fn render_exit_popup(frame: &mut Frame, app: &App) {
         ])
         .split(vertical_panels[1]);
 
-    let input_text = Paragraph::new(match app.target_write_file.clone() {
-        None => String::from(""),
-        Some(path) => path,
-    })
-    .block(input_block);
+    let input_text =
+        Paragraph::new(app.target_write_file.clone().unwrap_or_default()).block(input_block);
 
     let positive_label = "save";
     let negative_label = "discard";
@@ -341,6 +1260,15 @@ fn render_exit_popup(frame: &mut Frame, app: &App) {
             Constraint::Length(positive_label.len() as u16 + 2),
             Constraint::Fill(2),
         ])
+        .split(vertical_panels[3]);
+
+    let format_row_panels = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Fill(1),
+            Constraint::Length(20),
+            Constraint::Fill(1),
+        ])
         .split(vertical_panels[2]);
 
     // the `trim: false` will stop the text from being cut off when over the edge of the block
@@ -356,8 +1284,18 @@ fn render_exit_popup(frame: &mut Frame, app: &App) {
     frame.render_widget(popup_block, area);
     frame.render_widget(message, vertical_panels[0]);
     frame.render_widget(input_text, middle_row_panels[1]);
+    frame.render_widget(format_text, format_row_panels[1]);
     frame.render_widget(negative_text, action_row_panels[1]);
     frame.render_widget(positive_text, action_row_panels[3]);
+
+    if let Some(exit_error) = &app.exit_error {
+        let error_text = Paragraph::new(Text::styled(
+            format!(" {exit_error}"),
+            Style::default().fg(Color::Red),
+        ))
+        .wrap(Wrap { trim: false });
+        frame.render_widget(error_text, vertical_panels[4]);
+    }
 }
 
 fn compose_popup(x_constraint: Constraint, y_constraint: Constraint, r: Rect) -> Rect {